@@ -32,9 +32,13 @@ async fn main() {
 								println!("Image Path: {}", path.display());
 							}
 						}
+						Body::EncodedImage { format, .. } => println!("Received {format:?} image"),
 						Body::FileList(files) => println!("Received files: {files:#?}"),
 						Body::Html(html) => println!("Received html: \n{html}"),
+						Body::Text { mime, content } => println!("Received {mime}: \n{content}"),
 						Body::Custom { .. } => {}
+						Body::Color { r, g, b, a } => println!("Received color: ({r}, {g}, {b}, {a})"),
+						Body::Multi(bodies) => println!("Received {} representations", bodies.len()),
 					};
 				}
 				Err(e) => eprintln!("Got an error: {e}"),
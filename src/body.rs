@@ -1,48 +1,566 @@
 use crate::*;
 
+use std::path::Path;
+
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_encode};
+
 /// The content extracted from the clipboard.
 ///
 /// To avoid extracting all types of content each time, only one of them is chosen, in the following order of priority:
 ///
 /// - Custom formats (in the order they are given, if present)
+/// - Color (macOS only)
 /// - Png Image
-/// - Raw Image (normalized to rgb8)
+/// - Raw Image (normalized to rgb8), or Encoded Image (see [`ImageMode`])
 /// - File list
+/// - Text formats (in the order they are given, if present)
 /// - HTML
 /// - Plain text
 ///
-/// When a clipboard item can fit more than one of these formats, only the one with the highest priority will be chosen.
+/// When a clipboard item can fit more than one of these formats, only the one with the highest priority will be chosen, unless the listener is configured to extract more than one representation (in which case a [`Body::Multi`] is emitted).
+///
+/// The images can be emitted in several representations, which can be handled as a single [`ClipboardImage`] with [`Body::to_image`].
+///
+/// With the `serde` feature, a body is serialized with the name of its variant in the `kind` field and its content in the `data` field, so that the variants with the same shape (such as [`Body::Html`] and [`Body::PlainText`]) are never mistaken for one another.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[cfg_attr(feature = "serde", serde(untagged))]
+// The variants are tagged, since several of them have the same shape (such as the ones that hold a string)
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "data"))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Body {
   /// HTML content.
   Html(String),
   /// Plaintext content.
   PlainText(String),
-  /// An raw image taken from the clipboard (in bmp or tiff format)
+  /// An raw image taken from the clipboard (in bmp, tiff, webp or gif format)
   /// and converted to raw rgb8 bytes.
+  ///
+  /// It can also be loaded from a copied image file, with [`load_image_from_single_file`](crate::ClipboardEventListenerBuilder::load_image_from_single_file).
   RawImage(RawImage),
   /// An image in png format.
   PngImage {
     bytes: Vec<u8>,
     path: Option<PathBuf>,
   },
+  /// An image in its original encoding, which is emitted without being decoded.
+  ///
+  /// This is only emitted when the listener uses [`ImageMode::Original`].
+  EncodedImage {
+    format: EncodedImageFormat,
+    bytes: Vec<u8>,
+    path: Option<PathBuf>,
+  },
   /// A list of files.
+  ///
+  /// Whether the files were cut or copied is reported in the [`file_operation`](crate::EventMetadata::file_operation) field of the event's metadata.
   FileList(Vec<PathBuf>),
   /// A custom format.
   Custom { name: Arc<str>, data: Vec<u8> },
+  /// Text in a specific format (such as `text/markdown`), decoded as UTF-8.
+  ///
+  /// This is only emitted for the formats that are given to [`with_text_formats`](crate::ClipboardEventListenerBuilder::with_text_formats) or [`with_text_custom_formats`](crate::ClipboardEventListenerBuilder::with_text_custom_formats).
+  Text { mime: Arc<str>, content: String },
+  /// A color, with its components in the sRGB color space.
+  ///
+  /// This is only emitted on macOS, for the colors copied from the system color picker (or from any other application that writes an `NSColor` to the pasteboard).
+  Color { r: u8, g: u8, b: u8, a: u8 },
+  /// Multiple representations of the same clipboard item, in order of priority.
+  ///
+  /// This is only emitted when the listener is configured to extract more than one format, such as with [`include_text_with_html`](crate::ClipboardEventListenerBuilder::include_text_with_html).
+  Multi(Vec<Self>),
+}
+
+// The characters that are kept as they are in the path of a `file://` uri
+const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'.')
+  .remove(b'_')
+  .remove(b'~')
+  .remove(b'/')
+  .remove(b':');
+
+// Converts an absolute path into a `file://` uri with an empty authority
+fn file_uri(path: &Path) -> String {
+  #[cfg(unix)]
+  let encoded = percent_encode(
+    std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()),
+    URI_PATH,
+  );
+
+  // Windows paths use forward slashes in uris, and the drive letter follows the root (as in `file:///C:/`)
+  #[cfg(not(unix))]
+  let path = {
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    if path.starts_with('/') {
+      path
+    } else {
+      format!("/{path}")
+    }
+  };
+
+  #[cfg(not(unix))]
+  let encoded = percent_encode(path.as_bytes(), URI_PATH);
+
+  format!("file://{encoded}")
+}
+
+/// The encoding of a [`Body::EncodedImage`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncodedImageFormat {
+  /// A device-independent bitmap, from the `CF_DIBV5` or `CF_DIB` formats on Windows (without the bmp file header).
+  Dib,
+  /// The TIFF data from the `public.tiff` type of the macOS pasteboard.
+  Tiff,
+}
+
+/// Defines how the images that are not in png format are emitted.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ImageMode {
+  /// The images are decoded and emitted as a [`Body::RawImage`].
+  #[default]
+  Decoded,
+  /// The images are emitted in their original encoding as a [`Body::EncodedImage`], without being decoded.
+  Original,
+}
+
+/// Defines the representation of the images that are emitted by the listener.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ImageOutput {
+  /// The images are emitted in the representation in which they are found, which can be either a [`Body::PngImage`] or a [`Body::RawImage`], depending on the platform and on the source application.
+  #[default]
+  Native,
+  /// The images are always emitted as a [`Body::PngImage`]. Raw images are encoded in png format.
+  Png,
+  /// The images are always emitted as a [`Body::RawImage`]. Png images are decoded.
+  Raw,
+}
+
+/// Defines how the images that are copied along with the path of their file are emitted.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ImagePathMode {
+  /// The image is emitted with its bytes, and the path of the file is attached to it (such as in the `path` of a [`Body::PngImage`]).
+  #[default]
+  PreferBytes,
+  /// The image is emitted as a [`Body::FileList`] with the path of its file, without reading its bytes from the clipboard.
+  PreferPath,
+}
+
+/// Defines how the directories in a [`Body::FileList`] are handled.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DirectoryHandling {
+  /// The directories are kept in the list as they are.
+  #[default]
+  Keep,
+  /// The directories are removed from the list.
+  Skip,
+  /// Each directory is replaced with the files that it directly contains (without recursing into its subdirectories). Directories that cannot be read are kept as they are.
+  ExpandShallow,
+}
+
+/// Defines how the formats that cannot be rendered are handled on Windows.
+///
+/// With delayed rendering, an application advertises its formats on the clipboard, but it only produces their data when they are requested. Reading such a format fails if the application is busy or has exited in the meantime.
+///
+/// The handling applies to every format that the listener reads, including the text, the html, the file list, the images and the custom formats.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DelayedRendering {
+  /// A [`ClipboardError::ReadError`] is emitted.
+  #[default]
+  Error,
+  /// The clipboard is closed, so that the application can access it while rendering, and the content is extracted once more after a short delay. An error is emitted if a format fails again.
+  Retry,
+  /// The format is treated as if it was not on the clipboard, so the next one in the order of priority is extracted instead.
+  Skip,
+}
+
+/// The layout of the pixels in a [`RawImage`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorType {
+  /// Three bytes per pixel, in red, green, blue order.
+  #[default]
+  Rgb8,
+  /// Four bytes per pixel, in red, green, blue, alpha order, with straight (non-premultiplied) alpha.
+  Rgba8,
+}
+
+impl ColorType {
+  /// Returns the number of bytes (one for each channel) that make up a single pixel.
+  #[must_use]
+  #[inline]
+  pub const fn channels(self) -> usize {
+    match self {
+      Self::Rgb8 => 3,
+      Self::Rgba8 => 4,
+    }
+  }
+}
+
+/// The kind of a [`Body`], without its content.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodyKind {
+  /// A [`Body::Html`].
+  Html,
+  /// A [`Body::PlainText`].
+  PlainText,
+  /// A [`Body::RawImage`]. Its size limit also applies to the images loaded from a single file.
+  RawImage,
+  /// A [`Body::PngImage`].
+  PngImage,
+  /// A [`Body::EncodedImage`].
+  EncodedImage,
+  /// A [`Body::FileList`]. Its size limit only applies on Linux, where it caps the size of the uri-list.
+  FileList,
+  /// A [`Body::Custom`]. Its size limit also applies to the formats read by the [`Gatekeeper`](crate::Gatekeeper).
+  Custom,
+  /// A [`Body::Text`], from one of the [`with_text_formats`](crate::ClipboardEventListenerBuilder::with_text_formats) or [`with_text_custom_formats`](crate::ClipboardEventListenerBuilder::with_text_custom_formats).
+  Text,
+  /// A [`Body::Color`].
+  Color,
+  /// A [`Body::Multi`], which holds several representations of the same clipboard item.
+  Multi,
+}
+
+/// Returns the [`BodyKind`]s that can be emitted on the current platform.
+///
+/// This can be used to adapt a generic interface to the capabilities of the build, such as by hiding the controls for the kinds of content that are never produced. Some kinds are only emitted when the listener is configured accordingly (for example, [`BodyKind::Custom`] requires custom formats).
+#[must_use]
+#[inline]
+pub const fn supported_kinds() -> &'static [BodyKind] {
+  #[cfg(windows)]
+  {
+    &[
+      BodyKind::Html,
+      BodyKind::PlainText,
+      BodyKind::RawImage,
+      BodyKind::PngImage,
+      BodyKind::EncodedImage,
+      BodyKind::FileList,
+      BodyKind::Custom,
+      BodyKind::Text,
+      BodyKind::Multi,
+    ]
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    &[
+      BodyKind::Html,
+      BodyKind::PlainText,
+      BodyKind::RawImage,
+      BodyKind::PngImage,
+      BodyKind::EncodedImage,
+      BodyKind::FileList,
+      BodyKind::Custom,
+      BodyKind::Text,
+      BodyKind::Color,
+      BodyKind::Multi,
+    ]
+  }
+
+  // Encoded images are only emitted for the original DIB bytes on Windows
+  // and the original TIFF bytes on macOS, and colors are only read from the
+  // macOS pasteboard
+  #[cfg(target_os = "linux")]
+  {
+    &[
+      BodyKind::Html,
+      BodyKind::PlainText,
+      BodyKind::RawImage,
+      BodyKind::PngImage,
+      BodyKind::FileList,
+      BodyKind::Custom,
+      BodyKind::Text,
+      BodyKind::Multi,
+    ]
+  }
 }
 
 impl Body {
+  /// Returns the [`BodyKind`] of this instance.
+  #[must_use]
+  pub const fn kind(&self) -> BodyKind {
+    match self {
+      Self::Html(_) => BodyKind::Html,
+      Self::PlainText(_) => BodyKind::PlainText,
+      Self::RawImage(_) => BodyKind::RawImage,
+      Self::PngImage { .. } => BodyKind::PngImage,
+      Self::EncodedImage { .. } => BodyKind::EncodedImage,
+      Self::FileList(_) => BodyKind::FileList,
+      Self::Custom { .. } => BodyKind::Custom,
+      Self::Text { .. } => BodyKind::Text,
+      Self::Color { .. } => BodyKind::Color,
+      Self::Multi(_) => BodyKind::Multi,
+    }
+  }
+
   /// Checks whether this instance contains an image.
   #[must_use]
   pub const fn is_image(&self) -> bool {
-    matches!(self, Self::RawImage(_) | Self::PngImage { .. })
+    matches!(
+      self,
+      Self::RawImage(_) | Self::PngImage { .. } | Self::EncodedImage { .. }
+    )
+  }
+
+  /// Returns the size of the content in bytes, which is defined as follows:
+  ///
+  /// - Text (including html): the length of the UTF-8 string.
+  /// - Images: the length of the bytes (which, for a [`RawImage`], is `width * height * channels`). The paths and the [`source_bytes`](RawImage::source_bytes) are not counted.
+  /// - File lists: the sum of the lengths of the paths.
+  /// - Custom formats: the length of the data.
+  /// - Colors: 4, one byte for each component.
+  /// - [`Body::Multi`]: the sum of the sizes of its representations.
+  #[must_use]
+  pub fn byte_size(&self) -> usize {
+    match self {
+      Self::Html(text) | Self::PlainText(text) | Self::Text { content: text, .. } => text.len(),
+      Self::RawImage(image) => image.bytes.len(),
+      Self::PngImage { bytes, .. }
+      | Self::EncodedImage { bytes, .. }
+      | Self::Custom { data: bytes, .. } => bytes.len(),
+      Self::FileList(files) => files.iter().map(|file| file.as_os_str().len()).sum(),
+      Self::Color { .. } => 4,
+      Self::Multi(bodies) => bodies.iter().map(Self::byte_size).sum(),
+    }
+  }
+
+  /// Checks whether this instance has no content, which is the case when its [`byte_size`](Body::byte_size) is 0.
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.byte_size() == 0
+  }
+
+  /// Returns the representation with the highest priority if this is a [`Body::Multi`], or this instance otherwise.
+  ///
+  /// The priority is the default one used by the listener (see [`Body`]): custom formats, color, png image, raw or encoded image, file list, text formats, html and plain text. When more than one representation has the same priority, the first one is returned.
+  ///
+  /// This can be used to handle a single representation in the same way, whether the listener extracts more than one or not.
+  #[must_use]
+  pub fn best(&self) -> &Self {
+    match self {
+      Self::Multi(bodies) => bodies
+        .iter()
+        .map(Self::best)
+        .min_by_key(|body| body.priority())
+        .unwrap_or(self),
+      _ => self,
+    }
+  }
+
+  /// Splits a [`Body::Multi`] into its representations, or returns a list with this instance as its only item otherwise.
+  #[must_use]
+  pub fn into_representations(self) -> Vec<Self> {
+    match self {
+      Self::Multi(bodies) => bodies,
+      body => vec![body],
+    }
+  }
+
+  /// Checks whether two bodies have the same content, ignoring where the content comes from.
+  ///
+  /// Unlike [`PartialEq`], this ignores the `path` of the images (as well as the [`source_bytes`](RawImage::source_bytes) of raw images), so the same image copied from two different files (such as temporary files) is considered equal. This can be used to deduplicate the content. All of the other kinds of content are compared in full.
+  #[must_use]
+  pub fn content_eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::RawImage(a), Self::RawImage(b)) => {
+        a.width == b.width
+          && a.height == b.height
+          && a.color_type == b.color_type
+          && a.frame_count == b.frame_count
+          && a.bytes == b.bytes
+      }
+      (Self::PngImage { bytes: a, .. }, Self::PngImage { bytes: b, .. }) => a == b,
+      (
+        Self::EncodedImage {
+          format: a_format,
+          bytes: a,
+          ..
+        },
+        Self::EncodedImage {
+          format: b_format,
+          bytes: b,
+          ..
+        },
+      ) => a_format == b_format && a == b,
+      (Self::Multi(a), Self::Multi(b)) => {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.content_eq(b))
+      }
+      (a, b) => a == b,
+    }
+  }
+
+  /// Converts the image in this body (if there is one) into an [`arboard::ImageData`].
+  ///
+  /// Png images are decoded in the process, and `None` is returned if the decoding fails.
+  ///
+  /// See [`RawImage::to_arboard_image`] for more information.
+  #[cfg(feature = "arboard")]
+  #[must_use]
+  pub fn to_arboard_image(&self) -> Option<arboard::ImageData<'static>> {
+    match self {
+      Self::RawImage(image) => Some(image.to_arboard_image()),
+      Self::PngImage { bytes, .. } => {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+          .ok()?
+          .into_rgba8();
+
+        Some(arboard::ImageData {
+          width: image.width() as usize,
+          height: image.height() as usize,
+          bytes: image.into_raw().into(),
+        })
+      }
+      _ => None,
+    }
+  }
+
+  /// If this is a [`Body::FileList`] with a single file that looks like an image (judging by its extension), it loads and decodes the file from disk.
+  ///
+  /// Returns `None` if this is not a single image file, or an error if the file cannot be read or decoded (for example, because its format is not supported on the current platform).
+  ///
+  /// Unlike [`load_image_from_single_file`](crate::ClipboardEventListenerBuilder::load_image_from_single_file), this keeps the disk read explicit at the call site.
+  #[must_use]
+  pub fn try_load_single_image(&self) -> Option<Result<RawImage, ClipboardError>> {
+    match self {
+      Self::FileList(files) if files.len() == 1 => {
+        let path = &files[0];
+
+        if image::ImageFormat::from_path(path).is_err() {
+          return None;
+        }
+
+        Some(decode_image_file(path))
+      }
+      _ => None,
+    }
+  }
+
+  /// Returns the `text/uri-list` representation of the files in this body, with one percent-encoded `file://` uri for each of them (terminated by `\r\n`).
+  ///
+  /// This is available for a [`Body::FileList`], or for an image that comes with the path of its file. Returns `None` for the other kinds of content, or if there are no files. The paths are expected to be absolute.
+  #[must_use]
+  pub fn to_uri_list(&self) -> Option<String> {
+    let files = self.file_paths().filter(|files| !files.is_empty())?;
+
+    Some(
+      files
+        .iter()
+        .map(|file| format!("{}\r\n", file_uri(file)))
+        .collect(),
+    )
+  }
+
+  // Converts the images to the representation selected with `ImageOutput`.
+  // When decoding png images, their bytes are kept as the source bytes if requested.
+  pub(crate) fn into_image_output(
+    self,
+    output: ImageOutput,
+    keep_source_bytes: bool,
+  ) -> Result<Self, ClipboardError> {
+    match (output, self) {
+      (ImageOutput::Png, Self::RawImage(image)) => Ok(Self::PngImage {
+        bytes: image.encode_png()?,
+        path: image.path,
+      }),
+      (ImageOutput::Raw, Self::PngImage { bytes, path }) => {
+        let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+          .map_err(|e| ClipboardError::read_with_context("Failed to load PNG image", e))?;
+
+        let mut image = RawImage::from_dynamic_image(image, path);
+        image.source_bytes = keep_source_bytes.then(|| bytes.into());
+
+        Ok(Self::RawImage(image))
+      }
+      (output, Self::Multi(bodies)) => bodies
+        .into_iter()
+        .map(|body| body.into_image_output(output, keep_source_bytes))
+        .collect::<Result<_, _>>()
+        .map(Self::Multi),
+      (_, body) => Ok(body),
+    }
+  }
+
+  // Returns the size of the content in bytes (the largest one for `Multi`), or None for file lists and colors
+  pub(crate) fn content_len(&self) -> Option<usize> {
+    match self {
+      Self::Html(text) | Self::PlainText(text) | Self::Text { content: text, .. } => Some(text.len()),
+      Self::RawImage(image) => Some(image.bytes.len()),
+      Self::PngImage { bytes, .. }
+      | Self::EncodedImage { bytes, .. }
+      | Self::Custom { data: bytes, .. } => Some(bytes.len()),
+      Self::FileList(_) | Self::Color { .. } => None,
+      Self::Multi(bodies) => bodies.iter().filter_map(Self::content_len).max(),
+    }
+  }
+
+  // Returns the name of the format for custom and text content, or the name of the kind otherwise
+  #[cfg(feature = "tracing")]
+  pub(crate) fn format_name(&self) -> std::borrow::Cow<'_, str> {
+    match self {
+      Self::Custom { name, .. } | Self::Text { mime: name, .. } => name.as_ref().into(),
+      _ => format!("{:?}", self.kind()).into(),
+    }
+  }
+
+  // Checks whether this is (or, for `Multi`, contains) the content of a custom or text format with the given name
+  pub(crate) fn has_format(&self, format: &str) -> bool {
+    match self {
+      Self::Custom { name, .. } | Self::Text { mime: name, .. } => name.as_ref() == format,
+      Self::Multi(bodies) => bodies.iter().any(|body| body.has_format(format)),
+      _ => false,
+    }
+  }
+
+  // Whether this is text that only describes the given files, which is the case if it is
+  // the path of a single file, or if its lines are the `file://` uris of the files, in order.
+  // The trailing line breaks are ignored.
+  pub(crate) fn is_text_of_files(&self, files: &[PathBuf]) -> bool {
+    let (Self::PlainText(text) | Self::Text { content: text, .. }) = self else {
+      return false;
+    };
+
+    let text = text.trim_end_matches(['\r', '\n']);
+
+    if text.is_empty() || files.is_empty() {
+      return false;
+    }
+
+    if let [file] = files
+      && file.as_os_str() == text
+    {
+      return true;
+    }
+
+    text.lines().eq(files.iter().map(|file| file_uri(file)))
+  }
+
+  // Returns the file paths for file lists, or the path for images that have one
+  pub(crate) fn file_paths(&self) -> Option<Vec<PathBuf>> {
+    match self {
+      Self::FileList(files) => Some(files.clone()),
+      Self::RawImage(RawImage {
+        path: Some(path), ..
+      })
+      | Self::PngImage {
+        path: Some(path), ..
+      }
+      | Self::EncodedImage {
+        path: Some(path), ..
+      } => Some(vec![path.clone()]),
+      _ => None,
+    }
   }
 
   pub(crate) fn new_png(bytes: Vec<u8>, path: Option<PathBuf>) -> Self {
-    if log::log_enabled!(log::Level::Debug) {
+    if debug_enabled!() {
       if let Some(path) = &path {
         debug!(
           "Found PNG image. Size: {}, Path: {}",
@@ -60,27 +578,44 @@ impl Body {
     Self::PngImage { bytes, path }
   }
 
-  #[cfg(not(target_os = "linux"))]
-  pub(crate) fn new_image(image: image::DynamicImage, path: Option<PathBuf>) -> Self {
-    let rgb = image.into_rgb8();
+  pub(crate) fn new_image(mut image: RawImage, source_bytes: Option<Arc<[u8]>>) -> Self {
+    image.source_bytes = source_bytes;
 
-    let (width, height) = rgb.dimensions();
-    let image = RawImage {
-      bytes: rgb.into_raw(),
-      path,
-      width,
-      height,
-    };
-
-    if log::log_enabled!(log::Level::Debug) {
+    if debug_enabled!() {
       image.log_info();
     }
 
     Self::RawImage(image)
   }
 
-  pub(crate) fn new_custom(name: Arc<str>, data: Vec<u8>) -> Self {
-    if log::log_enabled!(log::Level::Debug) {
+  #[cfg(any(windows, target_os = "macos"))]
+  pub(crate) fn new_encoded_image(
+    format: EncodedImageFormat,
+    bytes: Vec<u8>,
+    path: Option<PathBuf>,
+  ) -> Self {
+    if debug_enabled!() {
+      debug!(
+        "Found {format:?} image. Size: {}, Path: {:?}",
+        HumanBytes(bytes.len()),
+        path.as_deref().map(std::path::Path::display)
+      );
+    }
+
+    Self::EncodedImage {
+      format,
+      bytes,
+      path,
+    }
+  }
+
+  // Custom formats that are marked as text are decoded and emitted as `Text`
+  pub(crate) fn new_custom(name: Arc<str>, data: Vec<u8>, as_text: bool) -> Self {
+    if as_text {
+      return Self::new_text_with_mime(name, String::from_utf8_lossy(&data).into_owned());
+    }
+
+    if debug_enabled!() {
       debug!(
         "Found content with custom format `{name}`. Size: {}",
         HumanBytes(data.len())
@@ -90,8 +625,35 @@ impl Body {
     Self::Custom { name, data }
   }
 
+  // Emits the bytes of an image that could not be decoded, for debugging
+  pub(crate) fn new_undecoded(format: &str, data: Vec<u8>, error: &ClipboardError) -> Self {
+    warn!(
+      "Failed to decode the image in the `{format}` format, emitting its bytes instead: {error}"
+    );
+
+    Self::Custom {
+      name: format!("{format}-undecoded").into(),
+      data,
+    }
+  }
+
+  // Converts the components of a color, which range from 0 to 1
+  #[cfg(target_os = "macos")]
+  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+  pub(crate) fn new_color(r: f64, g: f64, b: f64, a: f64) -> Self {
+    let to_u8 = |component: f64| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    let (r, g, b, a) = (to_u8(r), to_u8(g), to_u8(b), to_u8(a));
+
+    if debug_enabled!() {
+      debug!("Found color. Components: ({r}, {g}, {b}, {a})");
+    }
+
+    Self::Color { r, g, b, a }
+  }
+
   pub(crate) fn new_file_list(files: Vec<PathBuf>) -> Self {
-    if log::log_enabled!(log::Level::Debug) {
+    if debug_enabled!() {
       debug!("Found file list with {} elements: {files:?}", files.len());
     }
 
@@ -99,15 +661,61 @@ impl Body {
   }
 
   pub(crate) fn new_html(html: String) -> Self {
-    if log::log_enabled!(log::Level::Debug) {
+    if debug_enabled!() {
       debug!("Found html content");
     }
 
     Self::Html(html)
   }
 
+  // Emits the html along with its plain text alternative, if there is one
+  pub(crate) fn new_html_with_text(html: String, text: Option<String>) -> Self {
+    match text {
+      Some(text) => {
+        if debug_enabled!() {
+          debug!("Found html content with a plain text alternative");
+        }
+
+        Self::Multi(vec![Self::Html(html), Self::PlainText(text)])
+      }
+      None => Self::new_html(html),
+    }
+  }
+
+  // The position of this kind of content in the default order of priority, from highest to lowest
+  const fn priority(&self) -> u8 {
+    match self {
+      Self::Custom { .. } => 0,
+      Self::Color { .. } => 1,
+      Self::PngImage { .. } => 2,
+      Self::RawImage(_) | Self::EncodedImage { .. } => 3,
+      Self::FileList(_) => 4,
+      Self::Text { .. } => 5,
+      Self::Html(_) => 6,
+      Self::PlainText(_) => 7,
+      Self::Multi(_) => 8,
+    }
+  }
+
+  // Combines the bodies into a single one, using `Multi` only when there is more than one
+  pub(crate) fn from_representations(mut bodies: Vec<Self>) -> Option<Self> {
+    match bodies.len() {
+      0 => None,
+      1 => bodies.pop(),
+      _ => Some(Self::Multi(bodies)),
+    }
+  }
+
+  pub(crate) fn new_text_with_mime(mime: Arc<str>, content: String) -> Self {
+    if debug_enabled!() {
+      debug!("Found text content with format `{mime}`");
+    }
+
+    Self::Text { mime, content }
+  }
+
   pub(crate) fn new_text(text: String) -> Self {
-    if log::log_enabled!(log::Level::Debug) {
+    if debug_enabled!() {
       debug!("Found text content");
     }
 
@@ -115,11 +723,13 @@ impl Body {
   }
 }
 
-/// An image from the clipboard, normalized to raw rgb8 bytes.
+/// An image from the clipboard, normalized to raw 8-bit pixels (see [`color_type`](RawImage::color_type)).
+///
+/// The length of the bytes is always `width * height * color_type().channels()`.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RawImage {
-  /// The rgb8 bytes of the image.
+  /// The bytes of the image, in the layout described by [`color_type`](RawImage::color_type).
   pub bytes: Vec<u8>,
   /// The width of the image
   pub width: u32,
@@ -127,16 +737,252 @@ pub struct RawImage {
   pub height: u32,
   /// The path to the image's file (if one can be detected).
   pub path: Option<PathBuf>,
+  /// The original bytes of the image, before it was decoded (such as the DIB bytes on Windows, the TIFF bytes on macOS, the WebP or GIF bytes on Linux, or the PNG bytes when using [`ImageOutput::Raw`]).
+  ///
+  /// This is only set when [`keep_source_bytes`](crate::ClipboardEventListenerBuilder::keep_source_bytes) is enabled.
+  pub source_bytes: Option<Arc<[u8]>>,
+  /// The number of frames in the original image, which is 1 for static images.
+  ///
+  /// Only the first frame is decoded, so a value above 1 signals an animated image (such as a GIF on Linux), whose animation can be read from the [`source_bytes`](RawImage::source_bytes).
+  #[cfg_attr(feature = "serde", serde(default = "single_frame"))]
+  pub frame_count: u32,
+  /// Whether the original image had an alpha channel, even if it was discarded when the image was converted to [`ColorType::Rgb8`].
+  ///
+  /// This can be used to warn the user that the transparency of the image was lost. On Windows, the alpha channel is only taken into account for the `CF_DIBV5` format, when its header declares an alpha mask and the pixels actually use it.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub had_alpha: bool,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub(crate) color_type: ColorType,
+}
+
+// The default frame count for the images that were serialized without one
+#[cfg(feature = "serde")]
+pub(crate) const fn single_frame() -> u32 {
+  1
+}
+
+/// Creates a [`ColorType::Rgba8`] image, assuming that the pixels have straight alpha.
+impl From<image::RgbaImage> for RawImage {
+  fn from(image: image::RgbaImage) -> Self {
+    let (width, height) = image.dimensions();
+
+    Self {
+      bytes: image.into_raw(),
+      width,
+      height,
+      path: None,
+      source_bytes: None,
+      frame_count: 1,
+      had_alpha: true,
+      color_type: ColorType::Rgba8,
+    }
+  }
 }
 
 impl RawImage {
+  /// Creates an image from raw 8-bit pixels in the given layout, such as the ones of another image library.
+  ///
+  /// The image has no path and no source bytes, and [`had_alpha`](RawImage::had_alpha) is set for [`ColorType::Rgba8`]. Returns `None` if the length of the bytes is not `width * height * color_type.channels()`.
+  #[must_use]
+  pub fn new(bytes: Vec<u8>, width: u32, height: u32, color_type: ColorType) -> Option<Self> {
+    let expected_len = (width as usize)
+      .checked_mul(height as usize)?
+      .checked_mul(color_type.channels())?;
+
+    if bytes.len() != expected_len {
+      return None;
+    }
+
+    Some(Self {
+      bytes,
+      width,
+      height,
+      path: None,
+      source_bytes: None,
+      frame_count: 1,
+      had_alpha: color_type == ColorType::Rgba8,
+      color_type,
+    })
+  }
+
+  pub(crate) fn from_dynamic_image(image: image::DynamicImage, path: Option<PathBuf>) -> Self {
+    let had_alpha = image.color().has_alpha();
+    let rgb = image.into_rgb8();
+
+    let (width, height) = rgb.dimensions();
+
+    Self {
+      bytes: rgb.into_raw(),
+      path,
+      width,
+      height,
+      source_bytes: None,
+      frame_count: 1,
+      had_alpha,
+      color_type: ColorType::Rgb8,
+    }
+  }
+
+  // Encodes the image in png format
+  pub(crate) fn encode_png(&self) -> Result<Vec<u8>, ClipboardError> {
+    let color_type = match self.color_type {
+      ColorType::Rgb8 => image::ExtendedColorType::Rgb8,
+      ColorType::Rgba8 => image::ExtendedColorType::Rgba8,
+    };
+
+    let mut bytes = Vec::new();
+
+    image::ImageEncoder::write_image(
+      image::codecs::png::PngEncoder::new(&mut bytes),
+      &self.bytes,
+      self.width,
+      self.height,
+      color_type,
+    )
+    .map_err(|e| ClipboardError::read_with_context("Failed to encode the image as png", e))?;
+
+    Ok(bytes)
+  }
+
+  /// Converts the pixels from premultiplied alpha to straight (un-premultiplied) alpha, which is the form used by the images emitted by the listener.
+  ///
+  /// Fully transparent pixels become black. This has no effect on images without an alpha channel.
+  ///
+  /// The listener never converts the alpha on its own, since the formats do not declare premultiplied alpha (for example, the alpha of a `CF_DIBV5` image on Windows is straight alpha, by definition). This can be used for the sources that are known to write premultiplied alpha regardless.
+  // The result is clamped to 255
+  #[allow(clippy::cast_possible_truncation)]
+  pub fn unmultiply_alpha(&mut self) {
+    if self.color_type != ColorType::Rgba8 {
+      return;
+    }
+
+    for pixel in self.bytes.chunks_exact_mut(4) {
+      let alpha = u16::from(pixel[3]);
+
+      for channel in &mut pixel[..3] {
+        *channel = match alpha {
+          0 => 0,
+          // Rounded, and clamped for invalid premultiplied values that exceed the alpha
+          _ => ((u16::from(*channel) * 255 + alpha / 2) / alpha).min(255) as u8,
+        };
+      }
+    }
+  }
+
+  /// Converts the pixels from straight alpha to premultiplied alpha, for consumers that need this form (such as some compositing APIs).
+  ///
+  /// This has no effect on images without an alpha channel.
+  // The result of the division can never exceed 255
+  #[allow(clippy::cast_possible_truncation)]
+  pub fn premultiply_alpha(&mut self) {
+    if self.color_type != ColorType::Rgba8 {
+      return;
+    }
+
+    for pixel in self.bytes.chunks_exact_mut(4) {
+      let alpha = u16::from(pixel[3]);
+
+      for channel in &mut pixel[..3] {
+        *channel = ((u16::from(*channel) * alpha + 127) / 255) as u8;
+      }
+    }
+  }
+
+  /// Returns a score from 0 to 1 that measures how similar this image is to another one, where 1 means that their pixels are identical. Images with different dimensions always have a score of 0.
+  ///
+  /// The score is 1 minus the mean absolute difference of the color channels, normalized to the range of a channel. The alpha channel is only compared when both images have one. This makes it tolerant of small differences (such as a blinking cursor or compression artifacts), so it can be used with a threshold to deduplicate screenshots.
+  ///
+  /// Every pixel is compared, so the cost is linear in the size of the image (which can amount to tens of millions of channels for a high-resolution screenshot). Comparing the dimensions first is cheap, so images of different sizes are rejected immediately.
+  // The score is always between 0 and 1
+  #[must_use]
+  #[allow(clippy::cast_possible_truncation)]
+  pub fn similarity(&self, other: &Self) -> f32 {
+    if self.width != other.width || self.height != other.height {
+      return 0.0;
+    }
+
+    let compared_channels =
+      if self.color_type == ColorType::Rgba8 && other.color_type == ColorType::Rgba8 {
+        4
+      } else {
+        3
+      };
+
+    let difference: u64 = self
+      .bytes
+      .chunks_exact(self.color_type.channels())
+      .zip(other.bytes.chunks_exact(other.color_type.channels()))
+      .flat_map(|(a, b)| a[..compared_channels].iter().zip(&b[..compared_channels]))
+      .map(|(a, b)| u64::from(a.abs_diff(*b)))
+      .sum();
+
+    let pixels = u64::from(self.width) * u64::from(self.height);
+    let max_difference = pixels * compared_channels as u64 * 255;
+
+    if max_difference == 0 {
+      return 1.0;
+    }
+
+    (1.0 - difference as f64 / max_difference as f64) as f32
+  }
+
+  /// Returns the layout of the pixels in [`bytes`](RawImage::bytes).
+  ///
+  /// This can be used to reconstruct the image unambiguously, for example with [`image::RgbImage::from_raw`] for [`ColorType::Rgb8`], or [`image::RgbaImage::from_raw`] for [`ColorType::Rgba8`].
+  #[must_use]
+  #[inline]
+  pub const fn color_type(&self) -> ColorType {
+    self.color_type
+  }
+
   /// Checks whether the clipboard has a file path attached to it.
   #[must_use]
   pub const fn has_path(&self) -> bool {
     self.path.is_some()
   }
 
-  #[cfg(not(target_os = "linux"))]
+  /// Converts this image into an [`arboard::ImageData`], so that it can be written back to the clipboard with `arboard`.
+  ///
+  /// Rgb8 bytes are converted to the rgba8 layout expected by `arboard`, with a fully opaque alpha channel.
+  ///
+  /// # Example
+  ///
+  /// ```rust,no_run
+  /// use clipboard_watcher::{Body, ClipboardEventListener};
+  /// use futures::StreamExt;
+  ///
+  /// # futures::executor::block_on(async {
+  /// let mut listener = ClipboardEventListener::spawn().unwrap();
+  /// let mut stream = listener.new_stream(16);
+  ///
+  /// while let Some(Ok(body)) = stream.next().await {
+  ///   if let Body::RawImage(image) = body.as_ref() {
+  ///     let mut clipboard = arboard::Clipboard::new().unwrap();
+  ///
+  ///     clipboard.set_image(image.to_arboard_image()).unwrap();
+  ///   }
+  /// }
+  /// # });
+  /// ```
+  #[cfg(feature = "arboard")]
+  #[must_use]
+  pub fn to_arboard_image(&self) -> arboard::ImageData<'static> {
+    let bytes: Vec<u8> = match self.color_type {
+      ColorType::Rgb8 => self
+        .bytes
+        .chunks_exact(3)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+        .collect(),
+      ColorType::Rgba8 => self.bytes.clone(),
+    };
+
+    arboard::ImageData {
+      width: self.width as usize,
+      height: self.height as usize,
+      bytes: bytes.into(),
+    }
+  }
+
   pub(crate) fn log_info(&self) {
     if let Some(path) = &self.path {
       debug!(
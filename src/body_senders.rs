@@ -1,23 +1,143 @@
+use std::{
+  fmt::Debug,
+  sync::{
+    Weak,
+    mpsc::{SyncSender, TrySendError},
+  },
+};
+
 use crate::*;
 
+// The callback registered with `on_change`
+pub(crate) struct ChangeCallback(pub(crate) Box<dyn FnMut(ClipboardResult) + Send>);
+
+// The maximum number of events waiting to be delivered by the dispatcher thread
+const DISPATCHER_CAPACITY: usize = 64;
+
+impl Debug for ChangeCallback {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("ChangeCallback")
+  }
+}
+
 // A wrapper for a mutex of HashMap that contains all of the registered receivers
 // for a given listener.
 #[derive(Debug)]
 pub(crate) struct BodySenders {
-  senders: Mutex<HashMap<StreamId, Sender<ClipboardResult>>>,
+  senders: Mutex<HashMap<StreamId, EventSender>>,
+  // The senders for the change ticks, which do not carry any content
+  tick_senders: Mutex<HashMap<StreamId, EventSender<()>>>,
+  // The senders for the changes rejected by the gatekeeper
+  rejection_senders: Mutex<HashMap<StreamId, EventSender<RejectedChange>>>,
+  // The most recent bodies, from oldest to newest
+  history: Mutex<VecDeque<Arc<Body>>>,
+  // The last event that was sent, which the events extracted on demand are compared with.
+  // It is updated when the event is handed over to the dispatcher, rather than when it is delivered.
+  last_event: Mutex<Option<ClipboardEvent>>,
+  // The last event that was delivered, which is replayed to the new streams that request it.
+  // It is only updated while holding the lock of the senders, so that it is never replayed twice.
+  last_delivered: Mutex<Option<ClipboardEvent>>,
+  history_capacity: usize,
+  on_change: Mutex<Option<ChangeCallback>>,
+  // Hands the events over to the dispatcher thread, if enabled
+  dispatcher: Option<SyncSender<EventResult>>,
+  // When set, the events and the ticks are dropped
+  muted: AtomicBool,
+  // Set by `refresh`, and consumed by the observer at its next poll
+  refresh_requested: AtomicBool,
+  // Shared by the channels of all of the content streams
+  memory_budget: Option<Arc<MemoryBudget>>,
+  // Updated when a content stream is registered, if there is a warmup
+  subscription_time: Option<SubscriptionTime>,
 }
 
 impl BodySenders {
-  pub(crate) fn new() -> Self {
-    Self {
+  pub(crate) fn new(
+    history_capacity: usize,
+    on_change: Option<ChangeCallback>,
+    dedicated_dispatcher: bool,
+    memory_budget: Option<u64>,
+    subscription_time: Option<SubscriptionTime>,
+  ) -> Arc<Self> {
+    Arc::new_cyclic(|weak| Self {
       senders: Mutex::default(),
+      tick_senders: Mutex::default(),
+      rejection_senders: Mutex::default(),
+      history: Mutex::new(VecDeque::with_capacity(history_capacity)),
+      last_event: Mutex::default(),
+      last_delivered: Mutex::default(),
+      history_capacity,
+      on_change: Mutex::new(on_change),
+      dispatcher: dedicated_dispatcher
+        .then(|| spawn_dispatcher(weak.clone()))
+        .flatten(),
+      muted: AtomicBool::new(false),
+      refresh_requested: AtomicBool::new(false),
+      memory_budget: memory_budget.map(MemoryBudget::new),
+      subscription_time,
+    })
+  }
+
+  // Prepares an event for the buffers of the content streams, counting it against the memory budget
+  fn buffered(&self, result: EventResult) -> Buffered<EventResult> {
+    match &self.memory_budget {
+      Some(budget) => budget.charge(result),
+      None => Buffered::Item(result),
     }
   }
 
-  /// Register Sender that was specified [`StreamId`].
-  pub(crate) fn register(&self, id: StreamId, tx: Sender<ClipboardResult>) {
+  pub(crate) fn mute(&self) {
+    self.muted.store(true, Ordering::Relaxed);
+  }
+
+  // Returns whether the listener was muted, so that concurrent calls only resume it once
+  pub(crate) fn unmute(&self) -> bool {
+    self.muted.swap(false, Ordering::Relaxed)
+  }
+
+  pub(crate) fn is_muted(&self) -> bool {
+    self.muted.load(Ordering::Relaxed)
+  }
+
+  pub(crate) fn request_refresh(&self) {
+    self.refresh_requested.store(true, Ordering::Relaxed);
+  }
+
+  // Returns whether a refresh was requested, and clears the request
+  pub(crate) fn take_refresh_request(&self) -> bool {
+    self.refresh_requested.swap(false, Ordering::Relaxed)
+  }
+
+  /// Register Sender with a new [`StreamId`], which is returned.
+  pub(crate) fn register(&self, tx: EventSender) -> StreamId {
     let mut guard = self.senders.lock().unwrap();
-    guard.insert(id, tx);
+    let id = StreamId::insert_new(&mut guard, tx);
+
+    self.record_subscription();
+
+    id
+  }
+
+  /// Register Sender with a new [`StreamId`], after sending it the last delivered event (if there is one).
+  pub(crate) fn register_with_last(&self, mut tx: EventSender) -> StreamId {
+    let mut guard = self.senders.lock().unwrap();
+
+    if let Some(event) = self.last_delivered.lock().unwrap().clone() {
+      let _ = tx.try_send_buffered(self.buffered(Ok(event)));
+    }
+
+    let id = StreamId::insert_new(&mut guard, tx);
+
+    self.record_subscription();
+
+    id
+  }
+
+  // Starts the warmup of the polling interval, if there is one
+  fn record_subscription(&self) {
+    if let Some(time) = &self.subscription_time {
+      time.record();
+    }
   }
 
   /// Close channel and unregister sender that was specified [`StreamId`]
@@ -26,14 +146,197 @@ impl BodySenders {
     guard.remove(id);
   }
 
-  pub(crate) fn send_all(&self, result: &ClipboardResult) {
+  /// Drops all of the registered senders, which closes their channels.
+  pub(crate) fn unregister_all(&self) {
+    let mut guard = self.senders.lock().unwrap();
+    guard.clear();
+    drop(guard);
+
+    self.tick_senders.lock().unwrap().clear();
+    self.rejection_senders.lock().unwrap().clear();
+  }
+
+  /// Register the sender of a change ticks stream with a new [`StreamId`], which is returned.
+  pub(crate) fn register_ticks(&self, tx: EventSender<()>) -> StreamId {
+    let mut guard = self.tick_senders.lock().unwrap();
+    StreamId::insert_new(&mut guard, tx)
+  }
+
+  /// Close channel and unregister the change ticks sender with the specified [`StreamId`].
+  pub(crate) fn unregister_ticks(&self, id: &StreamId) {
+    let mut guard = self.tick_senders.lock().unwrap();
+    guard.remove(id);
+  }
+
+  /// Register the sender of a rejected changes stream with a new [`StreamId`], which is returned.
+  pub(crate) fn register_rejections(&self, tx: EventSender<RejectedChange>) -> StreamId {
+    let mut guard = self.rejection_senders.lock().unwrap();
+    StreamId::insert_new(&mut guard, tx)
+  }
+
+  /// Close channel and unregister the rejected changes sender with the specified [`StreamId`].
+  pub(crate) fn unregister_rejections(&self, id: &StreamId) {
+    let mut guard = self.rejection_senders.lock().unwrap();
+    guard.remove(id);
+  }
+
+  // Used to skip building the rejected change when nobody is listening for it
+  pub(crate) fn has_rejection_streams(&self) -> bool {
+    !self.rejection_senders.lock().unwrap().is_empty()
+  }
+
+  // Notifies the rejected changes streams that the gatekeeper rejected a change
+  pub(crate) fn reject_all(&self, change: &RejectedChange) {
+    if self.is_muted() {
+      return;
+    }
+
+    let mut senders = self.rejection_senders.lock().unwrap();
+
+    for sender in senders.values_mut() {
+      if let Err(e) = sender.try_send(change.clone()) {
+        error!("Failed to send the rejected change: {e}");
+      }
+    }
+  }
+
+  // Notifies the change ticks streams that a change was detected.
+  // If a tick is still buffered, the new one is simply merged with it.
+  pub(crate) fn tick_all(&self) {
+    if self.is_muted() {
+      return;
+    }
+
+    let mut senders = self.tick_senders.lock().unwrap();
+
+    for sender in senders.values_mut() {
+      let _ = sender.try_send(());
+    }
+  }
+
+  /// Returns the bodies in the history, from oldest to newest.
+  pub(crate) fn recent(&self) -> Vec<Arc<Body>> {
+    let guard = self.history.lock().unwrap();
+    guard.iter().cloned().collect()
+  }
+
+  // Delivers the event, or hands it over to the dispatcher thread
+  pub(crate) fn send_all(&self, result: &EventResult) {
+    if self.is_muted() {
+      trace!("The listener is muted. Dropping the event...");
+      return;
+    }
+
+    match &self.dispatcher {
+      Some(dispatcher) => match dispatcher.try_send(result.clone()) {
+        Ok(()) => self.record_last(result),
+        Err(TrySendError::Full(_)) => {
+          warn!("The dispatcher thread is lagging behind. Dropping the event...");
+
+          for sender in self.senders.lock().unwrap().values() {
+            sender.record_dropped();
+          }
+        }
+        // Can only happen if the dispatcher thread panicked
+        Err(TrySendError::Disconnected(_)) => {
+          error!("The dispatcher thread stopped, delivering the event directly");
+          self.record_last(result);
+          self.deliver(result);
+        }
+      },
+      None => {
+        self.record_last(result);
+        self.deliver(result);
+      }
+    }
+  }
+
+  fn record_last(&self, result: &EventResult) {
+    if let Ok(event) = result {
+      *self.last_event.lock().unwrap() = Some(event.clone());
+    }
+  }
+
+  // Delivers an event (such as one that was extracted on demand), unless it has the same content as the last one
+  pub(crate) fn send_if_changed(&self, event: ClipboardEvent) {
+    let unchanged = self
+      .last_event
+      .lock()
+      .unwrap()
+      .as_ref()
+      .is_some_and(|last| last.body.content_eq(&event.body));
+
+    if unchanged {
+      trace!("The content is the same as in the last event. Skipping it...");
+      return;
+    }
+
+    self.send_all(&Ok(event));
+  }
+
+  fn deliver(&self, result: &EventResult) {
+    if self.history_capacity > 0
+      && let Ok(event) = result
+    {
+      let mut history = self.history.lock().unwrap();
+
+      if history.len() == self.history_capacity {
+        history.pop_front();
+      }
+
+      history.push_back(event.body.clone());
+    }
+
     let mut senders = self.senders.lock().unwrap();
 
+    if let Ok(event) = result {
+      *self.last_delivered.lock().unwrap() = Some(event.clone());
+    }
+
+    let item = self.buffered(result.clone());
+
     for sender in senders.values_mut() {
-      match sender.try_send(result.clone()) {
+      match sender.try_send_buffered(item.clone()) {
         Ok(()) => {}
         Err(e) => error!("Failed to send the clipboard data: {e}"),
       };
     }
+
+    drop(item);
+    drop(senders);
+
+    // The callback runs after the streams have received the event, without holding their lock.
+    // If a previous call panicked, the mutex is poisoned and the callback is skipped.
+    if let Ok(mut on_change) = self.on_change.lock()
+      && let Some(callback) = on_change.as_mut()
+    {
+      (callback.0)(result.clone().map(|event| event.body));
+    }
+  }
+}
+
+// Spawns the thread that delivers the events to the streams and to the callback.
+// It only holds a weak reference, and it stops when the senders are dropped (which closes the channel).
+fn spawn_dispatcher(body_senders: Weak<BodySenders>) -> Option<SyncSender<EventResult>> {
+  let (tx, rx) = std::sync::mpsc::sync_channel::<EventResult>(DISPATCHER_CAPACITY);
+
+  let spawned = std::thread::Builder::new()
+    .name("clipboard-dispatcher".to_string())
+    .spawn(move || {
+      for result in rx {
+        let Some(body_senders) = body_senders.upgrade() else {
+          break;
+        };
+
+        body_senders.deliver(&result);
+      }
+    });
+
+  match spawned {
+    Ok(_) => Some(tx),
+    Err(e) => {
+      warn!("Failed to spawn the dispatcher thread, delivering the events directly: {e}");
+      None
+    }
   }
 }
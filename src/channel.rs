@@ -0,0 +1,293 @@
+// The channel used to deliver events to the streams.
+// The implementation is selected with the `futures-channel` (default) and `tokio-channel` features.
+// If both are enabled, `tokio-channel` takes precedence.
+
+use std::sync::Weak;
+
+use crate::*;
+
+#[cfg(not(feature = "tokio-channel"))]
+use futures::channel::mpsc;
+#[cfg(feature = "tokio-channel")]
+use tokio::sync::mpsc;
+
+// The number of events that were dropped because the buffer of the stream was full,
+// or because they were evicted by the memory budget.
+// It is shared between the two ends of the channel.
+type DroppedCount = Arc<AtomicU64>;
+
+// The approximate number of bytes buffered by the content streams of a listener, and the maximum allowed.
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+  limit: u64,
+  used: AtomicU64,
+  // The events counted against the budget, from oldest to newest.
+  // The ones that already left all of the buffers are discarded when they reach the front.
+  charged: Mutex<VecDeque<Weak<Budgeted<EventResult>>>>,
+}
+
+impl MemoryBudget {
+  pub(crate) fn new(limit: u64) -> Arc<Self> {
+    Arc::new(Self {
+      limit,
+      used: AtomicU64::new(0),
+      charged: Mutex::default(),
+    })
+  }
+
+  // Counts a new event against the budget, evicting the oldest events from all of the streams until it fits.
+  // The new event itself is always kept, so that a single event larger than the budget is not blocked forever.
+  pub(crate) fn charge(self: &Arc<Self>, result: EventResult) -> Buffered<EventResult> {
+    let size = result
+      .as_ref()
+      .map_or(0, |event| event.body.byte_size() as u64);
+
+    let budgeted = Arc::new(Budgeted {
+      state: Mutex::new(BudgetedState {
+        item: Some(result),
+        holders: Vec::new(),
+      }),
+      size,
+      budget: self.clone(),
+    });
+
+    let mut charged = self.charged.lock().unwrap();
+
+    self.used.fetch_add(size, Ordering::Relaxed);
+
+    while let Some(oldest) = charged.front() {
+      if oldest.strong_count() == 0 {
+        charged.pop_front();
+      } else if self.used.load(Ordering::Relaxed) > self.limit {
+        if let Some(oldest) = charged
+          .pop_front()
+          .and_then(|oldest| oldest.upgrade())
+        {
+          oldest.evict();
+        }
+      } else {
+        break;
+      }
+    }
+
+    charged.push_back(Arc::downgrade(&budgeted));
+
+    Buffered::Budgeted(budgeted)
+  }
+}
+
+// An event counted against the memory budget.
+// It is shared by all of the streams that buffer it, and its bytes are returned to the budget
+// when it leaves the last buffer, or when it is evicted.
+#[derive(Debug)]
+pub(crate) struct Budgeted<T> {
+  state: Mutex<BudgetedState<T>>,
+  size: u64,
+  budget: Arc<MemoryBudget>,
+}
+
+#[derive(Debug)]
+struct BudgetedState<T> {
+  // Taken when the item is evicted
+  item: Option<T>,
+  // The dropped counts of the streams that still buffer the item
+  holders: Vec<DroppedCount>,
+}
+
+impl<T> Budgeted<T> {
+  // Discards the item for all of the streams that still buffer it
+  fn evict(&self) {
+    let mut state = self.state.lock().unwrap();
+
+    if state.item.take().is_some() {
+      self
+        .budget
+        .used
+        .fetch_sub(self.size, Ordering::Relaxed);
+
+      for dropped in state.holders.drain(..) {
+        dropped.fetch_add(1, Ordering::Relaxed);
+      }
+    }
+  }
+
+  fn add_holder(&self, dropped: &DroppedCount) {
+    self
+      .state
+      .lock()
+      .unwrap()
+      .holders
+      .push(dropped.clone());
+  }
+
+  // Returns false if the stream no longer held the item, because it was evicted
+  fn remove_holder(state: &mut BudgetedState<T>, dropped: &DroppedCount) -> bool {
+    let len = state.holders.len();
+
+    state
+      .holders
+      .retain(|holder| !Arc::ptr_eq(holder, dropped));
+
+    state.holders.len() != len
+  }
+}
+
+impl<T: Clone> Budgeted<T> {
+  // Returns a copy of the item for a stream that received it, unless it was evicted
+  fn take_for(&self, dropped: &DroppedCount) -> Option<T> {
+    let mut state = self.state.lock().unwrap();
+
+    Self::remove_holder(&mut state, dropped);
+
+    state.item.clone()
+  }
+}
+
+impl<T> Drop for Budgeted<T> {
+  fn drop(&mut self) {
+    if self
+      .state
+      .get_mut()
+      .is_ok_and(|state| state.item.is_some())
+    {
+      self
+        .budget
+        .used
+        .fetch_sub(self.size, Ordering::Relaxed);
+    }
+  }
+}
+
+// An item in the buffer of a channel
+#[derive(Debug, Clone)]
+pub(crate) enum Buffered<T> {
+  Item(T),
+  Budgeted(Arc<Budgeted<T>>),
+}
+
+#[derive(Debug)]
+pub(crate) struct EventSender<T = EventResult> {
+  tx: mpsc::Sender<Buffered<T>>,
+  dropped: DroppedCount,
+}
+
+#[derive(Debug)]
+pub(crate) struct EventReceiver<T = EventResult> {
+  rx: mpsc::Receiver<Buffered<T>>,
+  dropped: DroppedCount,
+}
+
+// Creates a bounded channel for the events of a single stream
+pub(crate) fn event_channel<T>(buffer: usize) -> (EventSender<T>, EventReceiver<T>) {
+  // Tokio channels panic with a capacity of 0
+  #[cfg(feature = "tokio-channel")]
+  let buffer = buffer.max(1);
+
+  let (tx, rx) = mpsc::channel(buffer);
+  let dropped = DroppedCount::default();
+
+  (
+    EventSender {
+      tx,
+      dropped: dropped.clone(),
+    },
+    EventReceiver { rx, dropped },
+  )
+}
+
+impl<T> EventSender<T> {
+  // Counts an event that never reached the buffer
+  pub(crate) fn record_dropped(&self) {
+    self.dropped.fetch_add(1, Ordering::Relaxed);
+  }
+
+  // Sends the event without blocking, failing if the buffer is full or if the receiver was dropped
+  pub(crate) fn try_send(&mut self, event: T) -> Result<(), String> {
+    self.try_send_buffered(Buffered::Item(event))
+  }
+
+  // Like `try_send`, but the item may be shared with the other streams
+  // (only the futures sender needs a mutable reference)
+  #[cfg_attr(feature = "tokio-channel", allow(clippy::needless_pass_by_ref_mut))]
+  pub(crate) fn try_send_buffered(&mut self, item: Buffered<T>) -> Result<(), String> {
+    // The stream is recorded as a holder before sending, so that the receiver always finds it
+    let budgeted = match &item {
+      Buffered::Budgeted(budgeted) => {
+        budgeted.add_holder(&self.dropped);
+        Some(budgeted.clone())
+      }
+      Buffered::Item(_) => None,
+    };
+
+    self.tx.try_send(item).map_err(|e| {
+      // If the item was evicted in the meantime, it was already counted as dropped
+      let evicted = budgeted.is_some_and(|budgeted| {
+        !Budgeted::remove_holder(&mut budgeted.state.lock().unwrap(), &self.dropped)
+      });
+
+      #[cfg(not(feature = "tokio-channel"))]
+      let is_full = e.is_full();
+      #[cfg(feature = "tokio-channel")]
+      let is_full = matches!(e, mpsc::error::TrySendError::Full(_));
+
+      if is_full && !evicted {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+      }
+
+      e.to_string()
+    })
+  }
+}
+
+impl<T: Clone> EventReceiver<T> {
+  pub(crate) fn dropped_count(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+
+  // Receives an event that is already in the buffer, without waiting
+  pub(crate) fn try_recv(&mut self) -> Option<T> {
+    loop {
+      let item = self.rx.try_recv().ok()?;
+
+      if let Some(event) = self.unwrap_item(item) {
+        return Some(event);
+      }
+    }
+  }
+
+  pub(crate) fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    loop {
+      #[cfg(not(feature = "tokio-channel"))]
+      let poll = Pin::new(&mut self.rx).poll_next(cx);
+
+      #[cfg(feature = "tokio-channel")]
+      let poll = self.rx.poll_recv(cx);
+
+      let Some(item) = std::task::ready!(poll) else {
+        return Poll::Ready(None);
+      };
+
+      if let Some(event) = self.unwrap_item(item) {
+        return Poll::Ready(Some(event));
+      }
+    }
+  }
+
+  // Returns the event carried by the item, unless it was evicted (in which case it was already counted as dropped)
+  fn unwrap_item(&self, item: Buffered<T>) -> Option<T> {
+    match item {
+      Buffered::Item(event) => Some(event),
+      Buffered::Budgeted(budgeted) => budgeted.take_for(&self.dropped),
+    }
+  }
+}
+
+impl<T> Drop for EventReceiver<T> {
+  fn drop(&mut self) {
+    // The items that are still buffered are discarded right away,
+    // so that the ones shared with the memory budget return their bytes to it
+    self.rx.close();
+
+    while self.rx.try_recv().is_ok() {}
+  }
+}
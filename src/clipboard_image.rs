@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use crate::*;
+
+/// An image found on the clipboard, regardless of the representation in which it was emitted.
+///
+/// This is returned by [`Body::to_image`], which normalizes the png images, the raw images, the images in their original encoding and the file lists with a single image file into a single type.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClipboardImage {
+  /// An image that has not been decoded, along with the path of its file (if there is one).
+  Encoded {
+    bytes: Vec<u8>,
+    format: ImageEncoding,
+    path: Option<PathBuf>,
+  },
+  /// An image that was already decoded.
+  Raw(RawImage),
+}
+
+/// The encoding of a [`ClipboardImage::Encoded`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageEncoding {
+  /// The png format.
+  Png,
+  /// The jpeg format.
+  Jpeg,
+  /// The gif format (only the first frame is decoded).
+  Gif,
+  /// The webp format.
+  WebP,
+  /// The bmp format, with its file header.
+  Bmp,
+  /// The tiff format.
+  Tiff,
+  /// A device-independent bitmap, without the bmp file header (see [`EncodedImageFormat::Dib`]).
+  Dib,
+  /// Any other format, which is detected from the content of the image when it is decoded.
+  Other,
+}
+
+impl ImageEncoding {
+  // Detects the format of an image file from its extension
+  fn from_path(path: &Path) -> Option<Self> {
+    let format = match image::ImageFormat::from_path(path).ok()? {
+      image::ImageFormat::Png => Self::Png,
+      image::ImageFormat::Jpeg => Self::Jpeg,
+      image::ImageFormat::Gif => Self::Gif,
+      image::ImageFormat::WebP => Self::WebP,
+      image::ImageFormat::Bmp => Self::Bmp,
+      image::ImageFormat::Tiff => Self::Tiff,
+      _ => Self::Other,
+    };
+
+    Some(format)
+  }
+
+  const fn image_format(self) -> Option<image::ImageFormat> {
+    match self {
+      Self::Png => Some(image::ImageFormat::Png),
+      Self::Jpeg => Some(image::ImageFormat::Jpeg),
+      Self::Gif => Some(image::ImageFormat::Gif),
+      Self::WebP => Some(image::ImageFormat::WebP),
+      Self::Bmp => Some(image::ImageFormat::Bmp),
+      Self::Tiff => Some(image::ImageFormat::Tiff),
+      Self::Dib | Self::Other => None,
+    }
+  }
+}
+
+impl ClipboardImage {
+  /// Returns the path of the file of this image, if there is one.
+  #[must_use]
+  #[inline]
+  pub const fn path(&self) -> Option<&PathBuf> {
+    match self {
+      Self::Encoded { path, .. } => path.as_ref(),
+      Self::Raw(image) => image.path.as_ref(),
+    }
+  }
+
+  /// Returns the image in png format, encoding it if necessary.
+  ///
+  /// The png images are returned as they are, while the others are decoded (if needed) and encoded again. Only the formats that are enabled on the current platform can be decoded (for example, [`ImageEncoding::Dib`] can only be decoded on Windows).
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the image cannot be decoded or encoded.
+  pub fn to_png(&self) -> Result<Vec<u8>, ClipboardError> {
+    match self {
+      Self::Encoded {
+        bytes,
+        format: ImageEncoding::Png,
+        ..
+      } => Ok(bytes.clone()),
+      _ => self.to_raw()?.encode_png(),
+    }
+  }
+
+  /// Returns the pixels of the image as a [`RawImage`], decoding it if necessary.
+  ///
+  /// Only the formats that are enabled on the current platform can be decoded (for example, [`ImageEncoding::Dib`] can only be decoded on Windows).
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the image cannot be decoded.
+  pub fn to_raw(&self) -> Result<RawImage, ClipboardError> {
+    let (bytes, format, path) = match self {
+      Self::Raw(image) => return Ok(image.clone()),
+      Self::Encoded {
+        bytes,
+        format,
+        path,
+      } => (bytes, *format, path),
+    };
+
+    #[cfg(windows)]
+    if format == ImageEncoding::Dib {
+      let mut image = crate::win::observer::load_dib(bytes)?;
+      image.path.clone_from(path);
+
+      return Ok(image);
+    }
+
+    let image = match format.image_format() {
+      Some(image_format) => image::load_from_memory_with_format(bytes, image_format),
+      None => image::load_from_memory(bytes),
+    }
+    .map_err(|e| {
+      ClipboardError::read_with_context(format_args!("Failed to load {format:?} image"), e)
+    })?;
+
+    Ok(RawImage::from_dynamic_image(image, path.clone()))
+  }
+}
+
+impl Body {
+  /// Returns the image in this body as a [`ClipboardImage`], regardless of its representation.
+  ///
+  /// - [`Body::PngImage`] and [`Body::EncodedImage`] are returned as a [`ClipboardImage::Encoded`].
+  /// - [`Body::RawImage`] is returned as a [`ClipboardImage::Raw`].
+  /// - A [`Body::FileList`] with a single file that looks like an image (judging by its extension) is read from disk and returned as a [`ClipboardImage::Encoded`], with the path of the file. It is not decoded, so its format is not checked until it is.
+  /// - For a [`Body::Multi`], the first representation that contains an image is used.
+  ///
+  /// The bytes of the image are copied, and the image file is only read if its size does not exceed `max_file_size` (if set), which works like the [`max_size_for`](crate::ClipboardEventListenerBuilder::max_size_for) limit of [`BodyKind::EncodedImage`] and [`BodyKind::RawImage`].
+  ///
+  /// Returns `None` if there is no image, or if the image file cannot be read or exceeds the limit.
+  #[must_use]
+  pub fn to_image(&self, max_file_size: Option<u64>) -> Option<ClipboardImage> {
+    match self {
+      Self::RawImage(image) => Some(ClipboardImage::Raw(image.clone())),
+      Self::PngImage { bytes, path } => Some(ClipboardImage::Encoded {
+        bytes: bytes.clone(),
+        format: ImageEncoding::Png,
+        path: path.clone(),
+      }),
+      Self::EncodedImage {
+        format,
+        bytes,
+        path,
+      } => Some(ClipboardImage::Encoded {
+        bytes: bytes.clone(),
+        format: match format {
+          EncodedImageFormat::Dib => ImageEncoding::Dib,
+          EncodedImageFormat::Tiff => ImageEncoding::Tiff,
+        },
+        path: path.clone(),
+      }),
+      Self::FileList(files) if files.len() == 1 => {
+        let path = &files[0];
+        let format = ImageEncoding::from_path(path)?;
+
+        if let Some(max_size) = max_file_size {
+          let size = std::fs::metadata(path)
+            .inspect_err(|e| debug!("Failed to read metadata for `{}`: {e}", path.display()))
+            .ok()?
+            .len();
+
+          if size > max_size {
+            debug!(
+              "Found image file with {} size, beyond maximum allowed size. Skipping it...",
+              HumanBytes(usize::try_from(size).unwrap_or(usize::MAX))
+            );
+
+            return None;
+          }
+        }
+
+        let bytes = std::fs::read(path)
+          .inspect_err(|e| debug!("Failed to read `{}`: {e}", path.display()))
+          .ok()?;
+
+        Some(ClipboardImage::Encoded {
+          bytes,
+          format,
+          path: Some(path.clone()),
+        })
+      }
+      Self::Multi(bodies) => bodies
+        .iter()
+        .find_map(|body| body.to_image(max_file_size)),
+      _ => None,
+    }
+  }
+}
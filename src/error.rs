@@ -35,19 +35,106 @@ impl From<Infallible> for InitializationError {
   }
 }
 
+/// The underlying error that caused a [`ClipboardError`], such as an error of the connection to the X11 server or of the image decoder.
+pub type ErrorSource = Arc<dyn std::error::Error + Send + Sync>;
+
 /// Various kinds of errors that can occur while monitoring or reading the clipboard.
+///
+/// When the error was caused by another one, the original error is available through [`Error::source`](std::error::Error::source). The source is not serialized.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Clone, Debug, Error)]
+#[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum ClipboardError {
-  #[error("Failed to monitor the clipboard: {0}")]
-  MonitorFailed(String),
+  MonitorFailed {
+    message: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<ErrorSource>,
+  },
 
-  #[error("Failed to read the clipboard: {0}")]
-  ReadError(String),
+  ReadError {
+    message: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<ErrorSource>,
+  },
 
-  #[error("The content of the clipboard did not match any supported format")]
   NoMatchingFormat,
+
+  Timeout,
+}
+
+impl Display for ClipboardError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::MonitorFailed { message, .. } => {
+        write!(f, "Failed to monitor the clipboard: {message}")
+      }
+      Self::ReadError { message, .. } => write!(f, "Failed to read the clipboard: {message}"),
+      Self::NoMatchingFormat => {
+        f.write_str("The content of the clipboard did not match any supported format")
+      }
+      Self::Timeout => f.write_str("Timed out while waiting for a clipboard change"),
+    }
+  }
+}
+
+// Implemented by hand so that the source is the original error, rather than the `Arc` that wraps it
+// (which would prevent downcasting it)
+impl std::error::Error for ClipboardError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::MonitorFailed { source, .. } | Self::ReadError { source, .. } => source
+        .as_deref()
+        .map(|source| source as &(dyn std::error::Error + 'static)),
+      Self::NoMatchingFormat | Self::Timeout => None,
+    }
+  }
+}
+
+impl ClipboardError {
+  pub(crate) fn monitor(message: impl Into<String>) -> Self {
+    Self::MonitorFailed {
+      message: message.into(),
+      source: None,
+    }
+  }
+
+  pub(crate) fn monitor_from<E>(source: E) -> Self
+  where
+    E: std::error::Error + Send + Sync + 'static,
+  {
+    Self::MonitorFailed {
+      message: source.to_string(),
+      source: Some(Arc::new(source)),
+    }
+  }
+
+  pub(crate) fn read(message: impl Into<String>) -> Self {
+    Self::ReadError {
+      message: message.into(),
+      source: None,
+    }
+  }
+
+  pub(crate) fn read_from<E>(source: E) -> Self
+  where
+    E: std::error::Error + Send + Sync + 'static,
+  {
+    Self::ReadError {
+      message: source.to_string(),
+      source: Some(Arc::new(source)),
+    }
+  }
+
+  // The message is made of the context followed by the message of the source
+  pub(crate) fn read_with_context<E>(context: impl Display, source: E) -> Self
+  where
+    E: std::error::Error + Send + Sync + 'static,
+  {
+    Self::ReadError {
+      message: format!("{context}: {source}"),
+      source: Some(Arc::new(source)),
+    }
+  }
 }
 
 impl From<Infallible> for ClipboardError {
@@ -61,6 +148,24 @@ pub(crate) enum ErrorWrapper {
   SizeTooLarge,
   ReadError(ClipboardError),
   UserSkipped,
+  // The listener was dropped while waiting for the data
+  #[cfg(target_os = "linux")]
+  Interrupted,
+  // The available targets are the same as in the previous change
+  #[cfg(target_os = "linux")]
+  UnchangedTargets,
+}
+
+impl ErrorWrapper {
+  // Used for one-shot reads, where early exits simply mean that there is no content
+  pub(crate) fn into_read_result<T>(self) -> Result<Option<T>, ClipboardError> {
+    match self {
+      Self::ReadError(e) => Err(e),
+      Self::EmptyContent | Self::SizeTooLarge | Self::UserSkipped => Ok(None),
+      #[cfg(target_os = "linux")]
+      Self::Interrupted | Self::UnchangedTargets => Ok(None),
+    }
+  }
 }
 
 impl From<ClipboardError> for ErrorWrapper {
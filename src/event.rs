@@ -0,0 +1,119 @@
+use crate::*;
+
+/// A clipboard change, along with some metadata about how it was extracted.
+///
+/// [`ClipboardStream`] only yields the [`Body`], while [`ClipboardEventStream`] (created with [`ClipboardStream::with_metadata`]) yields the whole event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardEvent {
+  /// The content extracted from the clipboard.
+  pub body: Arc<Body>,
+  /// Additional information about this event.
+  pub metadata: EventMetadata,
+}
+
+impl ClipboardEvent {
+  pub(crate) fn new(body: Body, metadata: EventMetadata) -> Self {
+    let event = Self {
+      body: Arc::new(body),
+      metadata,
+    };
+
+    #[cfg(feature = "tracing")]
+    trace_event(&event);
+
+    event
+  }
+}
+
+/// Diagnostic information attached to a [`ClipboardEvent`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EventMetadata {
+  /// Whether at least one of the reads for this event went through the X11 `INCR` (incremental) protocol, which is used for large payloads.
+  ///
+  /// Always `false` outside of Linux.
+  pub incremental: bool,
+
+  /// Whether the file list in this event was truncated because it exceeded the maximum file count.
+  ///
+  /// See [`max_file_count`](crate::ClipboardEventListenerBuilder::max_file_count) for more information.
+  pub truncated: bool,
+
+  /// The names of the custom formats in this event whose data was cut short to its prefix, which identify the [`Body::Custom`] items that are incomplete.
+  ///
+  /// See [`with_custom_format_prefix`](crate::ClipboardEventListenerBuilder::with_custom_format_prefix) for more information.
+  pub truncated_formats: Vec<String>,
+
+  /// The X11 [`Selection`] that this event was extracted from.
+  ///
+  /// Always `None` outside of Linux.
+  pub selection: Option<Selection>,
+
+  /// The application that put the content on the clipboard, if it could be detected.
+  ///
+  /// This is detected on a best-effort basis:
+  /// - On Linux, this is the class from the `WM_CLASS` property of the window that owns the selection (or of its client leader), such as `firefox` or `KeePassXC`.
+  /// - On macOS, this is the bundle identifier of the frontmost application when the change was detected, such as `com.apple.Safari`.
+  /// - On Windows, this is the file name of the executable of the process that owns the clipboard, such as `notepad.exe`.
+  pub source: Option<String>,
+
+  /// The id of the process that put the content on the clipboard, if it could be detected.
+  ///
+  /// On Windows, this is the process of the window that owns the clipboard. It is `None` when the content was set without an owner window (for example, by a window that was closed after offering its content with delayed rendering).
+  ///
+  /// Always `None` outside of Windows.
+  pub source_pid: Option<u32>,
+
+  /// Whether the files in a [`Body::FileList`] were cut or copied, as indicated by the file manager.
+  ///
+  /// On Linux, this is read from the `x-special/gnome-copied-files` (GNOME and most GTK file managers) or `application/x-kde-cutselection` (KDE) formats.
+  ///
+  /// Always [`FileOperation::Unknown`] outside of Linux, or for other kinds of content.
+  pub file_operation: FileOperation,
+
+  /// The URL of the page that copied html content comes from, if the browser provided it.
+  ///
+  /// This is read on a best-effort basis:
+  /// - On Windows, this is the `SourceURL` header of the `HTML Format` (CF_HTML).
+  /// - On Linux, this is read from the `chromium/x-source-url` (Chromium-based browsers) or `text/x-moz-url-priv` (Firefox) formats.
+  /// - On macOS, this is read from the `org.chromium.source-url` type (Chromium-based browsers).
+  ///
+  /// Always `None` for other kinds of content.
+  pub source_url: Option<String>,
+}
+
+/// A clipboard change that was rejected by the [`Gatekeeper`].
+///
+/// It is yielded by the [`RejectedStream`], which is created with [`ClipboardEventListener::rejected_stream`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RejectedChange {
+  /// The names of the formats that were available on the clipboard when the change was rejected.
+  pub formats: Vec<String>,
+}
+
+/// The operation that should be performed when pasting a list of files.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FileOperation {
+  /// The files were cut, so they should be moved.
+  Cut,
+  /// The files were copied.
+  Copy,
+  /// The operation could not be detected.
+  #[default]
+  Unknown,
+}
+
+// Checks whether the source of an event is in the list of excluded sources (ignoring the case)
+pub(crate) fn is_excluded_source(excluded: &[String], source: Option<&str>) -> bool {
+  source.is_some_and(|source| {
+    excluded
+      .iter()
+      .any(|excluded| excluded.eq_ignore_ascii_case(source))
+  })
+}
+
+pub type EventResult = Result<ClipboardEvent, ClipboardError>;
@@ -7,18 +7,83 @@ use crate::*;
 /// Use the [`builder`](ClipboardEventListener::builder) method to customize the options for the listener.
 pub struct ClipboardEventListener {
   pub(crate) stop_signal: Arc<AtomicBool>,
+  pub(crate) restart_signal: Arc<AtomicBool>,
   pub(crate) thread_handle: Option<JoinHandle<()>>,
   body_senders: Arc<BodySenders>,
-  next_id: AtomicUsize,
+  gatekeeper: Arc<SharedGatekeeper>,
+  shutdown_timeout: Option<Duration>,
+  emit_on_resume: bool,
+  // Used to read the custom formats on demand
+  custom_formats: Vec<Arc<str>>,
+  custom_max_size: Option<u64>,
+  pasteboard: PasteboardName,
 }
 
 /// The builder for the [`ClipboardEventListener`]. It can be used to specify more customized options such as the polling interval, or a list of custom clipboard formats.
 #[derive(Default)]
 pub struct ClipboardEventListenerBuilder<G = DefaultGatekeeper> {
+  pub(crate) options: ObserverOptions,
+  pub(crate) gatekeeper: G,
+  pub(crate) on_change: Option<ChangeCallback>,
+}
+
+// The options that are passed to the Observer.
+// They are cloned every time the Observer is (re)started.
+#[derive(Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct ObserverOptions {
   pub(crate) interval: Option<Duration>,
+  pub(crate) adaptive_interval: Option<(Duration, Duration)>,
+  pub(crate) warmup: Option<Warmup>,
   pub(crate) custom_formats: Vec<Arc<str>>,
-  pub(crate) max_bytes: Option<u32>,
-  pub(crate) gatekeeper: G,
+  pub(crate) collect_custom_formats: bool,
+  pub(crate) text_custom_formats: Vec<Arc<str>>,
+  pub(crate) format_validators: FormatValidators,
+  pub(crate) format_prefixes: FormatPrefixes,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  pub(crate) text_formats: Vec<Arc<str>>,
+  pub(crate) size_limits: SizeLimits,
+  pub(crate) max_file_count: Option<usize>,
+  pub(crate) truncate_file_lists: bool,
+  pub(crate) directory_handling: DirectoryHandling,
+  #[cfg_attr(not(windows), allow(dead_code))]
+  pub(crate) delayed_rendering: DelayedRendering,
+  pub(crate) include_text_with_html: bool,
+  pub(crate) coalesce_text_with_files: bool,
+  pub(crate) suppress_no_matching_format: bool,
+  pub(crate) load_image_from_single_file: bool,
+  #[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+  pub(crate) image_mode: ImageMode,
+  pub(crate) image_output: ImageOutput,
+  pub(crate) image_path_mode: ImagePathMode,
+  pub(crate) keep_source_bytes: bool,
+  pub(crate) deliver_undecoded_on_error: bool,
+  #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+  pub(crate) concatenate_text_items: bool,
+  #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+  pub(crate) exact_text: bool,
+  #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+  pub(crate) respect_nspasteboard_conventions: bool,
+  #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+  pub(crate) main_thread_polling: bool,
+  #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+  pub(crate) pasteboard: PasteboardName,
+  pub(crate) history: usize,
+  pub(crate) dedicated_dispatcher: bool,
+  pub(crate) memory_budget: Option<u64>,
+  pub(crate) shutdown_timeout: Option<Duration>,
+  pub(crate) emit_on_resume: bool,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  pub(crate) selections: Vec<Selection>,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  pub(crate) read_poll_interval: Option<Duration>,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  pub(crate) skip_unchanged_targets: bool,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  pub(crate) skip_clipboard_manager_duplicates: bool,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  pub(crate) event_driven: bool,
+  pub(crate) excluded_sources: Vec<String>,
 }
 
 impl<G: Gatekeeper> ClipboardEventListenerBuilder<G> {
@@ -26,11 +91,93 @@ impl<G: Gatekeeper> ClipboardEventListenerBuilder<G> {
   #[must_use]
   #[inline]
   pub const fn interval(mut self, duration: Duration) -> Self {
-    self.interval = Some(duration);
+    self.options.interval = Some(duration);
+    self
+  }
+
+  /// When enabled, a change is skipped (before any of its data is transferred) if the clipboard offers the same set of formats as in the previous change of the same selection.
+  ///
+  /// This reduces the redundant reads caused by applications that repeatedly re-assert the ownership of a selection without changing it. However, two different items copied from the same application usually offer the same formats, so the second one is skipped as well. For this reason, this should only be enabled when the cost of the reads matters more than catching every change. Disabled by default.
+  ///
+  /// The content extracted with [`refresh`](ClipboardEventListener::refresh) (including when the listener is unmuted with [`emit_on_resume`](ClipboardEventListenerBuilder::emit_on_resume)) is not subject to this check, and it does not affect the formats recorded for the next comparison.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms.
+  #[must_use]
+  #[inline]
+  pub const fn skip_unchanged_targets(mut self, skip: bool) -> Self {
+    self.options.skip_unchanged_targets = skip;
+    self
+  }
+
+  /// When enabled, the content served by a clipboard manager is skipped if it is the same as the last event.
+  ///
+  /// Clipboard managers take the ownership of a selection to preserve its content after the original owner exits, which produces another event for the same content. This is detected (on a best-effort basis) from the class of the new owner, so the managers that are recognized are Klipper (including the one integrated in `plasmashell`), GPaste, CopyQ, ClipIt, Parcellite, Diodon and Clipman (from Xfce). Disabled by default.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms.
+  #[must_use]
+  #[inline]
+  pub const fn skip_clipboard_manager_duplicates(mut self, skip: bool) -> Self {
+    self.options.skip_clipboard_manager_duplicates = skip;
+    self
+  }
+
+  /// Sets the longest wait between the checks for the replies of the clipboard owner while a format is being read. Defaults to 20 milliseconds.
+  ///
+  /// The wait starts at 1 millisecond and doubles after every check that finds no reply, up to this value. A lower value reduces the latency of the reads from slow clipboard owners, at the cost of more wakeups while waiting for them.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms.
+  #[must_use]
+  #[inline]
+  pub const fn read_poll_interval(mut self, max_wait: Duration) -> Self {
+    self.options.read_poll_interval = Some(max_wait);
+    self
+  }
+
+  /// When enabled, the monitoring thread blocks until the X server notifies a change, rather than sleeping for the polling interval before checking for it.
+  ///
+  /// This reduces the latency of the events to the time that it takes to read the content, and avoids the periodic wakeups. The [`interval`](ClipboardEventListenerBuilder::interval) (or the [`adaptive_interval`](ClipboardEventListenerBuilder::adaptive_interval)) is then only used as the longest wait between the checks for the stop signal, so dropping the listener still takes effect within one interval. Intervals shorter than 10 milliseconds (including zero) are raised to that, so that the wait never turns into a busy loop. Disabled by default.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms.
+  #[must_use]
+  #[inline]
+  pub const fn event_driven(mut self, enable: bool) -> Self {
+    self.options.event_driven = enable;
+    self
+  }
+
+  /// Makes the polling interval adapt to the clipboard activity, to reduce the wakeups during long idle periods (which can be useful to save battery on laptops).
+  ///
+  /// The listener starts polling at the `max` (slowest) interval. After every detected change, it polls at the `min` (fastest) interval for a few seconds, and then the interval gradually decays back to `max`.
+  ///
+  /// A `min` below 1 millisecond is raised to 1 millisecond, since the interval decays by doubling and could not grow from zero.
+  ///
+  /// When this is set, the fixed [`interval`](ClipboardEventListenerBuilder::interval) is ignored.
+  #[must_use]
+  #[inline]
+  pub const fn adaptive_interval(mut self, min: Duration, max: Duration) -> Self {
+    self.options.adaptive_interval = Some((min, max));
+    self
+  }
+
+  /// Makes the listener poll at a faster `interval` for the given `duration` after each new content stream is created (such as with [`new_stream`](ClipboardEventListener::new_stream)), and then relax back to the regular interval.
+  ///
+  /// This reduces the latency of the first event after a subscription, which would otherwise depend on where the listener is in its (possibly slow) polling cycle. It works alongside the [`adaptive_interval`](ClipboardEventListenerBuilder::adaptive_interval), and the faster of the two intervals is used. Creating a stream cuts short the wait that is in progress, so the faster interval applies right away. For example, a warmup of 2 seconds at 20 milliseconds covers the typical delay between subscribing and copying something.
+  ///
+  /// On Windows, where the changes are notified by the system, this only affects the checks for the stop signal.
+  #[must_use]
+  #[inline]
+  pub fn warmup(mut self, duration: Duration, interval: Duration) -> Self {
+    self.options.warmup = Some(Warmup {
+      duration,
+      interval,
+      subscribed: SubscriptionTime::default(),
+    });
     self
   }
 
   /// Sets the [`Gatekeeper`] for this listener, which indicates whether the clipboard content should be processed at any given moment or not.
+  ///
+  /// The gatekeeper can also be replaced after the listener has been spawned, with [`set_gatekeeper`](ClipboardEventListener::set_gatekeeper).
   #[must_use]
   #[inline]
   pub fn with_gatekeeper<F>(self, gatekeeper: F) -> ClipboardEventListenerBuilder<F>
@@ -38,10 +185,9 @@ impl<G: Gatekeeper> ClipboardEventListenerBuilder<G> {
     F: Fn(ClipboardContext) -> bool + Send + Sync + 'static,
   {
     ClipboardEventListenerBuilder {
-      interval: self.interval,
-      custom_formats: self.custom_formats,
-      max_bytes: self.max_bytes,
+      options: self.options,
       gatekeeper,
+      on_change: self.on_change,
     }
   }
 
@@ -57,7 +203,84 @@ impl<G: Gatekeeper> ClipboardEventListenerBuilder<G> {
     I: IntoIterator<Item = S>,
     S: AsRef<str>,
   {
-    self.custom_formats = formats.into_iter().map(|s| s.as_ref().into()).collect();
+    self.options.custom_formats = formats.into_iter().map(|s| s.as_ref().into()).collect();
+    self
+  }
+
+  /// Adds a custom format (such as `application/json`) whose data is checked with a validator after being read.
+  ///
+  /// The format is extracted like the ones given to [`with_custom_formats`](ClipboardEventListenerBuilder::with_custom_formats) (after them, if it is not already in that list). If the validator returns `false`, the content is considered malformed and it is skipped, just like empty content. Calling this again for the same format replaces its validator.
+  #[must_use]
+  #[inline]
+  pub fn with_validated_custom_format<F>(mut self, name: impl AsRef<str>, validator: F) -> Self
+  where
+    F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+  {
+    self
+      .options
+      .format_validators
+      .insert(name.as_ref().into(), Arc::new(validator));
+    self
+  }
+
+  /// Adds a custom format of which only the first `max_prefix_bytes` bytes are read, such as for sniffing the magic number of a large payload.
+  ///
+  /// The format is extracted like the ones given to [`with_custom_formats`](ClipboardEventListenerBuilder::with_custom_formats) (after them, if it is not already in that list), and it is emitted as a [`Body::Custom`] with the prefix of its data. If the data was longer, the name of the format is added to the [`truncated_formats`](EventMetadata::truncated_formats) of the metadata. The size limit for [`BodyKind::Custom`] does not apply to it. Calling this again for the same format replaces its length.
+  ///
+  /// If the format also has a validator (see [`with_validated_custom_format`](ClipboardEventListenerBuilder::with_validated_custom_format)), the validator only receives the prefix.
+  ///
+  /// On Linux, only the prefix is requested from the X server, unless the owner sends the data with an incremental transfer (which must be read in full, even if only its prefix is kept). On macOS and Windows, the data is read in full and then truncated.
+  #[must_use]
+  #[inline]
+  pub fn with_custom_format_prefix(
+    mut self,
+    name: impl AsRef<str>,
+    max_prefix_bytes: usize,
+  ) -> Self {
+    self
+      .options
+      .format_prefixes
+      .insert(name.as_ref().into(), max_prefix_bytes);
+    self
+  }
+
+  /// When enabled, every custom format that is present on the clipboard is extracted, rather than only the first one.
+  ///
+  /// If more than one of them is present, they are emitted together as a [`Body::Multi`] of [`Body::Custom`] items, in the order in which the formats were given. This is useful for protocols that split the data across multiple formats. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn collect_custom_formats(mut self, collect: bool) -> Self {
+    self.options.collect_custom_formats = collect;
+    self
+  }
+
+  /// Adds a list of custom formats whose content is textual (such as `text/markdown` or `application/json`).
+  ///
+  /// These are extracted like the formats given to [`with_custom_formats`](ClipboardEventListenerBuilder::with_custom_formats) (after them, if they are not already in that list), but their content is decoded as UTF-8 (replacing the invalid sequences) and emitted as a [`Body::Text`], tagged with the name of the format, rather than as a [`Body::Custom`]. Unlike [`with_text_formats`](ClipboardEventListenerBuilder::with_text_formats), this works on every platform.
+  #[must_use]
+  #[inline]
+  pub fn with_text_custom_formats<I, S>(mut self, formats: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    self.options.text_custom_formats = formats.into_iter().map(|s| s.as_ref().into()).collect();
+    self
+  }
+
+  /// Adds a list of text formats (such as `text/markdown`) to recognize, in order of priority.
+  ///
+  /// When one of them is available, its content is decoded as UTF-8 and emitted as a [`Body::Text`], tagged with the name of the format. These take priority over html and plain text, but not over images and file lists.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms. To read such formats elsewhere, use [`with_custom_formats`](ClipboardEventListenerBuilder::with_custom_formats).
+  #[must_use]
+  #[inline]
+  pub fn with_text_formats<I, S>(mut self, formats: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    self.options.text_formats = formats.into_iter().map(|s| s.as_ref().into()).collect();
     self
   }
 
@@ -66,30 +289,428 @@ impl<G: Gatekeeper> ClipboardEventListenerBuilder<G> {
   /// The various platform-specific implementations will attempt to use a performant method to check the size of the clipboard items without loading their content into a buffer, so this can be useful to avoid processing large files such as high-definition images.
   #[must_use]
   #[inline]
-  pub const fn max_size(mut self, max_bytes: u32) -> Self {
-    self.max_bytes = Some(max_bytes);
+  pub fn max_size(mut self, max_bytes: impl ByteSize) -> Self {
+    self.options.size_limits.global = Some(max_bytes.into_bytes());
+    self
+  }
+
+  /// Sets a minimum size, in bytes, below which the content is skipped. This can be used to ignore trivial content, such as single characters or tiny accidental copies.
+  ///
+  /// Unlike [`max_size`](ClipboardEventListenerBuilder::max_size), this is a simple byte count that applies to every kind of content (including text, whose length is measured in UTF-8 bytes), except for file lists. It is checked after the content has been extracted. For a [`Body::Multi`], the size of its largest representation is used.
+  #[must_use]
+  #[inline]
+  pub fn min_size(mut self, min_bytes: impl ByteSize) -> Self {
+    self.options.size_limits.min = Some(min_bytes.into_bytes());
+    self
+  }
+
+  /// Sets a maximum allowed size limit for a specific [`BodyKind`], which overrides the global [`max_size`](ClipboardEventListenerBuilder::max_size) for that kind.
+  ///
+  /// Like the global limit, it only applies to [`BodyKind::Custom`], [`BodyKind::PngImage`], [`BodyKind::EncodedImage`] and [`BodyKind::RawImage`] (which includes the images loaded with [`load_image_from_single_file`](ClipboardEventListenerBuilder::load_image_from_single_file)). On Linux, a limit for [`BodyKind::FileList`] also caps the size of the uri-list that describes the files, which is checked while it is being read, and the file lists that exceed it are skipped. The limits for the other kinds are ignored.
+  #[must_use]
+  #[inline]
+  pub fn max_size_for(mut self, kind: BodyKind, max_bytes: impl ByteSize) -> Self {
+    self
+      .options
+      .size_limits
+      .overrides
+      .insert(kind, max_bytes.into_bytes());
+    self
+  }
+
+  /// Sets all of the size and count limits at once, replacing the ones that were set before (including with the individual methods such as [`max_size`](ClipboardEventListenerBuilder::max_size)).
+  ///
+  /// This can be useful when the limits are computed or stored elsewhere.
+  #[must_use]
+  #[inline]
+  pub fn limits(mut self, limits: Limits) -> Self {
+    self.options.size_limits = SizeLimits {
+      global: limits.max_size,
+      overrides: limits.max_size_overrides,
+      min: limits.min_size,
+    };
+    self.options.max_file_count = limits.max_file_count;
+    self.options.truncate_file_lists = limits.truncate_file_lists;
+    self
+  }
+
+  /// Sets the maximum number of files allowed in a [`Body::FileList`]. If unset, file lists are unlimited.
+  ///
+  /// By default, file lists that exceed this limit are skipped entirely, like items that exceed the [`max_size`](ClipboardEventListenerBuilder::max_size). If [`truncate_file_lists`](ClipboardEventListenerBuilder::truncate_file_lists) is enabled, they are truncated to the first `max_count` files instead, and the [`truncated`](EventMetadata::truncated) flag is set in the event's metadata.
+  #[must_use]
+  #[inline]
+  pub const fn max_file_count(mut self, max_count: usize) -> Self {
+    self.options.max_file_count = Some(max_count);
+    self
+  }
+
+  /// When enabled, file lists that exceed the [`max_file_count`](ClipboardEventListenerBuilder::max_file_count) are truncated rather than skipped.
+  #[must_use]
+  #[inline]
+  pub const fn truncate_file_lists(mut self, truncate: bool) -> Self {
+    self.options.truncate_file_lists = truncate;
+    self
+  }
+
+  /// Sets the [`DirectoryHandling`], which defines whether the directories in a [`Body::FileList`] are kept, removed, or replaced with the files that they contain.
+  ///
+  /// This is applied before the [`max_file_count`](ClipboardEventListenerBuilder::max_file_count), so the limit counts the files in the resulting list. If no files are left after removing the directories, the content is skipped. Defaults to [`DirectoryHandling::Keep`], which does not access the file system.
+  #[must_use]
+  #[inline]
+  pub const fn directory_handling(mut self, handling: DirectoryHandling) -> Self {
+    self.options.directory_handling = handling;
+    self
+  }
+
+  /// Sets the [`DelayedRendering`] handling, which defines what happens when the application that owns the clipboard fails to produce the data of a format that it advertised.
+  ///
+  /// This only has an effect on Windows. Defaults to [`DelayedRendering::Error`].
+  #[must_use]
+  #[inline]
+  pub const fn delayed_rendering(mut self, handling: DelayedRendering) -> Self {
+    self.options.delayed_rendering = handling;
+    self
+  }
+
+  /// When enabled, the plain text alternative that usually accompanies html content is also extracted.
+  ///
+  /// If both are present, a [`Body::Multi`] is emitted, containing a [`Body::Html`] followed by a [`Body::PlainText`]. If there is no plain text alternative, a simple [`Body::Html`] is emitted.
+  ///
+  /// Disabled by default, to avoid the extra read.
+  #[must_use]
+  #[inline]
+  pub const fn include_text_with_html(mut self, include: bool) -> Self {
+    self.options.include_text_with_html = include;
+    self
+  }
+
+  /// When enabled, the text that only describes the file list on the clipboard is dropped in favor of the file list.
+  ///
+  /// Some applications (such as file managers, or the sources of a drag and drop) offer the files along with their paths as text. The file list already takes priority over html, plain text and the [`with_text_formats`](ClipboardEventListenerBuilder::with_text_formats), so this applies to the [`with_text_custom_formats`](ClipboardEventListenerBuilder::with_text_custom_formats), which come first. When the same clipboard content also has a file list, a text custom format that describes its files is skipped, and the extraction continues as if it was not there: the next custom format is used, or else the file list is emitted. With [`collect_custom_formats`](ClipboardEventListenerBuilder::collect_custom_formats), the text is left out of the [`Body::Multi`].
+  ///
+  /// This is a heuristic. The text describes the files if (ignoring its trailing line breaks) it is equal to the path of a single file, or if its lines are the percent-encoded `file://` uris of the files in the same order (which is the format of [`to_uri_list`](Body::to_uri_list)). Only the formats of the same clipboard change are compared. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn coalesce_text_with_files(mut self, coalesce: bool) -> Self {
+    self.options.coalesce_text_with_files = coalesce;
+    self
+  }
+
+  /// Sets the [`ImageMode`], which defines whether the images that are not in png format are decoded or emitted in their original encoding.
+  ///
+  /// With [`ImageMode::Original`], bitmaps are emitted as a [`Body::EncodedImage`] with the raw `CF_DIBV5`/`CF_DIB` bytes on Windows, and TIFF images are emitted with the original `public.tiff` bytes on macOS. This avoids the cost of decoding them and preserves the alpha channel, the color profile and the metadata. The size limit for [`BodyKind::EncodedImage`] applies to them.
+  ///
+  /// This option only applies to Windows and macOS, and it is ignored on Linux. Defaults to [`ImageMode::Decoded`].
+  #[must_use]
+  #[inline]
+  pub const fn image_mode(mut self, mode: ImageMode) -> Self {
+    self.options.image_mode = mode;
+    self
+  }
+
+  /// Sets the [`ImageOutput`], which defines whether images are always emitted in the same representation, regardless of the format in which they are found on the clipboard.
+  ///
+  /// For example, macOS and Windows can emit either a [`Body::PngImage`] or a [`Body::RawImage`] depending on the formats offered by the source application. With [`ImageOutput::Png`], raw images are encoded in png format, and with [`ImageOutput::Raw`], png images are decoded, so that only one of the two has to be handled.
+  ///
+  /// The size limits are checked against the images as they are found on the clipboard, before the conversion. Images emitted as a [`Body::EncodedImage`] (see [`image_mode`](ClipboardEventListenerBuilder::image_mode)) are never converted. Defaults to [`ImageOutput::Native`].
+  #[must_use]
+  #[inline]
+  pub const fn image_output(mut self, output: ImageOutput) -> Self {
+    self.options.image_output = output;
+    self
+  }
+
+  /// Sets the [`ImagePathMode`], which defines how the images are emitted when the clipboard also contains the path of their file (for example, when an image is copied from a file manager or from some image viewers).
+  ///
+  /// By default, the bytes of the image are emitted with the path attached to them. With [`ImagePathMode::PreferPath`], the image is emitted as a [`Body::FileList`] with the single path instead, which avoids holding the bytes when the file is all that is needed. This still goes through the handling of file lists, so the image is loaded from its file if [`load_image_from_single_file`](ClipboardEventListenerBuilder::load_image_from_single_file) is enabled.
+  ///
+  /// Defaults to [`ImagePathMode::PreferBytes`].
+  #[must_use]
+  #[inline]
+  pub const fn image_with_path_mode(mut self, mode: ImagePathMode) -> Self {
+    self.options.image_path_mode = mode;
+    self
+  }
+
+  /// When enabled, the decoded images keep a copy of their original bytes in [`RawImage::source_bytes`], which avoids reading the clipboard again to get them (for example, to inspect color issues).
+  ///
+  /// Disabled by default, since it roughly doubles the memory used by each image.
+  #[must_use]
+  #[inline]
+  pub const fn keep_source_bytes(mut self, keep: bool) -> Self {
+    self.options.keep_source_bytes = keep;
+    self
+  }
+
+  /// When enabled, the images that cannot be decoded are emitted as a [`Body::Custom`] with their original bytes, instead of sending a [`ClipboardError::ReadError`] to the streams.
+  ///
+  /// The name of the custom format is the name of the format of the image, followed by `-undecoded` (such as `image/webp-undecoded` on Linux, `public.tiff-undecoded` on macOS or `CF_DIBV5-undecoded` and `CF_DIB-undecoded` on Windows). This can be used to capture the data that the decoder does not support, in order to diagnose the issue. The decoding error is still logged. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn deliver_undecoded_on_error(mut self, deliver: bool) -> Self {
+    self.options.deliver_undecoded_on_error = deliver;
+    self
+  }
+
+  /// When enabled, a file list that contains a single image file is loaded from disk and emitted as a [`Body::RawImage`], with the [`path`](RawImage::path) set to the path of the file.
+  ///
+  /// The [`max_size`](ClipboardEventListenerBuilder::max_size) limit is checked against the size of the file on disk, before it is loaded. If the file exceeds it, or if it cannot be read or decoded (for example, because its format is not supported on the current platform), the regular [`Body::FileList`] is emitted instead.
+  ///
+  /// Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn load_image_from_single_file(mut self, load: bool) -> Self {
+    self.options.load_image_from_single_file = load;
+    self
+  }
+
+  /// When enabled, clipboard changes that do not contain any supported format are silently skipped, instead of sending a [`ClipboardError::NoMatchingFormat`] error to the streams.
+  ///
+  /// Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn suppress_no_matching_format(mut self, suppress: bool) -> Self {
+    self.options.suppress_no_matching_format = suppress;
+    self
+  }
+
+  /// When enabled, the text of all the items in the pasteboard is concatenated, instead of only reading the first item that contains text.
+  ///
+  /// This matches the behavior of `NSPasteboard`'s `stringForType` (used by crates such as `arboard`). It applies to both plain text and html content.
+  ///
+  /// This option only applies to macOS, and it is ignored on other platforms. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn concatenate_text_items(mut self, concatenate: bool) -> Self {
+    self.options.concatenate_text_items = concatenate;
+    self
+  }
+
+  /// When enabled, the text is read from the exact bytes of its pasteboard type (with `dataForType`), which are decoded as UTF-8 (replacing the invalid sequences), rather than from the string of the first pasteboard item that contains text.
+  ///
+  /// This bypasses the selection of the items (and [`concatenate_text_items`](ClipboardEventListenerBuilder::concatenate_text_items)), and it can be used when the text must be extracted exactly as it was written, such as for forensic purposes. It applies to both plain text and html content.
+  ///
+  /// This option only applies to macOS, and it is ignored on other platforms. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn exact_text(mut self, exact: bool) -> Self {
+    self.options.exact_text = exact;
+    self
+  }
+
+  /// When enabled, the content marked with the `org.nspasteboard.ConcealedType` or `org.nspasteboard.TransientType` types is skipped, following the [nspasteboard.org](http://nspasteboard.org) conventions.
+  ///
+  /// These markers are placed on the pasteboard by applications such as password managers, to signal that the content is sensitive or that it is only there temporarily. The content is skipped before the [`Gatekeeper`] runs. For finer control, the markers can also be checked in the gatekeeper with [`has_format`](ClipboardContext::has_format).
+  ///
+  /// This option only applies to macOS, and it is ignored on other platforms. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn respect_nspasteboard_conventions(mut self, respect: bool) -> Self {
+    self.options.respect_nspasteboard_conventions = respect;
+    self
+  }
+
+  /// When enabled, the pasteboard is accessed on the main thread (through the main dispatch queue) rather than on the dedicated monitoring thread.
+  ///
+  /// Reading the pasteboard off the main thread generally works, but some AppKit operations expect to run on the main thread. This should be enabled in GUI applications that run the main run loop (such as those built on `NSApplication`), to avoid subtle threading issues. The monitoring thread still handles the timing, and it waits for each read to complete on the main thread. As a consequence, the [`Gatekeeper`] and the validators of [`with_validated_custom_format`](ClipboardEventListenerBuilder::with_validated_custom_format) also run on the main thread, and a busy main thread delays the events. The events are never delivered from the main thread, so the [`on_change`](ClipboardEventListenerBuilder::on_change) callback does not run there.
+  ///
+  /// If the main run loop does not respond within a couple of seconds after the listener starts (for example, in command line tools and services, where the main thread is not running a run loop), a warning is logged and the pasteboard is accessed on the monitoring thread instead. For this reason, the main run loop should be started shortly after the listener is created.
+  ///
+  /// The reads are queued without blocking the main queue, so the listener can be dropped on the main thread. A read that has not started yet is cancelled as soon as the listener is dropped, and the monitoring thread exits without waiting for the main queue.
+  ///
+  /// This option only applies to macOS, and it is ignored on other platforms. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn main_thread_polling(mut self, enable: bool) -> Self {
+    self.options.main_thread_polling = enable;
+    self
+  }
+
+  /// Sets the pasteboard to monitor. Defaults to [`PasteboardName::General`].
+  ///
+  /// The other pasteboards have their own change count, so they are monitored in the same way. For example, [`PasteboardName::Find`] can be used to follow the searches made in other applications.
+  ///
+  /// This option only applies to macOS, and it is ignored on other platforms. The one-shot reads (such as [`read_text`](crate::read_text)) always use the general pasteboard, while [`read_all_custom`](ClipboardEventListener::read_all_custom) reads from this one.
+  #[must_use]
+  #[inline]
+  pub fn macos_pasteboard(mut self, pasteboard: PasteboardName) -> Self {
+    self.options.pasteboard = pasteboard;
+    self
+  }
+
+  /// Keeps a history of the last `capacity` items emitted by the listener, which can be retrieved with [`recent`](ClipboardEventListener::recent).
+  ///
+  /// The history is capped, so when it is full, the oldest item is evicted to make room for the new one. Every emitted item is recorded (errors excluded), and no deduplication is performed, so copying the same content twice results in two entries.
+  ///
+  /// Disabled by default (a capacity of 0 also disables it).
+  #[must_use]
+  #[inline]
+  pub const fn history(mut self, capacity: usize) -> Self {
+    self.options.history = capacity;
+    self
+  }
+
+  /// When enabled, the events are delivered to the streams (and to the [`on_change`](ClipboardEventListenerBuilder::on_change) callback) from a dedicated thread, rather than from the thread that monitors the clipboard. Disabled by default.
+  ///
+  /// The monitor thread simply hands each event over and goes back to polling the clipboard immediately, so the delivery (including a slow callback) never delays the detection of the following changes. The events are still delivered in order. This uses a plain thread, so it does not require an async runtime.
+  ///
+  /// Up to 64 events can wait for the dispatcher thread. While they are all waiting (for example, because the callback is slow), the new events are dropped for all of the streams, which is reported by their [`dropped_count`](ClipboardStream::dropped_count).
+  ///
+  /// If the callback panics, only the dispatcher thread is stopped: the monitor keeps running, and the following events are delivered directly to the streams.
+  #[must_use]
+  #[inline]
+  pub const fn dedicated_dispatcher(mut self, enable: bool) -> Self {
+    self.options.dedicated_dispatcher = enable;
+    self
+  }
+
+  /// Sets the maximum amount of memory (in bytes) that can be taken up by the items buffered across all of the streams of this listener, to protect against running out of memory when the consumers lag behind (for example, with many streams that buffer large images).
+  ///
+  /// When a new item does not fit in the budget, the oldest buffered items are evicted from all of the streams that buffer them until it fits, so the consumers always find the most recent content. The evicted items are reported by [`dropped_count`](ClipboardStream::dropped_count), just like the ones dropped because the buffer of a stream was full. The new item itself is always kept, so that a single item larger than the budget is not blocked forever (use [`max_size`](ClipboardEventListenerBuilder::max_size) to skip it instead).
+  ///
+  /// The accounting is approximate: it only counts the size of the content itself (such as the bytes of an image or of a text, or their sum for [`Body::Multi`]). All of the streams share the same copy of an event, so an item is counted once, no matter how many streams buffer it, until it leaves the last of them. The budget is per-listener, and it is not shared with other listeners.
+  ///
+  /// Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn memory_budget(mut self, max_bytes: u64) -> Self {
+    self.options.memory_budget = Some(max_bytes);
+    self
+  }
+
+  /// Registers a callback that is invoked with every new clipboard item (or error) emitted by the listener, as an alternative to [`new_stream`](ClipboardEventListener::new_stream).
+  ///
+  /// The callback coexists with the streams: every stream still receives the events, and the callback is invoked right after they have been delivered to them.
+  ///
+  /// The callback runs inline on the monitor thread, so the clipboard is not monitored while it is running, and a slow callback delays the delivery of the following events to all of the streams. Work that takes a long time should be moved to another thread. With the [`dedicated_dispatcher`](ClipboardEventListenerBuilder::dedicated_dispatcher), it runs on the dispatcher thread instead.
+  ///
+  /// The callback may safely call the methods of the [`ClipboardEventListener`] (such as creating a new stream) from within, but it is never invoked concurrently with itself. If it panics, the monitor is treated as failed (see [`ClipboardError::MonitorFailed`]) and the callback is not invoked again.
+  #[must_use]
+  #[inline]
+  pub fn on_change<F>(mut self, callback: F) -> Self
+  where
+    F: FnMut(ClipboardResult) + Send + 'static,
+  {
+    self.on_change = Some(ChangeCallback(Box::new(callback)));
+    self
+  }
+
+  /// When enabled, the current content of the clipboard is extracted again when the listener is [`unmuted`](ClipboardEventListener::unmute), and it is emitted if it differs from the last item that was delivered, so that the consumers catch up with the changes that were dropped while the listener was muted.
+  ///
+  /// This works like a call to [`refresh`](ClipboardEventListener::refresh) right after unmuting. Only the final state of the clipboard is emitted, not the intermediate changes that were missed in the meantime. Disabled by default.
+  #[must_use]
+  #[inline]
+  pub const fn emit_on_resume(mut self, emit: bool) -> Self {
+    self.options.emit_on_resume = emit;
+    self
+  }
+
+  /// Sets the maximum amount of time to wait for the monitor thread to exit when the [`ClipboardEventListener`] is dropped.
+  ///
+  /// If the thread is stuck in a blocking call to the OS (for example, while another application holds the clipboard lock) and does not exit in time, it is detached and a warning is logged, so that dropping the listener does not block indefinitely. The detached thread still exits on its own as soon as the blocking call returns.
+  ///
+  /// If unset, the listener waits for the thread to exit without a time limit.
+  #[must_use]
+  #[inline]
+  pub const fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+    self.options.shutdown_timeout = Some(timeout);
+    self
+  }
+
+  /// Sets the X11 [`Selection`] to monitor. If unset, it defaults to [`Selection::Clipboard`].
+  ///
+  /// Arbitrary selections can be monitored by name. The name must not be empty.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms.
+  #[must_use]
+  #[inline]
+  pub fn selection(mut self, selection: impl Into<Selection>) -> Self {
+    self.options.selections = vec![selection.into()];
+    self
+  }
+
+  /// Sets multiple X11 [`Selection`]s to monitor at the same time (for example, both [`Selection::Clipboard`] and [`Selection::Primary`]).
+  ///
+  /// Each event is extracted from the selection that changed, which is reported in the [`selection`](EventMetadata::selection) field of its metadata.
+  ///
+  /// This option only applies to Linux, and it is ignored on other platforms.
+  #[must_use]
+  #[inline]
+  pub fn selections<I, S>(mut self, selections: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<Selection>,
+  {
+    self.options.selections = selections.into_iter().map(Into::into).collect();
+    self
+  }
+
+  /// Ignores the content that is copied from the given applications, such as password managers or the application that uses the listener.
+  ///
+  /// The names are compared (ignoring the case) with the source detected for each change, which is also reported in the [`source`](EventMetadata::source) field of the event's metadata. See its documentation for the format of the names on each platform.
+  ///
+  /// Source detection is best-effort. When the source of a change cannot be detected, its content is not filtered.
+  #[must_use]
+  #[inline]
+  pub fn exclude_sources<I, S>(mut self, sources: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    self.options.excluded_sources = sources.into_iter().map(|s| s.as_ref().to_string()).collect();
     self
   }
 
   /// Spawns the [`ClipboardEventListener`].
   #[inline(never)]
   #[cold]
-  pub fn spawn(self) -> Result<ClipboardEventListener, InitializationError> {
-    let body_senders = Arc::new(BodySenders::new());
+  pub fn spawn(mut self) -> Result<ClipboardEventListener, InitializationError> {
+    let body_senders = BodySenders::new(
+      self.options.history,
+      self.on_change,
+      self.options.dedicated_dispatcher,
+      self.options.memory_budget,
+      self
+        .options
+        .warmup
+        .as_ref()
+        .map(|warmup| warmup.subscribed.clone()),
+    );
+    let shutdown_timeout = self.options.shutdown_timeout;
+    let emit_on_resume = self.options.emit_on_resume;
+
+    // The textual, validated and prefixed custom formats are extracted along with the others
+    for format in self
+      .options
+      .text_custom_formats
+      .iter()
+      .chain(self.options.format_validators.names())
+      .chain(self.options.format_prefixes.names())
+    {
+      if !self.options.custom_formats.contains(format) {
+        self.options.custom_formats.push(format.clone());
+      }
+    }
 
-    let driver = Driver::new(
-      body_senders.clone(),
-      self.interval,
-      self.custom_formats,
-      self.max_bytes,
-      self.gatekeeper,
-    )?;
+    let custom_formats = self.options.custom_formats.clone();
+    let custom_max_size = self.options.size_limits.get(BodyKind::Custom);
+    let pasteboard = self.options.pasteboard.clone();
+
+    let gatekeeper = Arc::new(SharedGatekeeper::new(self.gatekeeper, body_senders.clone()));
+
+    let driver = Driver::new(body_senders.clone(), self.options, gatekeeper.clone())?;
 
     Ok(ClipboardEventListener {
       stop_signal: driver.stop,
+      restart_signal: driver.restart,
       thread_handle: driver.handle,
       body_senders,
-      next_id: AtomicUsize::new(0),
+      gatekeeper,
+      shutdown_timeout,
+      emit_on_resume,
+      custom_formats,
+      custom_max_size,
+      pasteboard,
     })
   }
 }
@@ -110,25 +731,346 @@ impl ClipboardEventListener {
     Self::builder().spawn()
   }
 
+  /// Replaces the [`Gatekeeper`] of this listener while it is running.
+  ///
+  /// The new gatekeeper takes effect starting from the next clipboard change. A check that is already running when this method is called completes with the previous gatekeeper.
+  #[inline]
+  pub fn set_gatekeeper<F>(&self, gatekeeper: F)
+  where
+    F: Fn(ClipboardContext) -> bool + Send + Sync + 'static,
+  {
+    self.gatekeeper.set(gatekeeper);
+  }
+
+  /// Reads the image that is currently on the clipboard, encoded in png format.
+  ///
+  /// Images that are already stored in png format are returned as they are, while the others (such as DIB on Windows or TIFF on macOS) are decoded and re-encoded. Returns `None` if the clipboard does not contain an image.
+  ///
+  /// This reads the clipboard directly, so the gatekeeper and the size limits of the listener do not apply.
+  #[inline(never)]
+  #[cold]
+  pub fn read_image_png(&self) -> Result<Option<Vec<u8>>, ClipboardError> {
+    match read_image()? {
+      Some(Body::PngImage { bytes, .. }) => Ok(Some(bytes)),
+      Some(Body::RawImage(image)) => image.encode_png().map(Some),
+      _ => Ok(None),
+    }
+  }
+
+  /// Reads the current data of all of the custom formats registered with [`with_custom_formats`](ClipboardEventListenerBuilder::with_custom_formats) (or [`with_text_custom_formats`](ClipboardEventListenerBuilder::with_text_custom_formats)), which can be useful for debugging.
+  ///
+  /// The clipboard is opened a single time for all of the formats, so this is more efficient than reading them one by one. The formats are returned in the order in which they were registered, with their raw bytes. The formats that are not on the clipboard, that are empty, or that exceed the [`max_size`](ClipboardEventListenerBuilder::max_size) for custom formats are skipped.
+  ///
+  /// This reads the clipboard directly, so the gatekeeper does not apply. On macOS, the formats are read from the [`macos_pasteboard`](ClipboardEventListenerBuilder::macos_pasteboard) of the listener.
+  #[inline(never)]
+  #[cold]
+  pub fn read_all_custom(&self) -> Result<Vec<CustomFormatData>, ClipboardError> {
+    if self.custom_formats.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    read_custom_formats(
+      &self.custom_formats,
+      self.custom_max_size,
+      &self.pasteboard,
+    )
+  }
+
+  /// Asks the clipboard manager to save the current content of the clipboard, so that it remains available after its owner exits.
+  ///
+  /// On Linux, the content of the clipboard is lost when the application that owns it exits, unless a clipboard manager saves it. This sends the `SAVE_TARGETS` request of the [clipboard manager specification](https://www.freedesktop.org/wiki/ClipboardManager/), and waits for the clipboard manager to confirm it. An error is returned if no clipboard manager is running, or if it fails to save the content.
+  ///
+  /// On other platforms, the content of the clipboard is kept by the system, so this does nothing.
+  #[inline(never)]
+  #[cold]
+  pub fn request_save_targets(&self) -> Result<(), ClipboardError> {
+    #[cfg(target_os = "linux")]
+    {
+      linux::observer::request_save_targets()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+      Ok(())
+    }
+  }
+
+  /// Returns the most recent items emitted by this listener, from oldest to newest.
+  ///
+  /// The items are only recorded if a [`history`](ClipboardEventListenerBuilder::history) capacity was set in the builder. Otherwise, the list is always empty.
+  #[must_use]
+  #[inline]
+  pub fn recent(&self) -> Vec<Arc<Body>> {
+    self.body_senders.recent()
+  }
+
+  /// Mutes the listener, so that nothing is delivered until [`unmute`](ClipboardEventListener::unmute) is called.
+  ///
+  /// The observer keeps monitoring the clipboard and the streams stay open, but the events (as well as the [`change_ticks`](ClipboardEventListener::change_ticks), the [`on_change`](ClipboardEventListenerBuilder::on_change) callback and the [`recent`](ClipboardEventListener::recent) history) are skipped while the listener is muted. The events are dropped, not buffered, so they are not delivered after unmuting.
+  #[inline]
+  pub fn mute(&self) {
+    self.body_senders.mute();
+  }
+
+  /// Resumes the delivery of the events after a call to [`mute`](ClipboardEventListener::mute).
+  ///
+  /// If [`emit_on_resume`](ClipboardEventListenerBuilder::emit_on_resume) is enabled, the current content is emitted if it changed while the listener was muted.
+  #[inline]
+  pub fn unmute(&self) {
+    if self.body_senders.unmute() && self.emit_on_resume {
+      self.body_senders.request_refresh();
+    }
+  }
+
+  /// Checks whether the listener is currently muted.
+  #[must_use]
+  #[inline]
+  pub fn is_muted(&self) -> bool {
+    self.body_senders.is_muted()
+  }
+
+  /// Asks the listener to extract the current content of the clipboard again, and to deliver it to the streams (and to the [`on_change`](ClipboardEventListenerBuilder::on_change) callback) if it differs from the last item that was emitted.
+  ///
+  /// This can be used when the content may have changed without the system signaling it, or after a change was missed (for example, while the listener was [`muted`](ClipboardEventListener::mute)). The content is compared with [`Body::content_eq`], so nothing is emitted if it is still the same. The errors encountered while reading it are delivered as usual.
+  ///
+  /// This does not wait for the extraction: it runs on the monitor thread at its next poll. On Linux, the first of the monitored [`selections`](ClipboardEventListenerBuilder::selections) is read. On macOS, the [`source`](EventMetadata::source) of the refreshed content is not reported.
+  #[inline]
+  pub fn refresh(&self) {
+    self.body_senders.request_refresh();
+  }
+
+  /// Closes all of the streams created by this listener.
+  ///
+  /// The items that were already buffered can still be received, and after that the streams yield `None`, even if they are owned elsewhere. The observer keeps running, and new streams can still be created afterwards.
+  #[inline]
+  pub fn close_all_streams(&self) {
+    self.body_senders.unregister_all();
+  }
+
+  /// Closes the [`ClipboardStream`] with the given [`StreamId`], without having to own it.
+  ///
+  /// This is useful when the streams are owned by tasks that cannot be reached directly, and are only tracked by their id. The items that were already buffered can still be received, and after that the stream yields `None`. Nothing happens if there is no open stream with this id.
+  #[inline]
+  pub fn unsubscribe(&self, id: &StreamId) {
+    self.body_senders.unregister(id);
+  }
+
   /// Creates a [`ClipboardStream`] for receiving clipboard change items as [`Body`](crate::body::Body).
   ///
   /// # Buffer size
   /// This method takes a buffer size. Items are buffered when not received immediately.
   /// The actual buffer capacity is `buf_size + 2`, where the extra `2` accounts for the
   /// number of internal senders used by the library.
+  ///
+  /// With the `tokio-channel` feature, the capacity is exactly `buf_size` (with a minimum of 1).
   #[inline(never)]
   #[cold]
   pub fn new_stream(&mut self, buffer: usize) -> ClipboardStream {
-    let (tx, rx) = mpsc::channel(buffer);
-    let id = StreamId(self.next_id.fetch_add(1, Ordering::Relaxed));
-    self.body_senders.register(id.clone(), tx);
+    let (tx, rx) = event_channel(buffer);
+    let id = self.body_senders.register(tx);
+
+    ClipboardStream {
+      id,
+      body_rx: rx,
+      body_senders: self.body_senders.clone(),
+    }
+  }
+
+  /// Creates a [`ClipboardStream`] like [`new_stream`](ClipboardEventListener::new_stream), which starts by yielding the last item emitted by this listener, so that the current content can be shown right away.
+  ///
+  /// The content is only known once the listener has detected a change, so if nothing has been emitted yet (for example, right after spawning the listener), no initial item is sent and the stream simply waits for the next change. The errors are never replayed.
+  #[inline(never)]
+  #[cold]
+  pub fn new_stream_with_current(&mut self, buffer: usize) -> ClipboardStream {
+    let (tx, rx) = event_channel(buffer);
+    let id = self.body_senders.register_with_last(tx);
 
     ClipboardStream {
       id,
-      body_rx: Box::pin(rx),
+      body_rx: rx,
+      body_senders: self.body_senders.clone(),
+    }
+  }
+
+  /// Creates a [`ChangeTicks`] stream, which yields a unit each time a clipboard change is detected.
+  ///
+  /// The ticks are emitted as soon as a change is detected, before and independently of the extraction of the content, so they are also emitted for the content that is skipped (for example, by the gatekeeper or by the size limits) or that cannot be read. This is the cheapest way to be notified of changes, such as for a simple activity indicator.
+  ///
+  /// If multiple changes happen before the stream is polled, they are merged into a single tick.
+  #[inline(never)]
+  #[cold]
+  pub fn change_ticks(&mut self) -> ChangeTicks {
+    let (tx, rx) = event_channel(1);
+    let id = self.body_senders.register_ticks(tx);
+
+    ChangeTicks {
+      id,
+      tick_rx: rx,
+      body_senders: self.body_senders.clone(),
+    }
+  }
+
+  /// Creates a [`RejectedStream`], which yields a [`RejectedChange`] each time the [`Gatekeeper`] rejects a clipboard change.
+  ///
+  /// This does not affect the gatekeeper or the other streams, and it can be used to log or to count the content that is being suppressed, such as to verify that a privacy policy works as intended. The rejected changes are only reported while at least one of these streams exists.
+  ///
+  /// The buffer size works in the same way as in [`new_stream`](ClipboardEventListener::new_stream).
+  #[inline(never)]
+  #[cold]
+  pub fn rejected_stream(&mut self, buffer: usize) -> RejectedStream {
+    let (tx, rx) = event_channel(buffer);
+    let id = self.body_senders.register_rejections(tx);
+
+    RejectedStream {
+      id,
+      rejection_rx: rx,
       body_senders: self.body_senders.clone(),
     }
   }
+
+  /// Creates a stream that only yields lists of file paths.
+  ///
+  /// It yields the content of [`Body::FileList`] items, as well as the path of images that have been copied from a file. All other kinds of content are ignored, and errors are logged and then discarded.
+  ///
+  /// The buffer size works in the same way as in [`new_stream`](ClipboardEventListener::new_stream).
+  #[inline(never)]
+  #[cold]
+  pub fn file_stream(&mut self, buffer: usize) -> impl Stream<Item = Vec<PathBuf>> + use<> {
+    self.new_stream(buffer).filter_map(|result| {
+      let files = match result {
+        Ok(body) => body.file_paths(),
+        Err(e) => {
+          debug!("Discarding error in file stream: {e}");
+          None
+        }
+      };
+
+      future::ready(files)
+    })
+  }
+
+  /// Blocks the current thread until a clipboard change carrying the given format is observed, and then returns its content.
+  ///
+  /// The format must be one of those extracted by the listener, as set with [`with_custom_formats`](ClipboardEventListenerBuilder::with_custom_formats) or [`with_text_formats`](ClipboardEventListenerBuilder::with_text_formats). All other changes are ignored, as well as the errors encountered while reading them, except for [`ClipboardError::MonitorFailed`], which is returned.
+  ///
+  /// Returns `None` if the timeout expires first, or if the streams are closed (for example, with [`close_all_streams`](ClipboardEventListener::close_all_streams)).
+  ///
+  /// Since this blocks, it should not be called from within an async context.
+  #[inline(never)]
+  #[cold]
+  pub fn wait_for_format(
+    &mut self,
+    name: &str,
+    timeout: Option<Duration>,
+  ) -> Result<Option<Arc<Body>>, ClipboardError> {
+    struct ThreadWaker(std::thread::Thread);
+
+    impl futures::task::ArcWake for ThreadWaker {
+      fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.unpark();
+      }
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut stream = self.new_stream(1);
+
+    let waker = futures::task::waker(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+      match stream.poll_next_unpin(&mut cx) {
+        Poll::Ready(Some(Ok(body))) if body.has_format(name) => return Ok(Some(body)),
+        Poll::Ready(Some(Err(e @ ClipboardError::MonitorFailed { .. }))) => return Err(e),
+        Poll::Ready(Some(_)) => continue,
+        Poll::Ready(None) => return Ok(None),
+        Poll::Pending => {}
+      }
+
+      match deadline {
+        Some(deadline) => {
+          let remaining = deadline.saturating_duration_since(Instant::now());
+
+          if remaining.is_zero() {
+            return Ok(None);
+          }
+
+          std::thread::park_timeout(remaining);
+        }
+        None => std::thread::park(),
+      }
+    }
+  }
+
+  /// Consumes the listener, waits for the first clipboard change that is successfully extracted, and then stops the observer and returns its content.
+  ///
+  /// This is a shortcut for creating a stream, taking its first item and dropping the listener, which can be useful for one-shot captures, scripts and tests. The errors encountered while reading the changes are ignored, except for [`ClipboardError::MonitorFailed`], which is returned.
+  ///
+  /// If a timeout is given and it expires first, [`ClipboardError::Timeout`] is returned.
+  #[inline(never)]
+  #[cold]
+  pub async fn once(mut self, timeout: Option<Duration>) -> Result<Arc<Body>, ClipboardError> {
+    let mut stream = self.new_stream(1);
+
+    // Closing the stream wakes it up, which ends the wait
+    let timer = timeout.map(|timeout| {
+      let body_senders = Arc::downgrade(&self.body_senders);
+      let done = Arc::new(AtomicBool::new(false));
+      let done_clone = done.clone();
+      let deadline = Instant::now() + timeout;
+
+      let handle = std::thread::spawn(move || {
+        while !done_clone.load(Ordering::Relaxed) {
+          let remaining = deadline.saturating_duration_since(Instant::now());
+
+          if remaining.is_zero() {
+            if let Some(body_senders) = body_senders.upgrade() {
+              body_senders.unregister_all();
+            }
+
+            break;
+          }
+
+          std::thread::park_timeout(remaining);
+        }
+      });
+
+      (handle, done)
+    });
+
+    let result = loop {
+      match stream.next().await {
+        Some(Ok(body)) => break Ok(body),
+        Some(Err(e @ ClipboardError::MonitorFailed { .. })) => break Err(e),
+        Some(Err(e)) => debug!("Ignoring error while waiting for the first change: {e}"),
+        // The listener is owned here, so only the timer can close the stream
+        None => break Err(ClipboardError::Timeout),
+      }
+    };
+
+    // Stops the timer early
+    if let Some((handle, done)) = timer {
+      done.store(true, Ordering::Relaxed);
+      handle.thread().unpark();
+    }
+
+    result
+  }
+
+  /// Creates a [`ClipboardStream`] like [`new_stream`](ClipboardEventListener::new_stream), and enables the automatic restart of the underlying observer.
+  ///
+  /// When the observer stops because of a fatal error (such as a lost connection to the X11 server), the [`ClipboardError::MonitorFailed`] error is still forwarded to the streams, but instead of closing, the observer is restarted after a backoff period (which starts at 500 milliseconds and doubles after every consecutive failure, up to 30 seconds).
+  ///
+  /// Since the observer is shared, enabling the restart affects all of the streams created by this listener, and not just the one returned by this method.
+  ///
+  /// # Delivery semantics
+  /// Events are delivered at most once. Changes that occur while the observer is down are lost, and the content that is on the clipboard when the observer is restarted is not emitted again. The next event will be the first change detected after the restart.
+  #[inline(never)]
+  #[cold]
+  pub fn resilient_stream(&mut self, buffer: usize) -> ClipboardStream {
+    self.restart_signal.store(true, Ordering::Relaxed);
+
+    self.new_stream(buffer)
+  }
 }
 
 impl Drop for ClipboardEventListener {
@@ -136,10 +1078,34 @@ impl Drop for ClipboardEventListener {
     // Change the AtomicBool, stop the observers
     self.stop_signal.store(true, Ordering::Relaxed);
 
-    // Wait for the thread to finish
     // We use option + take here because join consumes the value
-    if let Some(handle) = self.thread_handle.take() {
-      handle.join().unwrap();
+    let Some(handle) = self.thread_handle.take() else {
+      return;
+    };
+
+    // Wait for the thread to finish, up to the shutdown timeout
+    if let Some(timeout) = self.shutdown_timeout {
+      let deadline = Instant::now() + timeout;
+
+      while !handle.is_finished() {
+        if Instant::now() >= deadline {
+          // Dropping the handle detaches the thread
+          warn!(
+            "The clipboard monitor thread did not exit within {timeout:?}. Detaching it..."
+          );
+          return;
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+      }
+    }
+
+    // Panics are caught by the supervisor, but this must never panic in any case
+    if let Err(payload) = handle.join() {
+      error!(
+        "The clipboard monitor thread panicked: {}",
+        panic_message(&*payload)
+      );
     }
   }
 }
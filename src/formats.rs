@@ -10,6 +10,17 @@ pub struct Format {
   pub(crate) id: objc2::rc::Retained<objc2_foundation::NSString>,
 }
 
+/// The platform-specific identifier of a [`Format`], which can be passed to other clipboard APIs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FormatId {
+  /// The id of a clipboard format on Windows, as used by functions like `GetClipboardData`.
+  Windows(u32),
+  /// The atom of a selection target on X11.
+  X11(u32),
+  /// A pasteboard type on macOS. Pasteboard types are identified by their name, so this is the same as [`Format::name`].
+  Pasteboard(Arc<str>),
+}
+
 impl Format {
   /// Returns the name of the format
   #[must_use]
@@ -17,6 +28,104 @@ impl Format {
   pub fn name(&self) -> &str {
     &self.name
   }
+
+  /// Returns the platform-specific identifier of the format.
+  #[must_use]
+  #[inline]
+  #[cfg_attr(not(target_os = "macos"), allow(clippy::missing_const_for_fn))]
+  pub fn id(&self) -> FormatId {
+    #[cfg(windows)]
+    {
+      FormatId::Windows(self.id)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+      FormatId::X11(self.id)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+      FormatId::Pasteboard(self.name.clone())
+    }
+  }
+}
+
+// A validator registered with `with_validated_custom_format`
+pub(crate) type FormatValidator = Arc<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+// The validators of the custom formats, in the order in which they were registered
+#[derive(Clone, Default)]
+pub(crate) struct FormatValidators {
+  validators: Vec<(Arc<str>, FormatValidator)>,
+}
+
+impl FormatValidators {
+  // Replaces the validator if the format already has one
+  pub(crate) fn insert(&mut self, name: Arc<str>, validator: FormatValidator) {
+    if let Some(entry) = self.validators.iter_mut().find(|(n, _)| *n == name) {
+      entry.1 = validator;
+    } else {
+      self.validators.push((name, validator));
+    }
+  }
+
+  pub(crate) fn names(&self) -> impl Iterator<Item = &Arc<str>> {
+    self.validators.iter().map(|(name, _)| name)
+  }
+
+  // Runs the validator of the format (if it has one), and uses an error to skip the content if it is rejected
+  pub(crate) fn validate(&self, name: &str, data: &[u8]) -> Result<(), ErrorWrapper> {
+    if let Some((_, validator)) = self.validators.iter().find(|(n, _)| n.as_ref() == name)
+      && !validator(data)
+    {
+      debug!(
+        "The content of the custom format `{name}` was rejected by its validator. Skipping it..."
+      );
+
+      return Err(ErrorWrapper::EmptyContent);
+    }
+
+    Ok(())
+  }
+}
+
+// The custom formats registered with `with_custom_format_prefix`, with the maximum number of bytes
+// that are read from each of them
+#[derive(Clone, Default)]
+pub(crate) struct FormatPrefixes {
+  prefixes: Vec<(Arc<str>, usize)>,
+}
+
+impl FormatPrefixes {
+  // Replaces the length if the format was already registered
+  pub(crate) fn insert(&mut self, name: Arc<str>, max_bytes: usize) {
+    if let Some(entry) = self.prefixes.iter_mut().find(|(n, _)| *n == name) {
+      entry.1 = max_bytes;
+    } else {
+      self.prefixes.push((name, max_bytes));
+    }
+  }
+
+  pub(crate) fn names(&self) -> impl Iterator<Item = &Arc<str>> {
+    self.prefixes.iter().map(|(name, _)| name)
+  }
+
+  pub(crate) fn get(&self, name: &str) -> Option<usize> {
+    self
+      .prefixes
+      .iter()
+      .find(|(n, _)| n.as_ref() == name)
+      .map(|(_, max_bytes)| *max_bytes)
+  }
+}
+
+// Keeps the first bytes of the data, and returns whether it was cut short
+pub(crate) fn truncate_prefix(mut data: Vec<u8>, max_bytes: usize) -> (Vec<u8>, bool) {
+  let truncated = data.len() > max_bytes;
+  data.truncate(max_bytes);
+
+  (data, truncated)
 }
 
 /// A struct that represents the list of formats currently available on the clipboard.
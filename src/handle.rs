@@ -0,0 +1,63 @@
+use std::sync::mpsc::Receiver;
+
+use crate::*;
+
+/// A handle to a clipboard monitor started with [`spawn_channel`], which delivers its items to a [`Receiver`].
+///
+/// The monitoring stops when the handle is dropped, and the receiver then yields the items that were already sent before disconnecting.
+pub struct ClipboardHandle {
+  listener: ClipboardEventListener,
+}
+
+impl ClipboardHandle {
+  /// Returns the [`ClipboardEventListener`] that this handle controls, which can be used to mute it or to inspect the recent items.
+  #[must_use]
+  #[inline]
+  pub const fn listener(&self) -> &ClipboardEventListener {
+    &self.listener
+  }
+
+  /// Returns a mutable reference to the [`ClipboardEventListener`] that this handle controls, which can be used to create additional streams.
+  #[inline]
+  pub const fn listener_mut(&mut self) -> &mut ClipboardEventListener {
+    &mut self.listener
+  }
+}
+
+/// Starts monitoring the clipboard with the default options, and returns a [`ClipboardHandle`] along with a plain [`Receiver`] for the clipboard items.
+///
+/// This is the simplest way to receive the clipboard changes, without any streams or async runtime at the call site. Use [`ClipboardEventListenerBuilder::spawn_channel`] to customize the options.
+///
+/// The channel is unbounded, so the items are never dropped and the monitor is never blocked, but they accumulate in memory if they are not received.
+#[inline]
+pub fn spawn_channel() -> Result<(ClipboardHandle, Receiver<ClipboardResult>), InitializationError> {
+  ClipboardEventListener::builder().spawn_channel()
+}
+
+impl<G: Gatekeeper> ClipboardEventListenerBuilder<G> {
+  /// Spawns the [`ClipboardEventListener`] like [`spawn`](ClipboardEventListenerBuilder::spawn), but returns a [`ClipboardHandle`] and a plain [`Receiver`] for the clipboard items. See [`spawn_channel`] for more information.
+  ///
+  /// The items are sent from the [`on_change`](ClipboardEventListenerBuilder::on_change) callback, so a callback that was set before still runs, before each item is sent.
+  #[inline(never)]
+  #[cold]
+  pub fn spawn_channel(
+    mut self,
+  ) -> Result<(ClipboardHandle, Receiver<ClipboardResult>), InitializationError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let mut on_change = self.on_change.take();
+
+    self.on_change = Some(ChangeCallback(Box::new(move |result: ClipboardResult| {
+      if let Some(callback) = on_change.as_mut() {
+        (callback.0)(result.clone());
+      }
+
+      // Only fails if the receiver was dropped
+      let _ = tx.send(result);
+    })));
+
+    let listener = self.spawn()?;
+
+    Ok((ClipboardHandle { listener }, rx))
+  }
+}
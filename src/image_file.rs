@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use crate::*;
+
+// Loads and decodes the image file at the given path.
+// Returns `None` if the file cannot be read or decoded, or if it exceeds the size limit,
+// so that the caller can fall back to the file list.
+pub(crate) fn load_image_file(
+  path: &Path,
+  max_size: Option<u64>,
+) -> Result<Option<Body>, ErrorWrapper> {
+  let size = match std::fs::metadata(path) {
+    Ok(metadata) if metadata.is_file() => metadata.len(),
+    Ok(_) => return Ok(None),
+    Err(e) => {
+      debug!("Failed to read metadata for `{}`: {e}", path.display());
+      return Ok(None);
+    }
+  };
+
+  if let Some(max_size) = max_size
+    && size > max_size
+  {
+    debug!(
+      "Found image file with {} size, beyond maximum allowed size. Emitting the file list...",
+      HumanBytes(usize::try_from(size).unwrap_or(usize::MAX))
+    );
+
+    return Ok(None);
+  }
+
+  match decode_image_file(path) {
+    Ok(image) => {
+      if log::log_enabled!(log::Level::Debug) {
+        image.log_info();
+      }
+
+      Ok(Some(Body::RawImage(image)))
+    }
+    Err(e) => {
+      debug!("{e}");
+      Ok(None)
+    }
+  }
+}
+
+// Reads and decodes the image file at the given path
+pub(crate) fn decode_image_file(path: &Path) -> Result<RawImage, ClipboardError> {
+  let bytes = std::fs::read(path).map_err(|e| {
+    ClipboardError::read_with_context(format_args!("Failed to read `{}`", path.display()), e)
+  })?;
+
+  let image = image::load_from_memory(&bytes).map_err(|e| {
+    ClipboardError::read_with_context(
+      format_args!("Failed to load image from `{}`", path.display()),
+      e,
+    )
+  })?;
+
+  Ok(RawImage::from_dynamic_image(image, Some(path.to_path_buf())))
+}
@@ -0,0 +1,155 @@
+use std::sync::Condvar;
+
+use crate::*;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(200);
+
+// The lowest `min` of an adaptive interval, since a zero interval could never decay by doubling
+const MIN_ADAPTIVE_INTERVAL: Duration = Duration::from_millis(1);
+
+// How long the fastest interval is used after a change, before it starts decaying
+const ACTIVE_WINDOW: Duration = Duration::from_secs(5);
+
+// The time of the most recent subscription of a content stream, shared by the listener and the observer.
+// The observer is woken up when a new subscription is recorded, so that the warmup starts right away.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SubscriptionTime(Arc<(Mutex<Subscription>, Condvar)>);
+
+#[derive(Debug, Default)]
+struct Subscription {
+  time: Option<Instant>,
+  // Incremented with every subscription, to tell them apart from the spurious wakeups
+  count: u64,
+}
+
+impl SubscriptionTime {
+  pub(crate) fn record(&self) {
+    let (subscription, subscribed) = &*self.0;
+
+    let mut subscription = subscription.lock().unwrap();
+    subscription.time = Some(Instant::now());
+    subscription.count += 1;
+
+    subscribed.notify_all();
+  }
+
+  fn elapsed(&self) -> Option<Duration> {
+    self
+      .0
+      .0
+      .lock()
+      .unwrap()
+      .time
+      .map(|time| time.elapsed())
+  }
+
+  // Sleeps for the given duration, or until a new subscription is recorded
+  fn wait(&self, timeout: Duration) {
+    let (subscription, subscribed) = &*self.0;
+
+    let subscription = subscription.lock().unwrap();
+    let count = subscription.count;
+
+    let _ = subscribed
+      .wait_timeout_while(subscription, timeout, |subscription| {
+        subscription.count == count
+      })
+      .unwrap();
+  }
+}
+
+// A faster interval that is used for a while after each subscription
+#[derive(Debug, Clone)]
+pub(crate) struct Warmup {
+  pub(crate) duration: Duration,
+  pub(crate) interval: Duration,
+  pub(crate) subscribed: SubscriptionTime,
+}
+
+// The polling interval of an observer.
+//
+// With a fixed interval, `min` and `max` are the same.
+// With an adaptive interval, the observer polls at `min` for a while after each change, and then
+// the interval doubles every `ACTIVE_WINDOW` until it reaches `max`.
+// During a warmup, the warmup interval is used if it is faster.
+#[derive(Debug, Clone)]
+pub(crate) struct PollInterval {
+  min: Duration,
+  max: Duration,
+  last_change: Option<Instant>,
+  warmup: Option<Warmup>,
+}
+
+impl PollInterval {
+  pub(crate) fn new(
+    interval: Option<Duration>,
+    adaptive: Option<(Duration, Duration)>,
+    warmup: Option<Warmup>,
+  ) -> Self {
+    let (min, max) = match adaptive {
+      Some((min, max)) => (min.max(MIN_ADAPTIVE_INTERVAL), max),
+      None => {
+        let interval = interval.unwrap_or(DEFAULT_INTERVAL);
+
+        (interval, interval)
+      }
+    };
+
+    Self {
+      min,
+      max: max.max(min),
+      last_change: None,
+      warmup,
+    }
+  }
+
+  // Switches to the fastest interval
+  pub(crate) fn record_change(&mut self) {
+    if self.min != self.max {
+      self.last_change = Some(Instant::now());
+    }
+  }
+
+  // Returns the interval to wait before the next poll
+  pub(crate) fn current(&self) -> Duration {
+    let interval = self.current_without_warmup();
+
+    match &self.warmup {
+      Some(warmup)
+        if warmup
+          .subscribed
+          .elapsed()
+          .is_some_and(|elapsed| elapsed < warmup.duration) =>
+      {
+        interval.min(warmup.interval)
+      }
+      _ => interval,
+    }
+  }
+
+  fn current_without_warmup(&self) -> Duration {
+    let Some(last_change) = self.last_change else {
+      return self.max;
+    };
+
+    let mut interval = self.min;
+    let mut elapsed = last_change.elapsed();
+
+    while elapsed >= ACTIVE_WINDOW && interval < self.max {
+      interval *= 2;
+      elapsed -= ACTIVE_WINDOW;
+    }
+
+    interval.min(self.max)
+  }
+
+  // Waits for the next poll. With a warmup, a new subscription ends the wait early.
+  pub(crate) fn sleep(&self) {
+    let interval = self.current();
+
+    match &self.warmup {
+      Some(warmup) => warmup.subscribed.wait(interval),
+      None => std::thread::sleep(interval),
+    }
+  }
+}
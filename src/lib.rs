@@ -1,23 +1,23 @@
 #![doc = include_str!("../README.md")]
 
-use futures::{
-  Stream,
-  channel::mpsc::{self, Receiver, Sender},
-};
+use futures::{Stream, StreamExt, future};
+#[cfg(not(feature = "tracing"))]
 use log::{debug, error, info, trace, warn};
+#[cfg(feature = "tracing")]
+use tracing::{debug, error, info, trace, warn};
 use std::{
-  collections::HashMap,
+  collections::{HashMap, VecDeque},
   fmt::Display,
   path::PathBuf,
   pin::Pin,
   sync::{
     Arc, Mutex,
-    atomic::{AtomicBool, AtomicUsize, Ordering},
-    mpsc::sync_channel,
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    mpsc::{SyncSender, sync_channel},
   },
   task::{Context, Poll},
   thread::JoinHandle,
-  time::Duration,
+  time::{Duration, Instant},
 };
 
 mod body;
@@ -26,21 +26,67 @@ pub use body::*;
 mod body_senders;
 use body_senders::*;
 
+mod channel;
+use channel::*;
+
+mod clipboard_image;
+pub use clipboard_image::*;
+
 mod error;
 pub use error::*;
 
+mod event;
+pub use event::*;
+
 mod event_listener;
 pub use event_listener::*;
 
+mod image_file;
+use image_file::*;
+
+mod interval;
+use interval::*;
+
+mod limits;
+pub use limits::*;
+
 mod logging;
 use logging::*;
 
+#[cfg(feature = "testing")]
+mod mock;
+#[cfg(feature = "testing")]
+pub use mock::*;
+
+mod options;
+pub use options::*;
+
+mod read;
+pub use read::*;
+
+mod selection;
+pub use selection::*;
+
+mod shared_gatekeeper;
+use shared_gatekeeper::*;
+
+#[cfg(feature = "serde")]
+mod stored;
+#[cfg(feature = "serde")]
+pub use stored::*;
+
 mod stream;
 pub use stream::*;
 
+mod supervisor;
+use supervisor::*;
+
 mod formats;
 pub use formats::*;
 
+mod handle;
+pub use handle::*;
+
 #[cfg(target_os = "linux")]
 mod linux {
   pub(crate) mod driver;
@@ -49,12 +95,16 @@ mod linux {
 #[cfg(target_os = "macos")]
 mod macos {
   pub(crate) mod driver;
+  mod main_thread;
   pub(crate) mod observer;
 }
+/// Utilities that are specific to Windows.
 #[cfg(windows)]
-mod win {
+pub mod win {
+  mod cf_html;
+  pub use cf_html::*;
   mod driver;
-  mod observer;
+  pub(crate) mod observer;
 }
 
 pub(crate) trait Observer {
@@ -67,18 +117,37 @@ pub(crate) struct Driver {
   /// This is cloned and passed to the Observer threads to give them the interruption signal
   pub(crate) stop: Arc<AtomicBool>,
 
+  /// Signals whether the Observer should be restarted after a fatal error
+  pub(crate) restart: Arc<AtomicBool>,
+
   /// This is the handle of the spawned Observer thread.
   pub(crate) handle: Option<JoinHandle<()>>,
 }
 
+// The data read through `ClipboardContext::get_bytes`, which is kept for a single check
+pub(crate) type FormatCache = std::cell::RefCell<HashMap<Arc<str>, Option<Arc<[u8]>>>>;
+
 /// The context for the clipboard content
 #[derive(Clone, Copy)]
 pub struct ClipboardContext<'a> {
   formats: &'a Formats,
+  // The size limit for the reads made with `get_bytes`
+  max_size: Option<u64>,
+  cache: &'a FormatCache,
+  source: ContextSource<'a>,
+}
+
+// Where the data of the formats is read from
+#[derive(Clone, Copy)]
+pub(crate) enum ContextSource<'a> {
   #[cfg(target_os = "linux")]
-  x11: &'a linux::observer::X11Context,
+  X11(&'a linux::observer::X11Context),
   #[cfg(target_os = "macos")]
-  pasteboard: &'a objc2::rc::Retained<objc2_app_kit::NSPasteboard>,
+  Pasteboard(&'a objc2::rc::Retained<objc2_app_kit::NSPasteboard>),
+  #[cfg(windows)]
+  Clipboard(std::marker::PhantomData<&'a ()>),
+  #[cfg(feature = "testing")]
+  Mock(&'a MockClipboard),
 }
 
 impl ClipboardContext<'_> {
@@ -122,6 +191,28 @@ impl ClipboardContext<'_> {
       .find(|d| d.name.as_ref() == name)
       .and_then(|f| self.get_data(f))
   }
+
+  /// Reads the data of a particular format, only when it is needed.
+  ///
+  /// Unlike [`get_format_data`](ClipboardContext::get_format_data), the data is cached for the rest of the check, so calling this again for the same format does not read it again. The read is also bounded by the size limit of the custom formats (see [`limits`](ClipboardEventListenerBuilder::limits)), so `None` is returned for the data that exceeds it, as well as for the formats that are not available or cannot be read.
+  ///
+  /// This allows a [`Gatekeeper`] to make decisions based on the content, while keeping the checks that only need [`has_format`](ClipboardContext::has_format) cheap.
+  #[must_use]
+  pub fn get_bytes(&self, name: &str) -> Option<Arc<[u8]>> {
+    if let Some(cached) = self.cache.borrow().get(name) {
+      return cached.clone();
+    }
+
+    let format = self.get_format(name)?;
+    let bytes = self.read_bounded(format).map(Arc::from);
+
+    self
+      .cache
+      .borrow_mut()
+      .insert(format.name.clone(), bytes.clone());
+
+    bytes
+  }
 }
 
 /// Receives the [`ClipboardContext`] and returns a boolean that indicates whether the content should
@@ -129,6 +220,9 @@ impl ClipboardContext<'_> {
 ///
 /// Can be useful to read particular formats like `ExcludeClipboardContentFromMonitorProcessing` that are
 /// placed in the clipboard by other applications.
+///
+/// On macOS, the equivalent markers are the `org.nspasteboard.ConcealedType` and `org.nspasteboard.TransientType`
+/// types (see [`respect_nspasteboard_conventions`](ClipboardEventListenerBuilder::respect_nspasteboard_conventions)).
 pub trait Gatekeeper: Send + Sync + 'static {
   fn check(&self, ctx: ClipboardContext) -> bool;
 }
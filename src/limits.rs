@@ -0,0 +1,174 @@
+use crate::*;
+
+/// The size and count limits of a listener, which can be set all at once with [`limits`](crate::ClipboardEventListenerBuilder::limits).
+///
+/// Each field has the same effect as the builder method with the same name.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+  /// See [`max_size`](crate::ClipboardEventListenerBuilder::max_size).
+  pub max_size: Option<u64>,
+  /// The limits for specific kinds of content. See [`max_size_for`](crate::ClipboardEventListenerBuilder::max_size_for).
+  pub max_size_overrides: HashMap<BodyKind, u64>,
+  /// See [`min_size`](crate::ClipboardEventListenerBuilder::min_size).
+  pub min_size: Option<u64>,
+  /// See [`max_file_count`](crate::ClipboardEventListenerBuilder::max_file_count).
+  pub max_file_count: Option<usize>,
+  /// See [`truncate_file_lists`](crate::ClipboardEventListenerBuilder::truncate_file_lists).
+  pub truncate_file_lists: bool,
+}
+
+/// An amount of bytes that can be passed to the size limit methods of the builder, such as [`max_size`](crate::ClipboardEventListenerBuilder::max_size).
+///
+/// It is implemented for `u32`, `u64` and `usize`, as well as for `i32`, so that unsuffixed integer literals can be used. A negative `i32` is not a valid size, and it causes a panic.
+///
+/// This trait is sealed, and it cannot be implemented outside of this crate.
+pub trait ByteSize: private::Sealed {
+  #[doc(hidden)]
+  fn into_bytes(self) -> u64;
+}
+
+mod private {
+  pub trait Sealed {}
+}
+
+impl private::Sealed for u32 {}
+impl ByteSize for u32 {
+  #[inline]
+  fn into_bytes(self) -> u64 {
+    u64::from(self)
+  }
+}
+
+impl private::Sealed for u64 {}
+impl ByteSize for u64 {
+  #[inline]
+  fn into_bytes(self) -> u64 {
+    self
+  }
+}
+
+impl private::Sealed for usize {}
+impl ByteSize for usize {
+  #[inline]
+  fn into_bytes(self) -> u64 {
+    self as u64
+  }
+}
+
+impl private::Sealed for i32 {}
+impl ByteSize for i32 {
+  #[inline]
+  fn into_bytes(self) -> u64 {
+    u64::try_from(self).unwrap_or_else(|_| panic!("Invalid size limit: {self}"))
+  }
+}
+
+// The maximum allowed sizes, with the overrides for specific kinds of content,
+// and the minimum size
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SizeLimits {
+  pub(crate) global: Option<u64>,
+  pub(crate) overrides: HashMap<BodyKind, u64>,
+  pub(crate) min: Option<u64>,
+}
+
+impl SizeLimits {
+  // Returns the limit that applies to the given kind
+  pub(crate) fn get(&self, kind: BodyKind) -> Option<u64> {
+    self.overrides.get(&kind).copied().or(self.global)
+  }
+
+  // Checks whether the content is smaller than the minimum size.
+  // File lists are not affected.
+  pub(crate) fn is_below_min(&self, body: &Body) -> bool {
+    match (self.min, body.content_len()) {
+      (Some(min), Some(len)) if (len as u64) < min => {
+        debug!(
+          "Found content with {} size, below minimum allowed size. Skipping it...",
+          HumanBytes(len)
+        );
+
+        true
+      }
+      _ => false,
+    }
+  }
+}
+
+// Removes or expands the directories in a file list.
+// Returns an error to skip the content if no files are left.
+pub(crate) fn handle_directories(
+  files: Vec<PathBuf>,
+  handling: DirectoryHandling,
+) -> Result<Vec<PathBuf>, ErrorWrapper> {
+  let files: Vec<PathBuf> = match handling {
+    DirectoryHandling::Keep => return Ok(files),
+    DirectoryHandling::Skip => files.into_iter().filter(|path| !path.is_dir()).collect(),
+    DirectoryHandling::ExpandShallow => files
+      .into_iter()
+      .flat_map(|path| {
+        if !path.is_dir() {
+          return vec![path];
+        }
+
+        match std::fs::read_dir(&path) {
+          Ok(entries) => {
+            let mut children: Vec<PathBuf> = entries
+              .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+              .filter(|child| !child.is_dir())
+              .collect();
+
+            // The order of the entries depends on the file system
+            children.sort();
+
+            children
+          }
+          Err(e) => {
+            debug!("Failed to read directory `{}`: {e}", path.display());
+            vec![path]
+          }
+        }
+      })
+      .collect(),
+  };
+
+  if files.is_empty() {
+    debug!("Found file list with no files left after handling the directories. Skipping it...");
+    return Err(ErrorWrapper::EmptyContent);
+  }
+
+  Ok(files)
+}
+
+// Applies the maximum file count to a file list.
+// Returns the (possibly truncated) list, and whether it was truncated.
+pub(crate) fn limit_file_count(
+  mut files: Vec<PathBuf>,
+  max_count: Option<usize>,
+  truncate: bool,
+) -> Result<(Vec<PathBuf>, bool), ErrorWrapper> {
+  match max_count {
+    Some(max_count) if files.len() > max_count => {
+      if truncate {
+        debug!(
+          "Found file list with {} elements, beyond maximum allowed count. Truncating it to {max_count} elements...",
+          files.len()
+        );
+
+        files.truncate(max_count);
+
+        Ok((files, true))
+      } else {
+        debug!(
+          "Found file list with {} elements, beyond maximum allowed count. Skipping it...",
+          files.len()
+        );
+
+        Err(ErrorWrapper::SizeTooLarge)
+      }
+    }
+    _ => Ok((files, false)),
+  }
+}
@@ -4,36 +4,30 @@ impl Driver {
   #[inline(never)]
   #[cold]
   /// Construct [`Driver`] and spawn a thread for monitoring clipboard events
-  pub(crate) fn new<G: Gatekeeper>(
+  pub(crate) fn new(
     body_senders: Arc<BodySenders>,
-    interval: Option<Duration>,
-    custom_formats: Vec<Arc<str>>,
-    max_bytes: Option<u32>,
-    gatekeeper: G,
+    options: ObserverOptions,
+    gatekeeper: Arc<SharedGatekeeper>,
   ) -> Result<Self, InitializationError> {
     let stop = Arc::new(AtomicBool::new(false));
+    let restart = Arc::new(AtomicBool::new(false));
 
     let stop_cl = stop.clone();
+    let restart_cl = restart.clone();
 
     let (init_tx, init_rx) = sync_channel(0);
 
     let handle = std::thread::spawn(move || {
-      match LinuxObserver::new(stop_cl, interval, max_bytes, custom_formats, gatekeeper) {
-        Ok(mut observer) => {
-          init_tx.send(Ok(())).unwrap();
-
-          observer.observe(body_senders);
-        }
-        Err(e) => {
-          init_tx.send(Err(e)).unwrap();
-        }
-      };
+      supervise(&stop_cl, &restart_cl, &body_senders, init_tx, || {
+        LinuxObserver::new(stop_cl.clone(), options.clone(), gatekeeper.clone())
+      });
     });
 
     // Block until we get an init signal
     match init_rx.recv() {
       Ok(Ok(())) => Ok(Self {
         stop,
+        restart,
         handle: Some(handle),
       }),
       Ok(Err(e)) => Err(InitializationError(e)),
@@ -1,30 +1,80 @@
 use crate::*;
 use percent_encoding::percent_decode;
-use std::time::Instant;
+use std::{
+  cell::{Cell, RefCell},
+  time::Instant,
+};
 use x11rb::{
   CURRENT_TIME,
   connection::Connection,
   protocol::{
     Event, xfixes,
-    xproto::{Atom, ConnectionExt, CreateWindowAux, EventMask, Property, WindowClass},
+    xproto::{
+      Atom, AtomEnum, ConnectionExt, CreateWindowAux, EventMask, Property, Window, WindowClass,
+    },
   },
   rust_connection::RustConnection,
 };
 
-pub(crate) struct LinuxObserver<G: Gatekeeper = DefaultGatekeeper> {
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct LinuxObserver {
   stop_signal: Arc<AtomicBool>,
-  interval: Duration,
-  max_size: Option<u32>,
+  interval: PollInterval,
+  // Blocks until the next event rather than sleeping for the interval
+  event_driven: bool,
+  size_limits: SizeLimits,
+  max_file_count: Option<usize>,
+  truncate_file_lists: bool,
+  directory_handling: DirectoryHandling,
+  include_text_with_html: bool,
+  coalesce_text_with_files: bool,
+  suppress_no_matching_format: bool,
+  load_image_from_single_file: bool,
+  image_output: ImageOutput,
+  image_path_mode: ImagePathMode,
+  keep_source_bytes: bool,
+  deliver_undecoded_on_error: bool,
+  collect_custom_formats: bool,
   custom_formats: Formats,
+  // The custom formats that are decoded as text
+  text_custom_formats: Vec<Arc<str>>,
+  format_validators: FormatValidators,
+  format_prefixes: FormatPrefixes,
+  text_formats: Formats,
   x11: X11Context,
+  // The monitored selections, with their atoms
+  selections: Vec<(Atom, Selection)>,
+  excluded_sources: Vec<String>,
+  skip_unchanged_targets: bool,
+  // Skips the duplicates that are served by the known clipboard managers
+  skip_clipboard_manager_duplicates: bool,
+  // The sorted targets that were last seen for each selection
+  last_targets: HashMap<Atom, Vec<Atom>>,
   atoms_cache: HashMap<Atom, Arc<str>>,
-  gatekeeper: G,
+  gatekeeper: Arc<SharedGatekeeper>,
+  // The metadata for the event currently being extracted
+  metadata: EventMetadata,
 }
 
+// Every context owns its connection and its requestor window, and the properties used to
+// receive the data (`DATA` and `METADATA`) belong to that window.
+// This means that reads from different contexts (such as the observer and `read_text`) never
+// share a property. A single context is not `Sync`, so its reads are always sequential.
 pub(crate) struct X11Context {
   conn: RustConnection,
   win_id: u32,
   atoms: Atoms,
+  // The atom of the selection that is currently being read
+  selection: Cell<Atom>,
+  // Interrupts the waiting loops when the listener is dropped
+  stop_signal: Arc<AtomicBool>,
+  // Set when a read goes through the INCR path, reset before each extraction
+  used_incr: Cell<bool>,
+  // The longest wait between the polls for the events of a transfer
+  max_event_wait: Duration,
+  // The selection changes received while waiting for the events of a transfer,
+  // which are handled by the observer once the transfer is over
+  deferred_events: RefCell<VecDeque<Event>>,
 }
 
 impl ClipboardContext<'_> {
@@ -32,130 +82,219 @@ impl ClipboardContext<'_> {
   #[must_use]
   #[inline]
   pub fn get_data(&self, format: &Format) -> Option<Vec<u8>> {
-    self
-      .x11
-      .request_and_read_property(format.id, self.x11.atoms.DATA)
-      .ok()
+    match self.source {
+      ContextSource::X11(x11) => x11
+        .request_and_read_property(format.id, x11.atoms.DATA)
+        .ok(),
+      #[cfg(feature = "testing")]
+      ContextSource::Mock(mock) => mock.data(format),
+    }
+  }
+
+  // Reads the data of the format, unless it exceeds the size limit
+  pub(crate) fn read_bounded(&self, format: &Format) -> Option<Vec<u8>> {
+    match self.source {
+      ContextSource::X11(x11) => x11
+        .read_format_with_size_check(format.id, self.formats, self.max_size)
+        .ok(),
+      #[cfg(feature = "testing")]
+      ContextSource::Mock(mock) => mock.bounded_data(format, self.max_size),
+    }
   }
 }
 
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
 
-impl<G: Gatekeeper> LinuxObserver<G> {
+const DEFAULT_MAX_EVENT_WAIT: Duration = Duration::from_millis(20);
+
+// The shortest wait for the events in the event-driven mode, so that a zero interval
+// (such as the minimum of an adaptive interval) does not turn the wait into a busy loop
+const MIN_EVENT_DRIVEN_WAIT: Duration = Duration::from_millis(10);
+
+// The wait between the polls for the events of a transfer.
+// It starts small, so that quick replies are picked up immediately, and doubles up to the max.
+struct EventWait {
+  current: Duration,
+  max: Duration,
+}
+
+impl EventWait {
+  const INITIAL: Duration = Duration::from_millis(1);
+
+  fn new(max: Duration) -> Self {
+    Self {
+      current: Self::INITIAL.min(max),
+      max,
+    }
+  }
+
+  fn reset(&mut self) {
+    self.current = Self::INITIAL.min(self.max);
+  }
+
+  fn sleep(&mut self) {
+    std::thread::sleep(self.current);
+    self.current = (self.current * 2).min(self.max);
+  }
+}
+
+impl LinuxObserver {
   #[inline(never)]
   #[cold]
   pub(crate) fn new(
     stop: Arc<AtomicBool>,
-    interval: Option<Duration>,
-    max_size: Option<u32>,
-    custom_formats: Vec<Arc<str>>,
-    gatekeeper: G,
+    options: ObserverOptions,
+    gatekeeper: Arc<SharedGatekeeper>,
   ) -> Result<Self, String> {
-    let (conn, screen_id) = x11rb::connect(None).context("Failed to connect to the x11 server")?;
+    let (mut x11, screen_id) = X11Context::new(stop.clone())?;
 
-    let win_id = conn
-      .generate_id()
-      .context("Failed to generate a window id")?;
+    if let Some(max_wait) = options.read_poll_interval {
+      x11.max_event_wait = max_wait;
+    }
 
-    {
-      let screen = conn
-        .setup()
-        .roots
-        .get(screen_id)
-        .context("Failed to get the root window")?;
+    let mut selections: Vec<(Atom, Selection)> = Vec::with_capacity(options.selections.len());
 
-      conn
-        .create_window(
-          0,
-          win_id,
-          screen.root,
-          0,
-          0,
-          1,
-          1,
-          0,
-          WindowClass::INPUT_OUTPUT,
-          screen.root_visual,
-          &CreateWindowAux::new()
-            .event_mask(EventMask::STRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE),
-        )
-        .context("Failed to create a new x11 window")?
-        .check()
-        .context("Failed to create a new x11 window")?;
+    for selection in options.selections {
+      let atom = intern_selection(&x11.conn, &selection)?;
+
+      if !selections.iter().any(|(id, _)| *id == atom) {
+        selections.push((atom, selection));
+      }
     }
 
-    let atoms = Atoms::new(&conn)
-      .context("Failed to get the atoms identifiers")?
-      .reply()
-      .context("Failed to get the atoms identifiers")?;
+    if selections.is_empty() {
+      selections.push((x11.atoms.CLIPBOARD, Selection::Clipboard));
+    }
 
-    let custom_formats = register_custom_formats(&conn, custom_formats)?;
+    let custom_formats = register_formats(&x11.conn, options.custom_formats)?;
+    let text_formats = register_formats(&x11.conn, options.text_formats)?;
     let mut atoms_cache: HashMap<u32, Arc<str>> = HashMap::new();
 
-    for format in &custom_formats {
+    for format in custom_formats.iter().chain(&text_formats) {
       atoms_cache.insert(format.id, format.name.clone());
     }
 
-    let screen = conn
+    let screen = x11
+      .conn
       .setup()
       .roots
       .get(screen_id)
       .context("Failed to connect to the root window")?;
 
     // Check xfixes presence
-    xfixes::query_version(&conn, 5, 0).context("Failed to query xfixes version")?;
-
-    // Watch for events on the clipboard
-    // Cookie = request id
-    let cookie = xfixes::select_selection_input(
-      &conn,
-      screen.root,
-      atoms.CLIPBOARD,
-      xfixes::SelectionEventMask::SET_SELECTION_OWNER,
-    )
-    .context("Failed to select selection input with xfixes")?;
+    xfixes::query_version(&x11.conn, 5, 0).context("Failed to query xfixes version")?;
+
+    // Watch for events on the selections
+    for (atom, selection) in &selections {
+      // Cookie = request id
+      let cookie = xfixes::select_selection_input(
+        &x11.conn,
+        screen.root,
+        *atom,
+        xfixes::SelectionEventMask::SET_SELECTION_OWNER,
+      )
+      .map_err(|e| format!("Failed to select input for selection `{selection}` with xfixes: {e}"))?;
 
-    cookie
-      .check()
-      .context("Failed to get response from the X11 server")?;
+      cookie
+        .check()
+        .context("Failed to get response from the X11 server")?;
+    }
 
     Ok(Self {
       stop_signal: stop,
-      interval: interval.unwrap_or_else(|| std::time::Duration::from_millis(200)),
-      max_size,
+      interval: PollInterval::new(options.interval, options.adaptive_interval, options.warmup),
+      size_limits: options.size_limits,
+      max_file_count: options.max_file_count,
+      truncate_file_lists: options.truncate_file_lists,
+      directory_handling: options.directory_handling,
+      include_text_with_html: options.include_text_with_html,
+      coalesce_text_with_files: options.coalesce_text_with_files,
+      suppress_no_matching_format: options.suppress_no_matching_format,
+      load_image_from_single_file: options.load_image_from_single_file,
+      image_output: options.image_output,
+      image_path_mode: options.image_path_mode,
+      keep_source_bytes: options.keep_source_bytes,
+      deliver_undecoded_on_error: options.deliver_undecoded_on_error,
+      collect_custom_formats: options.collect_custom_formats,
       custom_formats,
+      text_custom_formats: options.text_custom_formats,
+      format_validators: options.format_validators,
+      format_prefixes: options.format_prefixes,
+      text_formats,
       atoms_cache,
-      x11: X11Context {
-        conn,
-        win_id,
-        atoms,
-      },
+      selections,
+      excluded_sources: options.excluded_sources,
+      skip_unchanged_targets: options.skip_unchanged_targets,
+      skip_clipboard_manager_duplicates: options.skip_clipboard_manager_duplicates,
+      event_driven: options.event_driven,
+      last_targets: HashMap::new(),
+      x11,
       gatekeeper,
+      metadata: EventMetadata::default(),
     })
   }
 }
 
-impl<G: Gatekeeper> Observer for LinuxObserver<G> {
+impl Observer for LinuxObserver {
   fn observe(&mut self, body_senders: Arc<BodySenders>) {
     info!("Started monitoring the clipboard");
 
     while !self.stop_signal.load(Ordering::Relaxed) {
-      match self.x11.conn.poll_for_event() {
+      // Whether the event queue was empty, in which case the next event is awaited
+      let idle;
+
+      match self.x11.poll_for_event() {
         Ok(event) => {
+          idle = event.is_none();
+
           if let Some(Event::XfixesSelectionNotify(notify_event)) = event
-            && notify_event.selection == self.x11.atoms.CLIPBOARD
+            && let Some((atom, selection)) = self
+              .selections
+              .iter()
+              .find(|(atom, _)| *atom == notify_event.selection)
+              .cloned()
           {
-            match self.poll_clipboard() {
-              Ok(Some(content)) => body_senders.send_all(&Ok(Arc::new(content))),
-
-              // Skipped content (size too large, empty, etc)
-              Ok(None) => {}
-
-              // Read error
-              Err(e) => {
-                warn!("{e}");
-
-                body_senders.send_all(&Err(e));
+            self.interval.record_change();
+            body_senders.tick_all();
+
+            // Read from the selection that changed
+            self.x11.selection.set(atom);
+
+            let source = self.x11.window_class(notify_event.owner);
+
+            if is_excluded_source(&self.excluded_sources, source.as_deref()) {
+              debug!(
+                "Skipping content from excluded source `{}`",
+                source.unwrap_or_default()
+              );
+            } else {
+              match self.poll_clipboard(true) {
+                Ok(Some(content)) => {
+                  let mut metadata = std::mem::take(&mut self.metadata);
+                  let from_manager = self.skip_clipboard_manager_duplicates
+                    && is_clipboard_manager(source.as_deref());
+
+                  metadata.selection = Some(selection);
+                  metadata.source = source;
+
+                  let event = ClipboardEvent::new(content, metadata);
+
+                  if from_manager {
+                    body_senders.send_if_changed(event);
+                  } else {
+                    body_senders.send_all(&Ok(event));
+                  }
+                }
+
+                // Skipped content (size too large, empty, etc)
+                Ok(None) => {}
+
+                // Read error
+                Err(e) => {
+                  warn!("{e}");
+
+                  body_senders.send_all(&Err(e));
+                }
               }
             }
           }
@@ -163,120 +302,415 @@ impl<G: Gatekeeper> Observer for LinuxObserver<G> {
         Err(e) => {
           error!("{e}");
 
-          body_senders.send_all(&Err(ClipboardError::MonitorFailed(e.to_string())));
+          body_senders.send_all(&Err(ClipboardError::monitor_from(e)));
 
           error!("Fatal error, terminating clipboard watcher");
           break;
         }
       };
 
-      std::thread::sleep(self.interval);
+      if body_senders.take_refresh_request() {
+        self.refresh(&body_senders);
+      }
+
+      if !self.event_driven {
+        self.interval.sleep();
+      } else if idle {
+        // The interval only bounds the wait, so that the stop signal is still checked regularly
+        self.x11.wait_for_event(self.interval.current());
+      }
     }
   }
 }
 
-impl<G: Gatekeeper> LinuxObserver<G> {
-  // Calls the extractor and unwraps the error
-  fn poll_clipboard(&mut self) -> Result<Option<Body>, ClipboardError> {
-    match self.extract_clipboard_content() {
-      Ok(Some(content)) => Ok(Some(content)),
+// The classes of the clipboard managers, which take the ownership of the selections to preserve their content
+const CLIPBOARD_MANAGERS: &[&str] = &[
+  "klipper",
+  "plasmashell",
+  "gpaste-daemon",
+  "copyq",
+  "clipit",
+  "parcellite",
+  "diodon",
+  "xfce4-clipman",
+];
+
+fn is_clipboard_manager(source: Option<&str>) -> bool {
+  source.is_some_and(|source| {
+    CLIPBOARD_MANAGERS
+      .iter()
+      .any(|manager| manager.eq_ignore_ascii_case(source))
+  })
+}
+
+impl LinuxObserver {
+  // Extracts the content of the first monitored selection on demand, and delivers it if it changed
+  fn refresh(&mut self, body_senders: &BodySenders) {
+    let Some((atom, selection)) = self.selections.first().cloned() else {
+      return;
+    };
+
+    self.x11.selection.set(atom);
 
-      // No content or non-fatal errors
-      Ok(None) | Err(ErrorWrapper::SizeTooLarge | ErrorWrapper::UserSkipped) => Ok(None),
+    let source = self
+      .x11
+      .conn
+      .get_selection_owner(atom)
+      .ok()
+      .and_then(|cookie| cookie.reply().ok())
+      .and_then(|reply| self.x11.window_class(reply.owner));
+
+    if is_excluded_source(&self.excluded_sources, source.as_deref()) {
+      return;
+    }
+
+    // The refresh is meant for the content that may have changed without a notification,
+    // so it is extracted even if its targets did not change
+    match self.poll_clipboard(false) {
+      Ok(Some(content)) => {
+        let mut metadata = std::mem::take(&mut self.metadata);
+        metadata.selection = Some(selection);
+        metadata.source = source;
+
+        body_senders.send_if_changed(ClipboardEvent::new(content, metadata));
+      }
+      Ok(None) => {}
+      Err(e) => {
+        warn!("{e}");
+
+        body_senders.send_all(&Err(e));
+      }
+    }
+  }
+
+  // Calls the extractor and unwraps the error.
+  // If `compare_targets` is false, the content is extracted even if `skip_unchanged_targets`
+  // is enabled and the targets did not change, and they are not recorded for the next comparison.
+  fn poll_clipboard(&mut self, compare_targets: bool) -> Result<Option<Body>, ClipboardError> {
+    self.metadata = EventMetadata::default();
+    self.x11.used_incr.set(false);
+
+    let result = self.extract_clipboard_content(compare_targets);
+
+    self.metadata.incremental = self.x11.used_incr.get();
+
+    match result {
+      Ok(Some(content)) if self.size_limits.is_below_min(&content) => Ok(None),
+      Ok(Some(content)) => content
+        .into_image_output(self.image_output, self.keep_source_bytes)
+        .map(Some),
+
+      // Non-fatal errors
+      Err(
+        ErrorWrapper::SizeTooLarge | ErrorWrapper::UserSkipped | ErrorWrapper::UnchangedTargets,
+      ) => Ok(None),
 
       Err(ErrorWrapper::EmptyContent) => {
         trace!("Found empty content. Skipping it...");
         Ok(None)
       }
 
+      Err(ErrorWrapper::Interrupted) => {
+        debug!("Read interrupted by the stop signal");
+        Ok(None)
+      }
+
       Err(ErrorWrapper::ReadError(e)) => Err(e),
+
+      // There was content but we could not read it
+      Ok(None) => {
+        if self.suppress_no_matching_format {
+          trace!("No supported format was found. Skipping it...");
+          Ok(None)
+        } else {
+          Err(ClipboardError::NoMatchingFormat)
+        }
+      }
+    }
+  }
+
+  // Only an explicit limit for file lists applies to the uri-list, as the global one does not cover them
+  fn file_list_max_size(&self) -> Option<u64> {
+    self
+      .size_limits
+      .overrides
+      .get(&BodyKind::FileList)
+      .copied()
+  }
+
+  // Returns the path of the copied file, if the content also has a list with a single file
+  fn single_file_path(&self, formats: &Formats) -> Option<PathBuf> {
+    if formats.contains_id(self.x11.atoms.FILE_LIST)
+      && let Ok(mut files) = self
+        .x11
+        .extract_file_list(Some(1), self.file_list_max_size())
+      && files.len() == 1
+    {
+      Some(files.remove(0))
+    } else {
+      None
     }
   }
 
   // Tries to extract the contents of the clipboard, and returns an error
   // wrapper that can indicate a normal early exit or an actual error
-  fn extract_clipboard_content(&mut self) -> Result<Option<Body>, ErrorWrapper> {
-    let formats = self.get_available_formats()?;
+  fn extract_clipboard_content(
+    &mut self,
+    compare_targets: bool,
+  ) -> Result<Option<Body>, ErrorWrapper> {
+    let formats = self.get_available_formats(compare_targets)?;
+
+    let cache = FormatCache::default();
 
     let ctx = ClipboardContext {
       formats: &formats,
-      x11: &self.x11,
+      max_size: self.size_limits.get(BodyKind::Custom),
+      cache: &cache,
+      source: ContextSource::X11(&self.x11),
     };
 
     if !self.gatekeeper.check(ctx) {
       return Err(ErrorWrapper::UserSkipped);
     }
 
+    // The file list that the text custom formats are compared with
+    let coalesced_files = if self.coalesce_text_with_files
+      && !self.text_custom_formats.is_empty()
+      && formats.contains_id(self.x11.atoms.FILE_LIST)
+    {
+      self
+        .x11
+        .extract_file_list(None, self.file_list_max_size())
+        .ok()
+    } else {
+      None
+    };
+
+    let mut custom_bodies = Vec::new();
+
     for format in self.custom_formats.iter() {
       if formats.contains_id(format.id) {
-        let data = self
-          .x11
-          .read_format_with_size_check(format.id, &formats, self.max_size)?;
+        let (data, truncated) = if let Some(max_bytes) = self.format_prefixes.get(&format.name) {
+          self
+            .x11
+            .read_format_prefix(format.id, max_bytes)?
+        } else {
+          let data = self.x11.read_format_with_size_check(
+            format.id,
+            &formats,
+            self.size_limits.get(BodyKind::Custom),
+          )?;
+
+          (data, false)
+        };
+
+        self.format_validators.validate(&format.name, &data)?;
 
-        return Ok(Some(Body::new_custom(format.name.clone(), data)));
+        let as_text = self.text_custom_formats.contains(&format.name);
+        let body = Body::new_custom(format.name.clone(), data, as_text);
+
+        if coalesced_files
+          .as_deref()
+          .is_some_and(|files| body.is_text_of_files(files))
+        {
+          trace!("The text describes the file list. Skipping it...");
+          continue;
+        }
+
+        if truncated {
+          self.metadata.truncated_formats.push(format.name.to_string());
+        }
+
+        if !self.collect_custom_formats {
+          return Ok(Some(body));
+        }
+
+        custom_bodies.push(body);
       }
     }
 
-    if formats.contains_id(self.x11.atoms.PNG_MIME) {
-      let bytes =
-        self
-          .x11
-          .read_format_with_size_check(self.x11.atoms.PNG_MIME, &formats, self.max_size)?;
+    if let Some(body) = Body::from_representations(custom_bodies) {
+      return Ok(Some(body));
+    }
 
-      let path = if formats.contains_id(self.x11.atoms.FILE_LIST)
-        && let Ok(mut files) = self.x11.extract_file_list()
-        && files.len() == 1
+    // The images that come with the path of their file can be emitted as a file list instead
+    let prefer_path = self.image_path_mode == ImagePathMode::PreferPath
+      && (formats.contains_id(self.x11.atoms.PNG_MIME)
+        || formats.contains_id(self.x11.atoms.WEBP_MIME)
+        || formats.contains_id(self.x11.atoms.GIF_MIME))
+      && self.single_file_path(&formats).is_some();
+
+    if !prefer_path && formats.contains_id(self.x11.atoms.PNG_MIME) {
+      let bytes = self.x11.read_format_with_size_check(
+        self.x11.atoms.PNG_MIME,
+        &formats,
+        self.size_limits.get(BodyKind::PngImage),
+      )?;
+
+      Ok(Some(Body::new_png(bytes, self.single_file_path(&formats))))
+    } else if !prefer_path && formats.contains_id(self.x11.atoms.WEBP_MIME) {
+      let bytes = self.x11.read_format_with_size_check(
+        self.x11.atoms.WEBP_MIME,
+        &formats,
+        self.size_limits.get(BodyKind::RawImage),
+      )?;
+
+      trace!("Found image in WebP format");
+
+      let image = match image::load_from_memory_with_format(&bytes, image::ImageFormat::WebP)
+        .map_err(|e| ClipboardError::read_with_context("Failed to load WebP image", e))
       {
-        Some(files.remove(0))
-      } else {
-        None
+        Ok(image) => image,
+        Err(e) if self.deliver_undecoded_on_error => {
+          return Ok(Some(Body::new_undecoded("image/webp", bytes, &e)));
+        }
+        Err(e) => return Err(e.into()),
       };
 
-      Ok(Some(Body::new_png(bytes, path)))
+      let source_bytes = self.keep_source_bytes.then(|| bytes.into());
+
+      Ok(Some(Body::new_image(
+        RawImage::from_dynamic_image(image, self.single_file_path(&formats)),
+        source_bytes,
+      )))
+    } else if !prefer_path && formats.contains_id(self.x11.atoms.GIF_MIME) {
+      let bytes = self.x11.read_format_with_size_check(
+        self.x11.atoms.GIF_MIME,
+        &formats,
+        self.size_limits.get(BodyKind::RawImage),
+      )?;
+
+      trace!("Found image in GIF format");
+
+      let mut image = match decode_gif(&bytes) {
+        Ok(image) => image,
+        Err(e) if self.deliver_undecoded_on_error => {
+          return Ok(Some(Body::new_undecoded("image/gif", bytes, &e)));
+        }
+        Err(e) => return Err(e.into()),
+      };
+      image.path = self.single_file_path(&formats);
+
+      let source_bytes = self.keep_source_bytes.then(|| bytes.into());
+
+      Ok(Some(Body::new_image(image, source_bytes)))
     } else if formats.contains_id(self.x11.atoms.FILE_LIST) {
-      let files = self.x11.extract_file_list()?;
+      // The directories can change the number of files, so the list can only be cut short without them
+      let max_count = self
+        .max_file_count
+        .filter(|_| self.directory_handling == DirectoryHandling::Keep);
+
+      let (files, truncated) = limit_file_count(
+        handle_directories(
+          self
+            .x11
+            .extract_file_list(max_count, self.file_list_max_size())?,
+          self.directory_handling,
+        )?,
+        self.max_file_count,
+        self.truncate_file_lists,
+      )?;
+
+      if self.load_image_from_single_file
+        && !truncated
+        && let [path] = files.as_slice()
+        && let Some(image) = load_image_file(path, self.size_limits.get(BodyKind::RawImage))?
+      {
+        return Ok(Some(image));
+      }
+
+      self.metadata.truncated = truncated;
+      self.metadata.file_operation = self.x11.file_operation(&formats);
 
       Ok(Some(Body::new_file_list(files)))
-    } else if formats.contains_id(self.x11.atoms.HTML) {
+    } else if let Some(format) = self
+      .text_formats
+      .iter()
+      .find(|format| formats.contains_id(format.id))
+    {
       let bytes = self
         .x11
-        .request_and_read_property(self.x11.atoms.HTML, self.x11.atoms.DATA)?;
+        .request_and_read_property(format.id, self.x11.atoms.DATA)?;
 
-      let html = String::from_utf8_lossy(&bytes);
+      let content = String::from_utf8_lossy(&bytes).into_owned();
 
-      Ok(Some(Body::new_html(html.into_owned())))
-    } else if let Some(format) = self.x11.available_text_format(&formats) {
+      Ok(Some(Body::new_text_with_mime(format.name.clone(), content)))
+    } else if formats.contains_id(self.x11.atoms.HTML) {
       let bytes = self
         .x11
-        .request_and_read_property(format, self.x11.atoms.DATA)?;
+        .request_and_read_property(self.x11.atoms.HTML, self.x11.atoms.DATA)?;
 
-      let text = String::from_utf8_lossy(&bytes);
+      let html = String::from_utf8_lossy(&bytes).into_owned();
 
-      Ok(Some(Body::new_text(text.into_owned())))
+      // A companion that cannot be read does not prevent the html from being emitted
+      let text = if self.include_text_with_html {
+        self
+          .read_plain_text(&formats)
+          .ok()
+          .flatten()
+          .filter(|text| !text.is_empty())
+      } else {
+        None
+      };
+
+      self.metadata.source_url = self.x11.html_source_url(&formats);
+
+      Ok(Some(Body::new_html_with_text(html, text)))
+    } else if let Some(text) = self.read_plain_text(&formats)? {
+      Ok(Some(Body::new_text(text)))
     } else {
-      Err(ErrorWrapper::ReadError(ClipboardError::NoMatchingFormat))
+      Ok(None)
     }
   }
 
-  fn get_available_formats(&mut self) -> Result<Formats, ErrorWrapper> {
-    let prop_reply = self
+  // Reads the plain text, preferring the UTF-8 formats.
+  // With the `encoding` feature, the formats with a different charset are decoded accordingly,
+  // and they are preferred over a generic `text/plain`, which does not specify its charset.
+  fn read_plain_text(&self, formats: &Formats) -> Result<Option<String>, ErrorWrapper> {
+    let utf8_format = self
       .x11
-      .request_and_read_property(self.x11.atoms.TARGETS, self.x11.atoms.METADATA)?;
+      .available_text_format(|atom| formats.contains_id(atom));
 
-    let ignored_formats = [
-      self.x11.atoms.TIMESTAMP,
-      self.x11.atoms.MULTIPLE,
-      self.x11.atoms.TARGETS,
-      self.x11.atoms.SAVE_TARGETS,
-    ];
+    #[cfg(feature = "encoding")]
+    if utf8_format.is_none_or(|format| format == self.x11.atoms.TEXT_PLAIN)
+      && let Some((format, charset)) = formats
+        .iter()
+        .find_map(|format| Some((format, text_charset(&format.name)?)))
+    {
+      let bytes = self
+        .x11
+        .request_and_read_property(format.id, self.x11.atoms.DATA)?;
 
-    // Convert the Vec<u8> into a Vec<Atom>
-    let available_formats: Vec<Atom> = prop_reply
-      // Split in chunks of 4 bytes
-      .chunks_exact(4)
-      .map(|chunk| u32::from_ne_bytes(chunk.try_into().unwrap()))
-      .filter(|atom| !ignored_formats.contains(atom))
-      .collect();
+      return Ok(Some(decode_text(&bytes, charset)));
+    }
+
+    let Some(format) = utf8_format else {
+      return Ok(None);
+    };
+
+    let bytes = self
+      .x11
+      .request_and_read_property(format, self.x11.atoms.DATA)?;
+
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+  }
+
+  fn get_available_formats(&mut self, compare_targets: bool) -> Result<Formats, ErrorWrapper> {
+    let available_formats = self.x11.available_targets()?;
+
+    if self.skip_unchanged_targets && compare_targets {
+      let selection = self.x11.selection.get();
+
+      let mut targets = available_formats.clone();
+      targets.sort_unstable();
+
+      let previous = self.last_targets.insert(selection, targets);
+
+      if previous.as_ref() == self.last_targets.get(&selection) {
+        debug!("The available targets did not change. Skipping the content...");
+        return Err(ErrorWrapper::UnchangedTargets);
+      }
+    }
 
     self.resolve_atom_names(&available_formats)
   }
@@ -331,10 +765,166 @@ impl<G: Gatekeeper> LinuxObserver<G> {
   }
 }
 
+// Returns the charset of a plain text format (such as `text/plain;charset=windows-1252`),
+// unless it is UTF-8, which is handled with the predefined atoms
+#[cfg(feature = "encoding")]
+fn text_charset(name: &str) -> Option<&str> {
+  let (mime, params) = name.split_once(';')?;
+
+  if !mime.trim().eq_ignore_ascii_case("text/plain") {
+    return None;
+  }
+
+  let charset = params.split(';').find_map(|param| {
+    let (key, value) = param.split_once('=')?;
+
+    key
+      .trim()
+      .eq_ignore_ascii_case("charset")
+      .then(|| value.trim().trim_matches('"'))
+  })?;
+
+  (!charset.eq_ignore_ascii_case("utf-8") && !charset.eq_ignore_ascii_case("utf8"))
+    .then_some(charset)
+}
+
+// Decodes the text in the given charset, or as (lossy) UTF-8 if the charset is not recognized
+#[cfg(feature = "encoding")]
+fn decode_text(bytes: &[u8], charset: &str) -> String {
+  match encoding_rs::Encoding::for_label(charset.as_bytes()) {
+    Some(encoding) => encoding.decode(bytes).0.into_owned(),
+    None => {
+      debug!("Unknown charset `{charset}`. Decoding the text as UTF-8...");
+
+      String::from_utf8_lossy(bytes).into_owned()
+    }
+  }
+}
+
+// Reads the text in the clipboard with a temporary connection
+pub(crate) fn read_text() -> Result<Option<String>, ClipboardError> {
+  let (x11, _) = X11Context::new(Arc::default()).map_err(ClipboardError::read)?;
+
+  let read = || -> Result<Option<String>, ErrorWrapper> {
+    let targets = x11.available_targets()?;
+
+    let Some(format) = x11.available_text_format(|atom| targets.contains(&atom)) else {
+      return Ok(None);
+    };
+
+    let bytes = x11.request_and_read_property(format, x11.atoms.DATA)?;
+
+    if bytes.is_empty() {
+      return Err(ErrorWrapper::EmptyContent);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+  };
+
+  read().or_else(ErrorWrapper::into_read_result)
+}
+
+// Reads the png image in the clipboard with a temporary connection
+pub(crate) fn read_image() -> Result<Option<Body>, ClipboardError> {
+  let (x11, _) = X11Context::new(Arc::default()).map_err(ClipboardError::read)?;
+
+  let read = || -> Result<Option<Body>, ErrorWrapper> {
+    if !x11.available_targets()?.contains(&x11.atoms.PNG_MIME) {
+      return Ok(None);
+    }
+
+    let bytes = x11.request_and_read_property(x11.atoms.PNG_MIME, x11.atoms.DATA)?;
+
+    if bytes.is_empty() {
+      return Err(ErrorWrapper::EmptyContent);
+    }
+
+    Ok(Some(Body::PngImage { bytes, path: None }))
+  };
+
+  read().or_else(ErrorWrapper::into_read_result)
+}
+
+// Reads the data of the given custom formats that are in the clipboard, with a temporary connection
+pub(crate) fn read_custom_formats(
+  names: &[Arc<str>],
+  max_size: Option<u64>,
+) -> Result<Vec<CustomFormatData>, ClipboardError> {
+  let (x11, _) = X11Context::new(Arc::default()).map_err(ClipboardError::read)?;
+
+  let custom_formats =
+    register_formats(&x11.conn, names.to_vec()).map_err(ClipboardError::read)?;
+
+  let Some(targets) = x11
+    .available_targets()
+    .map(Some)
+    .or_else(ErrorWrapper::into_read_result)?
+  else {
+    return Ok(Vec::new());
+  };
+
+  // The names of the available formats are not needed to read the data
+  let available_formats: Formats = targets
+    .into_iter()
+    .map(|id| Format {
+      id,
+      name: Arc::default(),
+    })
+    .collect();
+
+  let mut data = Vec::new();
+
+  for format in custom_formats {
+    if !available_formats.contains_id(format.id) {
+      continue;
+    }
+
+    // The formats that are empty or too large are skipped
+    if let Some(bytes) = x11
+      .read_format_with_size_check(format.id, &available_formats, max_size)
+      .map(Some)
+      .or_else(ErrorWrapper::into_read_result)?
+    {
+      data.push((format.name, bytes));
+    }
+  }
+
+  Ok(data)
+}
+
+// Asks the clipboard manager to save the content of the clipboard, with a temporary connection.
+// This follows the freedesktop clipboard manager specification, where the SAVE_TARGETS
+// target is converted on the CLIPBOARD_MANAGER selection.
+pub(crate) fn request_save_targets() -> Result<(), ClipboardError> {
+  let (x11, _) = X11Context::new(Arc::default()).map_err(ClipboardError::read)?;
+
+  let manager = x11
+    .conn
+    .get_selection_owner(x11.atoms.CLIPBOARD_MANAGER)
+    .map_err(ClipboardError::read_from)?
+    .reply()
+    .map_err(ClipboardError::read_from)?
+    .owner;
+
+  if manager == x11rb::NONE {
+    return Err(ClipboardError::read("No clipboard manager is running"));
+  }
+
+  x11.selection.set(x11.atoms.CLIPBOARD_MANAGER);
+
+  // The reply carries no data, it only signals that the content was saved
+  x11
+    .request_and_read_property(x11.atoms.SAVE_TARGETS, x11.atoms.METADATA)
+    .map(|_| ())
+    .or_else(|e| e.into_read_result().map(Option::unwrap_or_default))
+}
+
 x11rb::atom_manager! {
   pub Atoms: AtomCookies {
   // Atom to select the clipboard as a whole
   CLIPBOARD,
+  // The selection owned by the clipboard manager, which handles the SAVE_TARGETS requests
+  CLIPBOARD_MANAGER,
 
   // Ignored formats
   MULTIPLE,
@@ -342,6 +932,7 @@ x11rb::atom_manager! {
   TIMESTAMP,
 
   // Property slot names (arbitrary, just for organization)
+  // They are set on the window of each context, so they are never shared between contexts.
   //
   // For requesting metadata such as length
   METADATA,
@@ -359,24 +950,109 @@ x11rb::atom_manager! {
   // Type of response
   INCR,
 
+  // Window properties, for detecting the source of the content
+  //
+  // The main window of the application that owns a window
+  WM_CLIENT_LEADER,
+
   // Content formats
   //
   UTF8_STRING,
   UTF8_MIME_0: b"text/plain;charset=utf-8",
   UTF8_MIME_1: b"text/plain;charset=UTF-8",
+  // Without a charset, which is conventionally UTF-8 as well
+  TEXT_PLAIN: b"text/plain",
 
   HTML: b"text/html",
   PNG_MIME: b"image/png",
+  WEBP_MIME: b"image/webp",
+  GIF_MIME: b"image/gif",
   FILE_LIST: b"text/uri-list",
+
+  // The url of the page that copied html comes from
+  CHROMIUM_SOURCE_URL: b"chromium/x-source-url",
+  MOZ_URL_PRIV: b"text/x-moz-url-priv",
+
+  // Cut or copy indicators for file lists
+  //
+  // The first line is either `cut` or `copy`, followed by the uris of the files
+  GNOME_COPIED_FILES: b"x-special/gnome-copied-files",
+  // `1` for cut, `0` for copy
+  KDE_CUT_SELECTION: b"application/x-kde-cutselection",
+  }
+}
+
+// Decodes the first frame of a (possibly animated) GIF, and counts its frames
+fn decode_gif(bytes: &[u8]) -> Result<RawImage, ClipboardError> {
+  use image::AnimationDecoder;
+
+  let to_error =
+    |e: image::ImageError| ClipboardError::read_with_context("Failed to load GIF image", e);
+
+  let mut frames = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(bytes))
+    .map_err(to_error)?
+    .into_frames();
+
+  let first = frames
+    .next()
+    .ok_or_else(|| ClipboardError::read("Found GIF image without frames"))?
+    .map_err(to_error)?;
+
+  let mut image = RawImage::from(first.into_buffer());
+  image.frame_count = count_gif_frames(bytes)?;
+  // The frames are always decoded with an alpha channel, so it only counts if the first frame uses it
+  image.had_alpha = image
+    .bytes
+    .chunks_exact(4)
+    .any(|pixel| pixel[3] < u8::MAX);
+
+  Ok(image)
+}
+
+// Counts the frames of a GIF from their descriptors, skipping their pixel data
+fn count_gif_frames(bytes: &[u8]) -> Result<u32, ClipboardError> {
+  let to_error =
+    |e: gif::DecodingError| ClipboardError::read_with_context("Failed to read the GIF frames", e);
+
+  let mut options = gif::DecodeOptions::new();
+  options.skip_frame_decoding(true);
+
+  let mut decoder = options.read_info(bytes).map_err(to_error)?;
+  let mut count: u32 = 0;
+
+  while decoder.next_frame_info().map_err(to_error)?.is_some() {
+    count = count.saturating_add(1);
   }
+
+  Ok(count)
 }
 
-fn to_read_error<T: Display>(error: T) -> ErrorWrapper {
-  ErrorWrapper::ReadError(ClipboardError::ReadError(error.to_string()))
+fn to_read_error<E>(error: E) -> ErrorWrapper
+where
+  E: std::error::Error + Send + Sync + 'static,
+{
+  ErrorWrapper::ReadError(ClipboardError::read_from(error))
 }
 
 // Needs to be a pure fn because it's used in the constructor
-fn register_custom_formats(
+fn intern_selection(conn: &RustConnection, selection: &Selection) -> Result<Atom, String> {
+  let name = selection.name();
+
+  if name.is_empty() {
+    return Err("The name of the selection cannot be empty".to_string());
+  }
+
+  let reply = conn
+    .intern_atom(false, name.as_bytes())
+    .map_err(|e| format!("Failed to register selection `{name}`: {e}"))?
+    .reply()
+    .map_err(|e| format!("Failed to register selection `{name}`: {e}"))?;
+
+  Ok(reply.atom)
+}
+
+// Needs to be a pure fn because it's used in the constructor
+fn register_formats(
   conn: &RustConnection,
   format_names: Vec<Arc<str>>,
 ) -> Result<Formats, String> {
@@ -387,11 +1063,11 @@ fn register_custom_formats(
     let cookie = conn
       // false` means "create `it if it doesn't exist"
       .intern_atom(false, name.as_bytes())
-      .map_err(|e| format!("Failed to register custom format `{name}`: {e}"))?;
+      .map_err(|e| format!("Failed to register format `{name}`: {e}"))?;
 
     let reply = cookie
       .reply()
-      .map_err(|e| format!("Failed to register custom format `{name}`: {e}"))?;
+      .map_err(|e| format!("Failed to register format `{name}`: {e}"))?;
 
     data.push(Format {
       id: reply.atom,
@@ -403,28 +1079,247 @@ fn register_custom_formats(
 }
 
 impl X11Context {
-  fn extract_file_list(&self) -> Result<Vec<PathBuf>, ErrorWrapper> {
-    let raw_data = self.request_and_read_property(self.atoms.FILE_LIST, self.atoms.DATA)?;
+  // Returns the next event, starting from the ones that were deferred during a transfer
+  fn poll_for_event(&self) -> Result<Option<Event>, x11rb::errors::ConnectionError> {
+    if let Some(event) = self.deferred_events.borrow_mut().pop_front() {
+      return Ok(Some(event));
+    }
+
+    self.conn.poll_for_event()
+  }
+
+  // Keeps the selection changes that are received during a transfer, so that they are not lost.
+  // The other unrelated events are discarded.
+  fn defer_event(&self, event: Event) {
+    if let Event::XfixesSelectionNotify(_) = event {
+      self.deferred_events.borrow_mut().push_back(event);
+    }
+  }
+
+  // Blocks until the server sends some data (such as an event), or until the timeout expires.
+  // This must only be called when the event queue of the connection is empty.
+  fn wait_for_event(&self, timeout: Duration) {
+    use rustix::event::{PollFd, PollFlags, Timespec, poll};
+
+    let mut fds = [PollFd::new(self.conn.stream(), PollFlags::IN)];
+
+    let timeout = Timespec::try_from(timeout.max(MIN_EVENT_DRIVEN_WAIT)).ok();
+
+    // An error (such as an interruption by a signal) simply means that the loop runs earlier
+    let _ = poll(&mut fds, timeout.as_ref());
+  }
+
+  // Connects to the X11 server and creates the window that receives the selection data.
+  // Returns the context and the id of the screen.
+  #[inline(never)]
+  #[cold]
+  fn new(stop_signal: Arc<AtomicBool>) -> Result<(Self, usize), String> {
+    let (conn, screen_id) = x11rb::connect(None).context("Failed to connect to the x11 server")?;
+
+    let win_id = conn
+      .generate_id()
+      .context("Failed to generate a window id")?;
+
+    {
+      let screen = conn
+        .setup()
+        .roots
+        .get(screen_id)
+        .context("Failed to get the root window")?;
+
+      conn
+        .create_window(
+          0,
+          win_id,
+          screen.root,
+          0,
+          0,
+          1,
+          1,
+          0,
+          WindowClass::INPUT_OUTPUT,
+          screen.root_visual,
+          &CreateWindowAux::new()
+            .event_mask(EventMask::STRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE),
+        )
+        .context("Failed to create a new x11 window")?
+        .check()
+        .context("Failed to create a new x11 window")?;
+    }
+
+    let atoms = Atoms::new(&conn)
+      .context("Failed to get the atoms identifiers")?
+      .reply()
+      .context("Failed to get the atoms identifiers")?;
+
+    // Reads from the clipboard unless instructed otherwise
+    let selection = Cell::new(atoms.CLIPBOARD);
+
+    Ok((
+      Self {
+        conn,
+        win_id,
+        atoms,
+        selection,
+        stop_signal,
+        used_incr: Cell::new(false),
+        deferred_events: RefCell::new(VecDeque::new()),
+        max_event_wait: DEFAULT_MAX_EVENT_WAIT,
+      },
+      screen_id,
+    ))
+  }
+
+  // Reads the list of formats (as atoms) that are currently available in the selection
+  fn available_targets(&self) -> Result<Vec<Atom>, ErrorWrapper> {
+    let prop_reply = self.request_and_read_property(self.atoms.TARGETS, self.atoms.METADATA)?;
+
+    let ignored_formats = [
+      self.atoms.TIMESTAMP,
+      self.atoms.MULTIPLE,
+      self.atoms.TARGETS,
+      self.atoms.SAVE_TARGETS,
+    ];
+
+    // Convert the Vec<u8> into a Vec<Atom>
+    Ok(
+      prop_reply
+        // Split in chunks of 4 bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_ne_bytes(chunk.try_into().unwrap()))
+        .filter(|atom| !ignored_formats.contains(atom))
+        .collect(),
+    )
+  }
+
+  // Detects whether the files in the selection were cut or copied by a file manager
+  fn file_operation(&self, available_formats: &Formats) -> FileOperation {
+    if available_formats.contains_id(self.atoms.GNOME_COPIED_FILES)
+      && let Ok(bytes) =
+        self.request_and_read_property(self.atoms.GNOME_COPIED_FILES, self.atoms.DATA)
+    {
+      match bytes.split(|byte| *byte == b'\n').next() {
+        Some(b"cut") => FileOperation::Cut,
+        Some(b"copy") => FileOperation::Copy,
+        _ => FileOperation::Unknown,
+      }
+    } else if available_formats.contains_id(self.atoms.KDE_CUT_SELECTION)
+      && let Ok(bytes) =
+        self.request_and_read_property(self.atoms.KDE_CUT_SELECTION, self.atoms.DATA)
+    {
+      match bytes.first() {
+        Some(b'1') => FileOperation::Cut,
+        Some(b'0') => FileOperation::Copy,
+        _ => FileOperation::Unknown,
+      }
+    } else {
+      FileOperation::Unknown
+    }
+  }
+
+  // The uri-list is parsed while it is being read, and the paths beyond `max_count` (if set) are
+  // discarded, so a huge selection is never collected in full.
+  // One extra path is kept, so that the caller can tell whether the list exceeded the limit.
+  // If the uri-list exceeds `max_size`, the rest of the transfer is discarded and the list is skipped.
+  fn extract_file_list(
+    &self,
+    max_count: Option<usize>,
+    max_size: Option<u64>,
+  ) -> Result<Vec<PathBuf>, ErrorWrapper> {
+    let property = self.request_property(self.atoms.FILE_LIST, self.atoms.DATA)?;
+
+    let mut parser = UriListParser::new(
+      max_count.map(|max_count| max_count.saturating_add(1)),
+      max_size,
+    );
 
-    Ok(paths_from_uri_list(&raw_data))
+    self.read_property_chunks(property, |chunk| parser.feed(chunk))?;
+
+    parser.finish()
+  }
+
+  // Reads the class of the application that owns the window (such as the owner of a selection),
+  // from its `WM_CLASS` property or from the one of its client leader.
+  // Toolkits often use hidden windows as selection owners, which may only have the latter.
+  fn window_class(&self, window: Window) -> Option<String> {
+    if window == x11rb::NONE {
+      return None;
+    }
+
+    self.read_window_class(window).or_else(|| {
+      let leader = self
+        .conn
+        .get_property(
+          false,
+          window,
+          self.atoms.WM_CLIENT_LEADER,
+          AtomEnum::WINDOW,
+          0,
+          1,
+        )
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()?
+        .next()?;
+
+      if leader == window || leader == x11rb::NONE {
+        return None;
+      }
+
+      self.read_window_class(leader)
+    })
+  }
+
+  // Reads the `WM_CLASS` property of a window, which contains the instance and class names,
+  // separated by a null byte. The class name is preferred, if present.
+  fn read_window_class(&self, window: Window) -> Option<String> {
+    let reply = self
+      .conn
+      .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+      .ok()?
+      .reply()
+      .ok()?;
+
+    let mut names = reply.value.split(|byte| *byte == 0).filter(|name| !name.is_empty());
+
+    let instance = names.next()?;
+    let class = names.next().unwrap_or(instance);
+
+    Some(String::from_utf8_lossy(class).into_owned())
   }
 
   // Gets the first available plain text format
-  fn available_text_format(&self, available_formats: &Formats) -> Option<Atom> {
+  fn available_text_format(&self, is_available: impl Fn(Atom) -> bool) -> Option<Atom> {
     [
       self.atoms.UTF8_MIME_0,
       self.atoms.UTF8_MIME_1,
       self.atoms.UTF8_STRING,
+      self.atoms.TEXT_PLAIN,
     ]
     .into_iter()
-    .find(|&format| available_formats.contains_id(format))
+    .find(|&format| is_available(format))
   }
 
   // Reads the actual data of a property
   fn read_property_data(&self, property_atom: Atom) -> Result<Vec<u8>, ErrorWrapper> {
-    let start_time = Instant::now();
     let mut buffer = Vec::new();
 
+    self.read_property_chunks(property_atom, |chunk| buffer.extend_from_slice(chunk))?;
+
+    Ok(buffer)
+  }
+
+  // Reads the data of a property, passing each chunk to the callback as soon as it is received,
+  // so that it can be processed without collecting the whole payload first.
+  // A regular transfer is made of a single chunk, while an INCR transfer can have many.
+  fn read_property_chunks(
+    &self,
+    property_atom: Atom,
+    mut on_chunk: impl FnMut(&[u8]),
+  ) -> Result<(), ErrorWrapper> {
+    let start_time = Instant::now();
+
     // First, peek to see if this is an INCR transfer.
     let initial_reply = self
       .conn
@@ -443,14 +1338,23 @@ impl X11Context {
         .check()
         .map_err(to_read_error)?;
 
+      let mut wait = EventWait::new(self.max_event_wait);
+      let mut size = 0;
+
       loop {
+        if self.stop_signal.load(Ordering::Relaxed) {
+          return Err(ErrorWrapper::Interrupted);
+        }
+
         if start_time.elapsed() > DEFAULT_TIMEOUT {
-          return Err(to_read_error("Timeout during INCR transfer"));
+          return Err(ClipboardError::read("Timeout during INCR transfer").into());
         }
 
         let event = self.conn.poll_for_event().map_err(to_read_error)?; // Don't need sequence number here
-        if let Some(Event::PropertyNotify(ev)) = event {
-          if ev.atom == property_atom && ev.state == Property::NEW_VALUE {
+        match event {
+          Some(Event::PropertyNotify(ev))
+            if ev.atom == property_atom && ev.state == Property::NEW_VALUE =>
+          {
             let chunk_reply = self
               .conn
               .get_property(true, self.win_id, property_atom, x11rb::NONE, 0, u32::MAX)
@@ -460,16 +1364,26 @@ impl X11Context {
             if chunk_reply.value.is_empty() {
               break; // End of transfer
             }
-            buffer.extend_from_slice(&chunk_reply.value);
+            size += chunk_reply.value.len();
+            on_chunk(&chunk_reply.value);
+            wait.reset();
           }
-        } else {
-          std::thread::sleep(Duration::from_millis(20));
+          Some(event) => self.defer_event(event),
+          None => wait.sleep(),
         }
       }
+
+      self.used_incr.set(true);
+
+      trace!(
+        "Completed INCR transfer. Size: {}, Elapsed: {:?}",
+        HumanBytes(size),
+        start_time.elapsed()
+      );
     } else {
       // --- Normal Path ---
       // The data is all in the property we already peeked at.
-      buffer.extend_from_slice(&initial_reply.value);
+      on_chunk(&initial_reply.value);
       // We now must clean up the property.
       self
         .conn
@@ -479,7 +1393,7 @@ impl X11Context {
         .map_err(to_read_error)?;
     }
 
-    Ok(buffer)
+    Ok(())
   }
 
   // Attempts to extract a specific format from the clipboard while checking for the max size
@@ -487,7 +1401,7 @@ impl X11Context {
     &self,
     format_to_read: Atom,
     available_formats: &Formats,
-    max_size: Option<u32>,
+    max_size: Option<u64>,
   ) -> Result<Vec<u8>, ErrorWrapper> {
     // 1. Try the cheap size verification first
     if let Some(max_size) = max_size
@@ -502,7 +1416,7 @@ impl X11Context {
           return Err(ErrorWrapper::EmptyContent);
         }
 
-        if size > max_size {
+        if u64::from(size) > max_size {
           debug!(
             "Found content with {} size, beyond maximum allowed size. Skipping it...",
             HumanBytes(size as usize)
@@ -528,7 +1442,7 @@ impl X11Context {
       }
 
       // 4. Make a decision based on the size.
-      if size > max_size {
+      if u64::from(size) > max_size {
         debug!(
           "Found content with {} size, beyond maximum allowed size. Skipping it...",
           HumanBytes(size as usize)
@@ -549,6 +1463,51 @@ impl X11Context {
     self.read_property_data(data_prop)
   }
 
+  // Reads at most `max_bytes` of a format, and returns whether the data was longer.
+  // An INCR transfer must still be read in full, since the owner only stops when it is completed.
+  fn read_format_prefix(
+    &self,
+    format_to_read: Atom,
+    max_bytes: usize,
+  ) -> Result<(Vec<u8>, bool), ErrorWrapper> {
+    let property_atom = self.request_property(format_to_read, self.atoms.DATA)?;
+
+    // The length of the request is expressed in 32-bit units
+    let long_length = u32::try_from(max_bytes.div_ceil(4)).unwrap_or(u32::MAX);
+
+    let reply = self
+      .conn
+      .get_property(false, self.win_id, property_atom, x11rb::NONE, 0, long_length)
+      .map_err(to_read_error)?
+      .reply()
+      .map_err(to_read_error)?;
+
+    if reply.type_ == self.atoms.INCR {
+      let mut prefix = Vec::new();
+      let mut size = 0;
+
+      self.read_property_chunks(property_atom, |chunk| {
+        size += chunk.len();
+
+        let remaining = max_bytes.saturating_sub(prefix.len());
+        prefix.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+      })?;
+
+      return Ok((prefix, size > max_bytes));
+    }
+
+    self
+      .conn
+      .delete_property(self.win_id, property_atom)
+      .map_err(to_read_error)?
+      .check()
+      .map_err(to_read_error)?;
+
+    let (prefix, truncated) = truncate_prefix(reply.value, max_bytes);
+
+    Ok((prefix, truncated || reply.bytes_after > 0))
+  }
+
   // Requests the property without reading it (useful for checking the size
   // in case the LENGTH atom is not supported by the clipboard owner)
   fn request_property(
@@ -561,7 +1520,7 @@ impl X11Context {
       .conn
       .convert_selection(
         self.win_id,
-        self.atoms.CLIPBOARD,
+        self.selection.get(),
         format_to_request,
         property_name,
         CURRENT_TIME,
@@ -573,9 +1532,15 @@ impl X11Context {
     // Flush requests before checking for the response
     self.conn.flush().map_err(to_read_error)?;
 
+    let mut wait = EventWait::new(self.max_event_wait);
+
     loop {
+      if self.stop_signal.load(Ordering::Relaxed) {
+        return Err(ErrorWrapper::Interrupted);
+      }
+
       if start_time.elapsed() > DEFAULT_TIMEOUT {
-        return Err(to_read_error("Timeout waiting for SelectionNotify event"));
+        return Err(ClipboardError::read("Timeout waiting for SelectionNotify event").into());
       }
 
       let event_with_seq = self
@@ -584,23 +1549,22 @@ impl X11Context {
         .map_err(to_read_error)?;
 
       if let Some((event, seq)) = event_with_seq {
-        if seq < sequence_number {
-          continue;
-        }
-
-        if let Event::SelectionNotify(ev) = event
+        if seq >= sequence_number
+          && let Event::SelectionNotify(ev) = event
           && ev.requestor == self.win_id
-          && ev.selection == self.atoms.CLIPBOARD
+          && ev.selection == self.selection.get()
         {
           if ev.property == x11rb::NONE {
-            return Err(to_read_error("Clipboard owner failed to convert selection"));
+            return Err(ClipboardError::read("Clipboard owner failed to convert selection").into());
           }
           // Success! The data is on the server. Return the property's name,
           // which can later be used to inspect or get the data
           return Ok(ev.property);
         }
+
+        self.defer_event(event);
       } else {
-        std::thread::sleep(Duration::from_millis(20));
+        wait.sleep();
       }
     }
   }
@@ -626,6 +1590,18 @@ impl X11Context {
     Ok(prop_reply.bytes_after)
   }
 
+  // Reads the url of the page that the html comes from, if the browser provided it
+  fn html_source_url(&self, formats: &Formats) -> Option<String> {
+    [self.atoms.CHROMIUM_SOURCE_URL, self.atoms.MOZ_URL_PRIV]
+      .into_iter()
+      .filter(|atom| formats.contains_id(*atom))
+      .find_map(|atom| {
+        let bytes = self.request_and_read_property(atom, self.atoms.DATA).ok()?;
+
+        decode_source_url(&bytes)
+      })
+  }
+
   fn request_and_read_property(
     &self,
     format_to_read: Atom,
@@ -637,14 +1613,122 @@ impl X11Context {
   }
 }
 
-// From [arboard](https://github.com/1Password/arboard), with modifications
-fn paths_from_uri_list(uri_list: &[u8]) -> Vec<PathBuf> {
-  uri_list
-    .split(|char| *char == b'\n')
+// Firefox writes its url formats in UTF-16, while the others use UTF-8.
+// Only the first line is kept, since some formats add the title of the page after the url.
+fn decode_source_url(bytes: &[u8]) -> Option<String> {
+  let is_utf16 =
+    bytes.len().is_multiple_of(2) && bytes.iter().skip(1).step_by(2).any(|byte| *byte == 0);
+
+  let text = if is_utf16 {
+    let units: Vec<u16> = bytes
+      .chunks_exact(2)
+      .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+      .collect();
+
+    String::from_utf16_lossy(&units)
+  } else {
+    String::from_utf8_lossy(bytes).into_owned()
+  };
+
+  text
+    .lines()
+    .next()
+    .map(|line| line.trim_matches('\0').trim().to_string())
+    .filter(|url| !url.is_empty())
+}
+
+// Parses a uri-list incrementally, as its chunks are received.
+// Only the line that is split between two chunks is buffered.
+struct UriListParser {
+  partial_line: Vec<u8>,
+  paths: Vec<PathBuf>,
+  max_count: Option<usize>,
+  max_size: Option<u64>,
+  size: usize,
+}
+
+impl UriListParser {
+  const fn new(max_count: Option<usize>, max_size: Option<u64>) -> Self {
+    Self {
+      partial_line: Vec::new(),
+      paths: Vec::new(),
+      max_count,
+      max_size,
+      size: 0,
+    }
+  }
+
+  fn is_full(&self) -> bool {
+    self.max_count.is_some_and(|max| self.paths.len() >= max)
+  }
+
+  fn is_too_large(&self) -> bool {
+    self.max_size.is_some_and(|max| self.size as u64 > max)
+  }
+
+  fn feed(&mut self, chunk: &[u8]) {
+    self.size = self.size.saturating_add(chunk.len());
+
+    if self.is_too_large() {
+      self.paths = Vec::new();
+      self.partial_line = Vec::new();
+      return;
+    }
+
+    // The rest of the transfer must still be consumed, but it is discarded
+    if self.is_full() {
+      return;
+    }
+
+    let mut lines = chunk.split(|char| *char == b'\n');
+
+    // The last piece is not terminated, so it may continue in the next chunk
+    let last = lines.next_back().unwrap_or_default();
+
+    for line in lines {
+      if self.partial_line.is_empty() {
+        self.push_line(line);
+      } else {
+        let mut line_start = std::mem::take(&mut self.partial_line);
+        line_start.extend_from_slice(line);
+        self.push_line(&line_start);
+      }
+    }
+
+    if !self.is_full() {
+      self.partial_line.extend_from_slice(last);
+    }
+  }
+
+  fn finish(mut self) -> Result<Vec<PathBuf>, ErrorWrapper> {
+    if self.is_too_large() {
+      debug!(
+        "Found file list with {} size, beyond maximum allowed size. Skipping it...",
+        HumanBytes(self.size)
+      );
+
+      return Err(ErrorWrapper::SizeTooLarge);
+    }
+
+    let last = std::mem::take(&mut self.partial_line);
+    self.push_line(&last);
+
+    Ok(self.paths)
+  }
+
+  // From [arboard](https://github.com/1Password/arboard), with modifications
+  fn push_line(&mut self, line: &[u8]) {
+    if self.is_full() {
+      return;
+    }
+
     // Removing any trailing \r that might be captured
-    .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
-    .filter_map(|line| line.strip_prefix(b"file://"))
-    .filter_map(|s| percent_decode(s).decode_utf8().ok())
-    .map(|decoded| PathBuf::from(decoded.as_ref()))
-    .collect()
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    if let Some(path) = line.strip_prefix(b"file://")
+      && let Ok(decoded) = percent_decode(path).decode_utf8()
+    {
+      self.paths.push(PathBuf::from(decoded.as_ref()));
+    }
+  }
 }
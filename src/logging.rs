@@ -1,5 +1,39 @@
 use std::fmt;
 
+// Checks whether debug diagnostics are enabled for the active logging backend
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug_enabled {
+  () => {
+    log::log_enabled!(log::Level::Debug)
+  };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! debug_enabled {
+  () => {
+    tracing::enabled!(tracing::Level::DEBUG)
+  };
+}
+
+pub(crate) use debug_enabled;
+
+// Records the event that is about to be emitted, with its details as structured fields
+#[cfg(feature = "tracing")]
+pub(crate) fn trace_event(event: &crate::ClipboardEvent) {
+  let body = &event.body;
+
+  tracing::debug!(
+    selection = event.metadata.selection.as_ref().map(tracing::field::display),
+    format = %body.format_name(),
+    size = body.content_len().map(|len| tracing::field::display(HumanBytes(len))),
+    incr = event.metadata.incremental,
+    truncated = event.metadata.truncated,
+    truncated_formats = ?event.metadata.truncated_formats,
+    source = event.metadata.source.as_deref(),
+    "Emitting clipboard event"
+  );
+}
+
 pub(crate) struct HumanBytes(pub usize);
 
 impl fmt::Display for HumanBytes {
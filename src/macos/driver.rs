@@ -1,20 +1,21 @@
 use crate::{macos::observer::OSXObserver, *};
-use std::convert::Infallible;
 
 impl Driver {
   #[inline(never)]
   #[cold]
   /// Construct [`Driver`] and spawn a thread for monitoring clipboard events
-  pub(crate) fn new<G: Gatekeeper>(
+  pub(crate) fn new(
     body_senders: Arc<BodySenders>,
-    interval: Option<Duration>,
-    custom_formats: Vec<Arc<str>>,
-    max_bytes: Option<u32>,
-    gatekeeper: G,
-  ) -> Result<Self, Infallible> {
+    options: ObserverOptions,
+    gatekeeper: Arc<SharedGatekeeper>,
+  ) -> Result<Self, InitializationError> {
     let stop = Arc::new(AtomicBool::new(false));
+    let restart = Arc::new(AtomicBool::new(false));
 
     let stop_cl = stop.clone();
+    let restart_cl = restart.clone();
+
+    let (init_tx, init_rx) = sync_channel(0);
 
     // spawn OS thread
     // observe clipboard change event and send item
@@ -22,15 +23,24 @@ impl Driver {
       // construct Observer in thread
       // OSXSys is **not** implemented Send + Sync
       // in order to send Observer, construct it
-      let mut observer = OSXObserver::new(stop_cl, interval, custom_formats, max_bytes, gatekeeper);
-
-      // event change observe loop
-      observer.observe(body_senders);
+      supervise(&stop_cl, &restart_cl, &body_senders, init_tx, || {
+        Ok(OSXObserver::new(
+          stop_cl.clone(),
+          options.clone(),
+          gatekeeper.clone(),
+        ))
+      });
     });
 
-    Ok(Driver {
-      stop,
-      handle: Some(handle),
-    })
+    // Block until we get an init signal
+    match init_rx.recv() {
+      Ok(Ok(())) => Ok(Self {
+        stop,
+        restart,
+        handle: Some(handle),
+      }),
+      Ok(Err(e)) => Err(InitializationError(e)),
+      Err(e) => Err(InitializationError(e.to_string())),
+    }
   }
 }
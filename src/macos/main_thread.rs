@@ -0,0 +1,184 @@
+// Runs the pasteboard operations on the main thread, through the main dispatch queue.
+// The queue is only serviced if the host application runs the main run loop (or calls `dispatchMain`).
+
+use std::{ffi::c_void, panic::AssertUnwindSafe, sync::Condvar};
+
+use crate::*;
+
+#[repr(C)]
+struct DispatchQueue {
+  _private: [u8; 0],
+}
+
+type DispatchFunction = unsafe extern "C" fn(*mut c_void);
+
+// Part of libSystem, which is always linked
+unsafe extern "C" {
+  static _dispatch_main_q: DispatchQueue;
+
+  fn dispatch_async_f(queue: *const DispatchQueue, context: *mut c_void, work: DispatchFunction);
+}
+
+const fn main_queue() -> *const DispatchQueue {
+  &raw const _dispatch_main_q
+}
+
+// How often a thread that waits for the main queue checks the stop signal
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+// Checks whether the main queue executes a block within the timeout.
+// If it does not, the block is leaked, since it may still run later on.
+// Returns false as soon as the stop signal is received.
+pub(crate) fn is_main_queue_serviced(stop_signal: &AtomicBool, timeout: Duration) -> bool {
+  unsafe extern "C" fn signal(context: *mut c_void) {
+    let tx = unsafe { Box::from_raw(context.cast::<SyncSender<()>>()) };
+
+    let _ = tx.send(());
+  }
+
+  let (tx, rx) = sync_channel::<()>(1);
+
+  unsafe {
+    dispatch_async_f(main_queue(), Box::into_raw(Box::new(tx)).cast(), signal);
+  }
+
+  let deadline = Instant::now() + timeout;
+
+  while !stop_signal.load(Ordering::Relaxed) {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    if remaining.is_zero() {
+      return false;
+    }
+
+    if rx
+      .recv_timeout(remaining.min(STOP_CHECK_INTERVAL))
+      .is_ok()
+    {
+      return true;
+    }
+  }
+
+  false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WorkState {
+  // Waiting for the main queue
+  Pending,
+  Running,
+  Done,
+  // The caller stopped waiting before the work started, so it must never run
+  Cancelled,
+}
+
+// The state shared by the caller and the block on the main queue, which can outlive the caller
+struct WorkStatus {
+  state: Mutex<WorkState>,
+  changed: Condvar,
+}
+
+struct WorkSlot<F, R> {
+  work: Option<F>,
+  result: Option<std::thread::Result<R>>,
+}
+
+struct AsyncContext<F, R> {
+  status: Arc<WorkStatus>,
+  // Owned by the caller, and only accessed while the state is `Running`
+  slot: *mut WorkSlot<F, R>,
+}
+
+// Runs the closure on the main thread, waiting until it returns.
+//
+// The work is queued asynchronously, so that the wait can be interrupted: if the stop signal is
+// received before the main queue starts running the closure, the closure is cancelled and `None`
+// is returned. Once the closure has started, this waits for it to complete.
+// Must not be called from the main thread, or it never runs the closure.
+//
+// # Safety
+//
+// The closure is moved to the main thread and its result is created there, even though neither
+// has to be `Send`. This is only sound because the caller is blocked while the closure runs,
+// so the values it captures are never used by two threads at once. The caller must ensure that
+// the closure and its result do not hold anything that is tied to the calling thread (such as a
+// `MutexGuard` or a reference to thread-local data).
+pub(crate) unsafe fn run_on_main_thread<F, R>(stop_signal: &AtomicBool, work: F) -> Option<R>
+where
+  F: FnOnce() -> R,
+{
+  unsafe extern "C" fn run<F, R>(context: *mut c_void)
+  where
+    F: FnOnce() -> R,
+  {
+    let context = unsafe { Box::from_raw(context.cast::<AsyncContext<F, R>>()) };
+
+    {
+      let mut state = context.status.state.lock().unwrap();
+
+      if *state == WorkState::Cancelled {
+        return;
+      }
+
+      *state = WorkState::Running;
+    }
+
+    // The caller keeps the slot alive until the state changes to `Done`
+    let slot = unsafe { &mut *context.slot };
+
+    // Panics must not unwind across the dispatch frames, so they are resumed on the calling thread
+    if let Some(work) = slot.work.take() {
+      slot.result = Some(std::panic::catch_unwind(AssertUnwindSafe(work)));
+    }
+
+    *context.status.state.lock().unwrap() = WorkState::Done;
+    context.status.changed.notify_all();
+  }
+
+  let mut slot = WorkSlot {
+    work: Some(work),
+    result: None,
+  };
+
+  let status = Arc::new(WorkStatus {
+    state: Mutex::new(WorkState::Pending),
+    changed: Condvar::new(),
+  });
+
+  let context = Box::new(AsyncContext {
+    status: status.clone(),
+    slot: &raw mut slot,
+  });
+
+  // If the main queue is never serviced, the context is leaked along with the block
+  unsafe {
+    dispatch_async_f(main_queue(), Box::into_raw(context).cast(), run::<F, R>);
+  }
+
+  let mut state = status.state.lock().unwrap();
+
+  loop {
+    match *state {
+      WorkState::Done => break,
+      WorkState::Pending if stop_signal.load(Ordering::Relaxed) => {
+        *state = WorkState::Cancelled;
+        return None;
+      }
+      _ => {
+        state = status
+          .changed
+          .wait_timeout(state, STOP_CHECK_INTERVAL)
+          .unwrap()
+          .0;
+      }
+    }
+  }
+
+  drop(state);
+
+  match slot.result {
+    Some(Ok(result)) => Some(result),
+    Some(Err(payload)) => std::panic::resume_unwind(payload),
+    None => unreachable!("The main queue completed the work without running it"),
+  }
+}
@@ -1,4 +1,4 @@
-use crate::*;
+use crate::{macos::main_thread::*, *};
 
 use image::ImageFormat;
 use objc2::{
@@ -6,26 +6,70 @@ use objc2::{
   rc::{Retained, autoreleasepool},
 };
 use objc2_app_kit::{
-  NSPasteboard, NSPasteboardType, NSPasteboardTypeFileURL, NSPasteboardTypeHTML,
-  NSPasteboardTypePNG, NSPasteboardTypeString, NSPasteboardTypeTIFF,
-  NSPasteboardURLReadingFileURLsOnlyKey,
+  NSColor, NSColorSpace, NSPasteboard, NSPasteboardNameDrag, NSPasteboardNameFind,
+  NSPasteboardNameFont, NSPasteboardNameRuler, NSPasteboardType, NSPasteboardTypeColor,
+  NSPasteboardTypeFileURL, NSPasteboardTypeHTML, NSPasteboardTypePNG, NSPasteboardTypeString,
+  NSPasteboardTypeTIFF, NSPasteboardURLReadingFileURLsOnlyKey, NSWorkspace,
 };
 use objc2_foundation::{NSArray, NSData, NSDictionary, NSNumber, NSString, NSURL};
 
-pub(crate) struct OSXObserver<G: Gatekeeper = DefaultGatekeeper> {
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct OSXObserver {
   stop_signal: Arc<AtomicBool>,
   pasteboard: Retained<NSPasteboard>,
-  interval: Duration,
+  interval: PollInterval,
   custom_formats: Formats,
-  max_size: Option<u32>,
-  gatekeeper: G,
+  // The custom formats that are decoded as text
+  text_custom_formats: Vec<Arc<str>>,
+  format_validators: FormatValidators,
+  format_prefixes: FormatPrefixes,
+  size_limits: SizeLimits,
+  max_file_count: Option<usize>,
+  truncate_file_lists: bool,
+  directory_handling: DirectoryHandling,
+  include_text_with_html: bool,
+  coalesce_text_with_files: bool,
+  suppress_no_matching_format: bool,
+  load_image_from_single_file: bool,
+  image_mode: ImageMode,
+  image_output: ImageOutput,
+  image_path_mode: ImagePathMode,
+  keep_source_bytes: bool,
+  deliver_undecoded_on_error: bool,
+  collect_custom_formats: bool,
+  concatenate_text_items: bool,
+  exact_text: bool,
+  respect_nspasteboard_conventions: bool,
+  // Disabled when the main queue is not serviced
+  main_thread_polling: bool,
+  excluded_sources: Vec<String>,
+  gatekeeper: Arc<SharedGatekeeper>,
+  // The metadata for the event currently being extracted
+  metadata: EventMetadata,
 }
 
 impl ClipboardContext<'_> {
   /// Attempts to extract the data for a particular [`Format`].
   #[must_use]
   pub fn get_data(&self, format: &Format) -> Option<Vec<u8>> {
-    extract_clipboard_format_macos(&self.pasteboard, self.formats, &format.id, None).ok()?
+    match self.source {
+      ContextSource::Pasteboard(pasteboard) => {
+        extract_clipboard_format_macos(pasteboard, self.formats, &format.id, None).ok()?
+      }
+      #[cfg(feature = "testing")]
+      ContextSource::Mock(mock) => mock.data(format),
+    }
+  }
+
+  // Reads the data of the format, unless it exceeds the size limit
+  pub(crate) fn read_bounded(&self, format: &Format) -> Option<Vec<u8>> {
+    match self.source {
+      ContextSource::Pasteboard(pasteboard) => {
+        extract_clipboard_format_macos(pasteboard, self.formats, &format.id, self.max_size).ok()?
+      }
+      #[cfg(feature = "testing")]
+      ContextSource::Mock(mock) => mock.bounded_data(format, self.max_size),
+    }
   }
 }
 
@@ -37,18 +81,17 @@ impl Formats {
   }
 }
 
-impl<G: Gatekeeper> OSXObserver<G> {
+impl OSXObserver {
   #[inline(never)]
   #[cold]
   pub(crate) fn new(
     stop_signal: Arc<AtomicBool>,
-    interval: Option<Duration>,
-    custom_format_names: Vec<Arc<str>>,
-    max_size: Option<u32>,
-    gatekeeper: G,
+    options: ObserverOptions,
+    gatekeeper: Arc<SharedGatekeeper>,
   ) -> Self {
-    let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
-    let custom_formats: Formats = custom_format_names
+    let pasteboard = open_pasteboard(&options.pasteboard);
+    let custom_formats: Formats = options
+      .custom_formats
       .into_iter()
       .map(|str| Format {
         id: NSString::from_str(str.as_ref()),
@@ -59,69 +102,216 @@ impl<G: Gatekeeper> OSXObserver<G> {
     OSXObserver {
       stop_signal,
       pasteboard,
-      interval: interval.unwrap_or_else(|| std::time::Duration::from_millis(200)),
+      interval: PollInterval::new(options.interval, options.adaptive_interval, options.warmup),
       custom_formats,
-      max_size,
+      text_custom_formats: options.text_custom_formats,
+      format_validators: options.format_validators,
+      format_prefixes: options.format_prefixes,
+      size_limits: options.size_limits,
+      max_file_count: options.max_file_count,
+      truncate_file_lists: options.truncate_file_lists,
+      directory_handling: options.directory_handling,
+      include_text_with_html: options.include_text_with_html,
+      coalesce_text_with_files: options.coalesce_text_with_files,
+      suppress_no_matching_format: options.suppress_no_matching_format,
+      load_image_from_single_file: options.load_image_from_single_file,
+      image_mode: options.image_mode,
+      image_output: options.image_output,
+      image_path_mode: options.image_path_mode,
+      keep_source_bytes: options.keep_source_bytes,
+      deliver_undecoded_on_error: options.deliver_undecoded_on_error,
+      collect_custom_formats: options.collect_custom_formats,
+      concatenate_text_items: options.concatenate_text_items,
+      exact_text: options.exact_text,
+      respect_nspasteboard_conventions: options.respect_nspasteboard_conventions,
+      main_thread_polling: options.main_thread_polling,
+      excluded_sources: options.excluded_sources,
       gatekeeper,
+      metadata: EventMetadata::default(),
     }
   }
 }
 
-impl<G: Gatekeeper> Observer for OSXObserver<G> {
+// The maximum delay between checks while the pasteboard is unavailable
+const MAX_UNAVAILABLE_BACKOFF: Duration = Duration::from_secs(5);
+
+// How long to wait for the main queue to run a block, before falling back to the monitoring thread
+const MAIN_QUEUE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The types that mark sensitive or temporary content, from the nspasteboard.org conventions
+const NSPASTEBOARD_MARKERS: &[&str] = &[
+  "org.nspasteboard.ConcealedType",
+  "org.nspasteboard.TransientType",
+];
+
+impl Observer for OSXObserver {
   fn observe(&mut self, body_senders: Arc<BodySenders>) {
-    let mut last_count = unsafe { self.pasteboard.changeCount() };
+    if self.main_thread_polling && !is_main_queue_serviced(&self.stop_signal, MAIN_QUEUE_TIMEOUT) {
+      if self.stop_signal.load(Ordering::Relaxed) {
+        return;
+      }
+
+      warn!(
+        "The main run loop is not running. Falling back to polling the pasteboard from the monitoring thread"
+      );
+      self.main_thread_polling = false;
+    }
+
+    // The reads made on the main thread return None once the stop signal is received
+    let Some(mut last_count) = self.change_count() else {
+      return;
+    };
 
     info!("Started monitoring the clipboard");
 
     while !self.stop_signal.load(Ordering::Relaxed) {
-      let change_count = unsafe { self.pasteboard.changeCount() };
+      let Some(change_count) = self.change_count() else {
+        break;
+      };
 
       if change_count != last_count {
         last_count = change_count;
+        self.interval.record_change();
+        body_senders.tick_all();
+
+        let Some(source) = self.on_pasteboard_thread(|_| frontmost_application()) else {
+          break;
+        };
+
+        if is_excluded_source(&self.excluded_sources, source.as_deref()) {
+          debug!(
+            "Skipping content from excluded source `{}`",
+            source.unwrap_or_default()
+          );
+
+          self.interval.sleep();
+          continue;
+        }
+
+        let Some(result) = self.on_pasteboard_thread(Self::poll_clipboard) else {
+          break;
+        };
+
+        match result {
+          Ok(Some(content)) => {
+            let mut metadata = std::mem::take(&mut self.metadata);
+            metadata.source = source;
 
-        match self.poll_clipboard() {
-          Ok(Some(content)) => body_senders.send_all(&Ok(Arc::new(content))),
+            body_senders.send_all(&Ok(ClipboardEvent::new(content, metadata)));
+          }
           Err(e) => {
-            warn!("{e}");
-            body_senders.send_all(&Err(e));
+            if self.is_pasteboard_accessible() {
+              warn!("{e}");
+              body_senders.send_all(&Err(e));
+            } else {
+              // Avoid hammering the pasteboard (and the streams) while there is no session
+              self.wait_for_pasteboard(&body_senders);
+
+              match self.change_count() {
+                Some(count) => last_count = count,
+                None => break,
+              }
+
+              continue;
+            }
           }
           // Found content but ignored it (empty or beyond allowed size)
           Ok(None) => {}
         }
+      } else if body_senders.take_refresh_request() && self.refresh(&body_senders).is_none() {
+        break;
       }
 
-      std::thread::sleep(self.interval);
+      self.interval.sleep();
     }
   }
 }
 
-impl<G: Gatekeeper> OSXObserver<G> {
-  fn get_available_formats(&self) -> Result<Formats, ErrorWrapper> {
-    unsafe {
-      // 1. Get the NSArray of types
-      // types() returns Option<Retained<NSArray<NSPasteboardType>>>
-      let types_array = self.pasteboard.types().ok_or_else(|| {
-        ErrorWrapper::ReadError(ClipboardError::ReadError(
-          "Failed to read the clipboard formats".to_string(),
-        ))
-      })?;
-
-      // 2. Map NSArray -> Vec<Format>
-      let formats: Formats = types_array
-        .iter()
-        .map(|ns_string| {
-          // Convert NSString to Rust String
-          let rust_string = ns_string.to_string();
-
-          Format {
-            name: rust_string.into(), // Arc<str>
-            id: ns_string,            // Retained<NSString>
-          }
-        })
-        .collect();
+// Returns the bundle identifier of the frontmost application, which is most likely the one
+// that has just written to the pasteboard
+fn frontmost_application() -> Option<String> {
+  autoreleasepool(|_| {
+    NSWorkspace::sharedWorkspace()
+      .frontmostApplication()?
+      .bundleIdentifier()
+      .map(|id| id.to_string())
+  })
+}
+
+impl OSXObserver {
+  // Runs the work on the main thread if enabled, or on the monitoring thread otherwise.
+  // Returns None if the stop signal is received while waiting for the main queue, so that
+  // a stalled main thread (or one that is dropping the listener) never blocks the shutdown.
+  fn on_pasteboard_thread<R>(&mut self, work: impl FnOnce(&mut Self) -> R) -> Option<R> {
+    if self.main_thread_polling {
+      let stop_signal = self.stop_signal.clone();
+
+      // SAFETY: the observer is only used by the closure while this thread is blocked,
+      // and neither the pasteboard objects nor the results are tied to this thread
+      unsafe { run_on_main_thread(&stop_signal, || work(self)) }
+    } else {
+      Some(work(self))
+    }
+  }
+
+  fn change_count(&mut self) -> Option<isize> {
+    self.on_pasteboard_thread(|observer| unsafe { observer.pasteboard.changeCount() })
+  }
 
-      Ok(formats)
+  // Extracts the content of the pasteboard on demand, and delivers it if it changed.
+  // The frontmost application is not necessarily the source of the content, so it is not reported.
+  // Only the extraction runs on the pasteboard thread, so the content is never delivered
+  // from the main thread.
+  // Returns None if the stop signal is received while waiting for the main queue.
+  fn refresh(&mut self, body_senders: &BodySenders) -> Option<()> {
+    match self.on_pasteboard_thread(Self::poll_clipboard)? {
+      Ok(Some(content)) => {
+        let metadata = std::mem::take(&mut self.metadata);
+
+        body_senders.send_if_changed(ClipboardEvent::new(content, metadata));
+      }
+      Ok(None) => {}
+      Err(e) => {
+        warn!("{e}");
+
+        body_senders.send_all(&Err(e));
+      }
     }
+
+    Some(())
+  }
+
+  // The pasteboard types can be missing when there is no window server session,
+  // like when the screen is locked or when running as a launch daemon
+  fn is_pasteboard_accessible(&self) -> bool {
+    autoreleasepool(|_| unsafe { self.pasteboard.types() }.is_some())
+  }
+
+  // Emits a single error and then waits (with an increasing delay) until the
+  // pasteboard can be accessed again, or until the stop signal is received
+  fn wait_for_pasteboard(&self, body_senders: &BodySenders) {
+    let error = ClipboardError::monitor(
+      "The pasteboard is not accessible (no active session?). Pausing until access is restored",
+    );
+
+    error!("{error}");
+
+    body_senders.send_all(&Err(error));
+
+    let mut backoff = self.interval.current();
+
+    while sleep_unless_stopped(&self.stop_signal, backoff) {
+      if self.is_pasteboard_accessible() {
+        info!("Pasteboard access restored, resuming monitoring");
+        return;
+      }
+
+      backoff = (backoff * 2).min(MAX_UNAVAILABLE_BACKOFF);
+    }
+  }
+
+  fn get_available_formats(&self) -> Result<Formats, ErrorWrapper> {
+    available_formats(&self.pasteboard)
   }
 
   fn extract_files_list(
@@ -186,35 +376,47 @@ impl<G: Gatekeeper> OSXObserver<G> {
         &self.pasteboard,
         available_types,
         NSPasteboardTypePNG,
-        self.max_size,
+        self.size_limits.get(BodyKind::PngImage),
       )
     }
   }
 
-  fn extract_raw_image(
-    &self,
-    available_types: &Formats,
-  ) -> Result<Option<image::DynamicImage>, ErrorWrapper> {
-    if let Some(tiff_bytes) = unsafe {
+  fn extract_tiff(&self, available_types: &Formats) -> Result<Option<Vec<u8>>, ErrorWrapper> {
+    unsafe {
       extract_clipboard_format_macos(
         &self.pasteboard,
         available_types,
         NSPasteboardTypeTIFF,
-        self.max_size,
-      )?
-    } {
-      trace!("Found image in TIFF format");
-
-      let image = image::load_from_memory_with_format(&tiff_bytes, ImageFormat::Tiff)
-        .map_err(|e| ClipboardError::ReadError(format!("Failed to load TIFF image: {e}")))?;
+        self.size_limits.get(match self.image_mode {
+          ImageMode::Decoded => BodyKind::RawImage,
+          ImageMode::Original => BodyKind::EncodedImage,
+        }),
+      )
+    }
+  }
 
-      Ok(Some(image))
-    } else {
-      Ok(None)
+  // Reads the color in the pasteboard, converted to the sRGB color space.
+  // Colors that cannot be converted (such as pattern colors) are ignored.
+  fn extract_color(&self, available_types: &Formats) -> Option<Body> {
+    if !available_types.contains_format(unsafe { NSPasteboardTypeColor }) {
+      return None;
     }
+
+    let color = NSColor::colorFromPasteboard(&self.pasteboard)?;
+
+    let Some(srgb) = color.colorUsingColorSpace(&NSColorSpace::sRGBColorSpace()) else {
+      debug!("Failed to convert the color to the sRGB color space. Skipping it...");
+      return None;
+    };
+
+    Some(Body::new_color(
+      srgb.redComponent(),
+      srgb.greenComponent(),
+      srgb.blueComponent(),
+      srgb.alphaComponent(),
+    ))
   }
 
-  // From [arboard](https://github.com/1Password/arboard), with modifications
   fn string_from_type(
     &self,
     available_types: &Formats,
@@ -224,53 +426,108 @@ impl<G: Gatekeeper> OSXObserver<G> {
       return Ok(None);
     }
 
-    // XXX: We explicitly use `pasteboardItems` and not `stringForType` since the latter will concat
-    // multiple strings, if present, into one and return it instead of reading just the first
-    autoreleasepool(|_| {
-      // If no pasteboard items are found, we trigger the early exit
-      let contents =
-        unsafe { self.pasteboard.pasteboardItems() }.ok_or(ErrorWrapper::EmptyContent)?;
-
-      for item in contents {
-        if let Some(string) = unsafe { item.stringForType(type_) } {
-          if !string.is_empty() {
-            return Ok(Some(string.to_string()));
-          } else {
-            return Err(ErrorWrapper::EmptyContent);
-          }
-        }
-      }
+    if self.exact_text {
+      return exact_string_from_pasteboard(&self.pasteboard, type_);
+    }
 
-      Ok(None)
-    })
+    string_from_pasteboard(&self.pasteboard, type_, self.concatenate_text_items)
   }
 
   // Reads the clipboard and extract the first kind of format available, following the priority list
-  fn extract_clipboard_content(&self) -> Result<Option<Body>, ErrorWrapper> {
+  fn extract_clipboard_content(&mut self) -> Result<Option<Body>, ErrorWrapper> {
     autoreleasepool(|_| {
       let formats = self.get_available_formats()?;
 
+      if self.respect_nspasteboard_conventions
+        && let Some(marker) = NSPASTEBOARD_MARKERS
+          .iter()
+          .find(|marker| formats.iter().any(|format| format.name.as_ref() == **marker))
+      {
+        debug!("Found the `{marker}` marker. Skipping the content...");
+        return Err(ErrorWrapper::UserSkipped);
+      }
+
+      let max_size = self.size_limits.get(BodyKind::Custom);
+      let cache = FormatCache::default();
+
       let ctx = ClipboardContext {
         formats: &formats,
-        pasteboard: &self.pasteboard,
+        max_size,
+        cache: &cache,
+        source: ContextSource::Pasteboard(&self.pasteboard),
       };
 
       if !self.gatekeeper.check(ctx) {
         return Err(ErrorWrapper::UserSkipped);
       }
 
-      let max_size = self.max_size;
+      // The file list that the text custom formats are compared with
+      let coalesced_files = if self.coalesce_text_with_files && !self.text_custom_formats.is_empty()
+      {
+        self.extract_files_list(&formats).ok().flatten()
+      } else {
+        None
+      };
+
+      let mut custom_bodies = Vec::new();
 
       for format in self.custom_formats.iter() {
+        let prefix_len = self.format_prefixes.get(&format.name);
+
         // For custom formats, we check the size as well as the presence
-        if let Some(bytes) =
-          extract_clipboard_format_macos(&self.pasteboard, &formats, &format.id, max_size)?
-        {
-          return Ok(Some(Body::new_custom(format.name.clone(), bytes)));
+        // (except for the prefixes, which are truncated instead)
+        if let Some(mut bytes) = extract_clipboard_format_macos(
+          &self.pasteboard,
+          &formats,
+          &format.id,
+          if prefix_len.is_some() { None } else { max_size },
+        )? {
+          let mut truncated = false;
+
+          if let Some(max_bytes) = prefix_len {
+            (bytes, truncated) = truncate_prefix(bytes, max_bytes);
+          }
+
+          self.format_validators.validate(&format.name, &bytes)?;
+
+          let as_text = self.text_custom_formats.contains(&format.name);
+          let body = Body::new_custom(format.name.clone(), bytes, as_text);
+
+          if coalesced_files
+            .as_deref()
+            .is_some_and(|files| body.is_text_of_files(files))
+          {
+            trace!("The text describes the file list. Skipping it...");
+            continue;
+          }
+
+          if truncated {
+            self.metadata.truncated_formats.push(format.name.to_string());
+          }
+
+          if !self.collect_custom_formats {
+            return Ok(Some(body));
+          }
+
+          custom_bodies.push(body);
         }
       }
 
-      if let Some(png_bytes) = self.extract_png(&formats)? {
+      if let Some(body) = Body::from_representations(custom_bodies) {
+        return Ok(Some(body));
+      }
+
+      if let Some(color) = self.extract_color(&formats) {
+        return Ok(Some(color));
+      }
+
+      // The images that come with the path of their file can be emitted as a file list instead
+      let prefer_path = self.image_path_mode == ImagePathMode::PreferPath
+        && self
+          .extract_files_list(&formats)?
+          .is_some_and(|list| list.len() == 1);
+
+      if !prefer_path && let Some(png_bytes) = self.extract_png(&formats)? {
         // Extract the image path if we have a list of files with a single item
         let image_path = self
           .extract_files_list(&formats)?
@@ -278,19 +535,77 @@ impl<G: Gatekeeper> OSXObserver<G> {
           .map(|mut files| files.remove(0));
 
         Ok(Some(Body::new_png(png_bytes, image_path)))
-      } else if let Some(image) = self.extract_raw_image(&formats)? {
+      } else if !prefer_path && let Some(tiff_bytes) = self.extract_tiff(&formats)? {
+        trace!("Found image in TIFF format");
+
         // Extract the image path if we have a list of files with a single item
         let image_path = self
           .extract_files_list(&formats)?
           .filter(|list| list.len() == 1)
           .map(|mut files| files.remove(0));
 
-        Ok(Some(Body::new_image(image, image_path)))
+        match self.image_mode {
+          ImageMode::Decoded => {
+            let image = match image::load_from_memory_with_format(&tiff_bytes, ImageFormat::Tiff)
+              .map_err(|e| ClipboardError::read_with_context("Failed to load TIFF image", e))
+            {
+              Ok(image) => image,
+              Err(e) if self.deliver_undecoded_on_error => {
+                return Ok(Some(Body::new_undecoded("public.tiff", tiff_bytes, &e)));
+              }
+              Err(e) => return Err(e.into()),
+            };
+            let source_bytes = self.keep_source_bytes.then(|| tiff_bytes.into());
+
+            Ok(Some(Body::new_image(
+              RawImage::from_dynamic_image(image, image_path),
+              source_bytes,
+            )))
+          }
+          ImageMode::Original => Ok(Some(Body::new_encoded_image(
+            EncodedImageFormat::Tiff,
+            tiff_bytes,
+            image_path,
+          ))),
+        }
       } else if let Some(files_list) = self.extract_files_list(&formats)? {
+        let (files_list, truncated) = limit_file_count(
+          handle_directories(files_list, self.directory_handling)?,
+          self.max_file_count,
+          self.truncate_file_lists,
+        )?;
+
+        if self.load_image_from_single_file
+          && !truncated
+          && let [path] = files_list.as_slice()
+          && let Some(image) = load_image_file(path, self.size_limits.get(BodyKind::RawImage))?
+        {
+          return Ok(Some(image));
+        }
+
+        self.metadata.truncated = truncated;
+
         Ok(Some(Body::new_file_list(files_list)))
       } else {
         if let Some(html) = unsafe { self.string_from_type(&formats, NSPasteboardTypeHTML)? } {
-          return Ok(Some(Body::new_html(html)));
+          let text = if self.include_text_with_html {
+            // A missing or empty alternative should not discard the html
+            unsafe { self.string_from_type(&formats, NSPasteboardTypeString) }
+              .ok()
+              .flatten()
+          } else {
+            None
+          };
+
+          self.metadata.source_url = string_from_pasteboard(
+            &self.pasteboard,
+            &NSString::from_str("org.chromium.source-url"),
+            false,
+          )
+          .ok()
+          .flatten();
+
+          return Ok(Some(Body::new_html_with_text(html, text)));
         }
         if let Some(plain_text) =
           unsafe { self.string_from_type(&formats, NSPasteboardTypeString)? }
@@ -305,10 +620,15 @@ impl<G: Gatekeeper> OSXObserver<G> {
 
   // Tries to read the clipboard and handles the result, which can be
   // an early exit (for skipped/empty content), or an actual error
-  fn poll_clipboard(&self) -> Result<Option<Body>, ClipboardError> {
+  fn poll_clipboard(&mut self) -> Result<Option<Body>, ClipboardError> {
+    self.metadata = EventMetadata::default();
+
     match self.extract_clipboard_content() {
       // Found content
-      Ok(Some(content)) => Ok(Some(content)),
+      Ok(Some(content)) if self.size_limits.is_below_min(&content) => Ok(None),
+      Ok(Some(content)) => content
+        .into_image_output(self.image_output, self.keep_source_bytes)
+        .map(Some),
 
       // Non-fatal errors, we just return None
       Err(ErrorWrapper::EmptyContent) => {
@@ -322,17 +642,183 @@ impl<G: Gatekeeper> OSXObserver<G> {
       Err(ErrorWrapper::ReadError(e)) => Err(e),
 
       // There was content but we could not read it
-      Ok(None) => Err(ClipboardError::NoMatchingFormat),
+      Ok(None) => {
+        if self.suppress_no_matching_format {
+          trace!("No supported format was found. Skipping it...");
+          Ok(None)
+        } else {
+          Err(ClipboardError::NoMatchingFormat)
+        }
+      }
     }
   }
 }
 
+// From [arboard](https://github.com/1Password/arboard), with modifications
+fn string_from_pasteboard(
+  pasteboard: &NSPasteboard,
+  type_: &NSPasteboardType,
+  concatenate: bool,
+) -> Result<Option<String>, ErrorWrapper> {
+  // XXX: We explicitly use `pasteboardItems` and not `stringForType` since the latter will concat
+  // multiple strings, if present, into one and return it instead of reading just the first
+  // (unless the concatenation is explicitly requested)
+  autoreleasepool(|_| {
+    if concatenate {
+      return match unsafe { pasteboard.stringForType(type_) } {
+        Some(string) if !string.is_empty() => Ok(Some(string.to_string())),
+        Some(_) => Err(ErrorWrapper::EmptyContent),
+        None => Ok(None),
+      };
+    }
+
+    // If no pasteboard items are found, we trigger the early exit
+    let contents = unsafe { pasteboard.pasteboardItems() }.ok_or(ErrorWrapper::EmptyContent)?;
+
+    for item in contents {
+      if let Some(string) = unsafe { item.stringForType(type_) } {
+        if !string.is_empty() {
+          return Ok(Some(string.to_string()));
+        } else {
+          return Err(ErrorWrapper::EmptyContent);
+        }
+      }
+    }
+
+    Ok(None)
+  })
+}
+
+// Reads the exact bytes of the type as UTF-8, without going through the pasteboard items
+fn exact_string_from_pasteboard(
+  pasteboard: &NSPasteboard,
+  type_: &NSPasteboardType,
+) -> Result<Option<String>, ErrorWrapper> {
+  autoreleasepool(|_| match unsafe { pasteboard.dataForType(type_) } {
+    Some(data) if !data.is_empty() => {
+      Ok(Some(String::from_utf8_lossy(&data.to_vec()).into_owned()))
+    }
+    Some(_) => Err(ErrorWrapper::EmptyContent),
+    None => Ok(None),
+  })
+}
+
+// Reads the text in the general pasteboard
+pub(crate) fn read_text() -> Result<Option<String>, ClipboardError> {
+  let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
+
+  unsafe { string_from_pasteboard(&pasteboard, NSPasteboardTypeString, false) }
+    .or_else(ErrorWrapper::into_read_result)
+}
+
+// Reads the image in the pasteboard, preferring png over tiff
+pub(crate) fn read_image() -> Result<Option<Body>, ClipboardError> {
+  let pasteboard = unsafe { NSPasteboard::generalPasteboard() };
+
+  autoreleasepool(|_| {
+    if let Some(data) = unsafe { pasteboard.dataForType(NSPasteboardTypePNG) }
+      && !data.is_empty()
+    {
+      return Ok(Some(Body::PngImage {
+        bytes: data.to_vec(),
+        path: None,
+      }));
+    }
+
+    if let Some(data) = unsafe { pasteboard.dataForType(NSPasteboardTypeTIFF) }
+      && !data.is_empty()
+    {
+      let image = image::load_from_memory_with_format(&data.to_vec(), ImageFormat::Tiff)
+        .map_err(|e| ClipboardError::read_with_context("Failed to load TIFF image", e))?;
+
+      return Ok(Some(Body::RawImage(RawImage::from_dynamic_image(image, None))));
+    }
+
+    Ok(None)
+  })
+}
+
+// Lists the types that are currently available in the pasteboard
+fn available_formats(pasteboard: &NSPasteboard) -> Result<Formats, ErrorWrapper> {
+  unsafe {
+    // 1. Get the NSArray of types
+    // types() returns Option<Retained<NSArray<NSPasteboardType>>>
+    let types_array = pasteboard.types().ok_or_else(|| {
+      ErrorWrapper::ReadError(ClipboardError::read("Failed to read the clipboard formats"))
+    })?;
+
+    // 2. Map NSArray -> Vec<Format>
+    let formats: Formats = types_array
+      .iter()
+      .map(|ns_string| {
+        // Convert NSString to Rust String
+        let rust_string = ns_string.to_string();
+
+        Format {
+          name: rust_string.into(), // Arc<str>
+          id: ns_string,            // Retained<NSString>
+        }
+      })
+      .collect();
+
+    Ok(formats)
+  }
+}
+
+// Opens the given pasteboard, using the AppKit constants for the standard ones
+fn open_pasteboard(name: &PasteboardName) -> Retained<NSPasteboard> {
+  let name = match name {
+    PasteboardName::General => return unsafe { NSPasteboard::generalPasteboard() },
+    PasteboardName::Find => unsafe { NSPasteboardNameFind },
+    PasteboardName::Font => unsafe { NSPasteboardNameFont },
+    PasteboardName::Ruler => unsafe { NSPasteboardNameRuler },
+    PasteboardName::Drag => unsafe { NSPasteboardNameDrag },
+    PasteboardName::Named(name) => {
+      return unsafe { NSPasteboard::pasteboardWithName(&NSString::from_str(name)) };
+    }
+  };
+
+  unsafe { NSPasteboard::pasteboardWithName(name) }
+}
+
+// Reads the data of the given custom formats that are in the given pasteboard
+pub(crate) fn read_custom_formats(
+  names: &[Arc<str>],
+  max_size: Option<u64>,
+  pasteboard: &PasteboardName,
+) -> Result<Vec<CustomFormatData>, ClipboardError> {
+  let pasteboard = open_pasteboard(pasteboard);
+
+  let Some(available_formats) = available_formats(&pasteboard)
+    .map(Some)
+    .or_else(ErrorWrapper::into_read_result)?
+  else {
+    return Ok(Vec::new());
+  };
+
+  let mut data = Vec::new();
+
+  for name in names {
+    let format_type = NSString::from_str(name.as_ref());
+
+    // The formats that are empty or too large are skipped
+    if let Some(bytes) =
+      extract_clipboard_format_macos(&pasteboard, &available_formats, &format_type, max_size)
+        .or_else(ErrorWrapper::into_read_result)?
+    {
+      data.push((name.clone(), bytes));
+    }
+  }
+
+  Ok(data)
+}
+
 // Attempts to extract a specific format from the clipboard
 pub(crate) fn extract_clipboard_format_macos(
   pasteboard: &NSPasteboard,
   available_types: &Formats,
   format_type: &NSPasteboardType,
-  max_size: Option<u32>,
+  max_size: Option<u64>,
 ) -> Result<Option<Vec<u8>>, ErrorWrapper> {
   if !available_types.contains_format(format_type) {
     return Ok(None);
@@ -351,7 +837,7 @@ pub(crate) fn extract_clipboard_format_macos(
 
         // Check the size limit. If exceeded, return Err to signal an early exit.
         if let Some(limit) = max_size {
-          if size > limit as usize {
+          if size as u64 > limit {
             debug!(
               "Found content with {} size, beyond maximum allowed size. Skipping it...",
               HumanBytes(size)
@@ -0,0 +1,111 @@
+use crate::*;
+
+/// Synthetic clipboard content, which can be used to test a [`Gatekeeper`] without reading the system clipboard.
+///
+/// A [`ClipboardContext`] created from it with [`ClipboardContext::mock`] behaves like the one that the listener passes to the gatekeeper, with these differences:
+///
+/// - The formats are listed in the order in which they were given (duplicates keep the data given last), while the order of the real formats depends on the application that owns the clipboard.
+/// - The [`id`](Format::id) of the formats is synthetic. On Windows and Linux it is the position of the format in the list, so it does not match any registered format or atom. On macOS it is the name of the format, like for the real pasteboard types.
+/// - The data is returned exactly as it was given. The conversions that the platforms may perform when a format is read (such as the ones made by X11 selection owners or by the Windows synthesized formats) do not happen, and the reads never fail.
+///
+/// The caching of [`get_bytes`](ClipboardContext::get_bytes) and its size limit (see [`max_size`](MockClipboard::max_size)) work in the same way as in the real contexts. The cache is kept for as long as the mock exists, as if all of its contexts were part of the same check.
+///
+/// Only available with the `testing` feature.
+///
+/// # Example
+///
+/// ```
+/// use clipboard_watcher::{ClipboardContext, Gatekeeper, MockClipboard};
+///
+/// let gatekeeper = |ctx: ClipboardContext| !ctx.has_format("ExcludeClipboardContentFromMonitorProcessing");
+///
+/// let clipboard = MockClipboard::new([
+///   ("text/plain", b"hello".to_vec()),
+///   ("ExcludeClipboardContentFromMonitorProcessing", Vec::new()),
+/// ]);
+///
+/// assert!(!gatekeeper.check(ClipboardContext::mock(&clipboard)));
+/// ```
+pub struct MockClipboard {
+  formats: Formats,
+  data: HashMap<Arc<str>, Vec<u8>>,
+  max_size: Option<u64>,
+  cache: FormatCache,
+}
+
+impl MockClipboard {
+  /// Creates a new mock with the given formats and their data.
+  ///
+  /// Anything that yields the names and the data of the formats can be used, such as a `HashMap<String, Vec<u8>>` or (to control the order of the formats) an array or a `Vec` of pairs.
+  #[must_use]
+  pub fn new<I, S>(formats: I) -> Self
+  where
+    I: IntoIterator<Item = (S, Vec<u8>)>,
+    S: AsRef<str>,
+  {
+    let mut data = HashMap::new();
+    let mut list = Vec::new();
+
+    for (name, bytes) in formats {
+      let name: Arc<str> = name.as_ref().into();
+
+      if data.insert(name.clone(), bytes).is_none() {
+        #[cfg(not(target_os = "macos"))]
+        let id = u32::try_from(list.len()).unwrap_or(u32::MAX);
+        #[cfg(target_os = "macos")]
+        let id = objc2_foundation::NSString::from_str(&name);
+
+        list.push(Format { name, id });
+      }
+    }
+
+    Self {
+      formats: list.into_iter().collect(),
+      data,
+      max_size: None,
+      cache: FormatCache::default(),
+    }
+  }
+
+  /// Sets the size limit for the reads made with [`get_bytes`](ClipboardContext::get_bytes), which stands in for the limit of the custom formats (see [`limits`](ClipboardEventListenerBuilder::limits)).
+  ///
+  /// By default, there is no limit. The data cached by the previous reads is discarded.
+  #[must_use]
+  #[inline]
+  pub fn max_size(mut self, max_bytes: u64) -> Self {
+    self.max_size = Some(max_bytes);
+    self.cache.get_mut().clear();
+    self
+  }
+
+  pub(crate) fn data(&self, format: &Format) -> Option<Vec<u8>> {
+    self.data.get(&format.name).cloned()
+  }
+
+  // Returns the data of the format, unless it exceeds the size limit
+  pub(crate) fn bounded_data(&self, format: &Format, max_size: Option<u64>) -> Option<Vec<u8>> {
+    let data = self.data.get(&format.name)?;
+
+    if max_size.is_some_and(|max| data.len() as u64 > max) {
+      return None;
+    }
+
+    Some(data.clone())
+  }
+}
+
+impl<'a> ClipboardContext<'a> {
+  /// Creates a context that serves the content of a [`MockClipboard`], to test a [`Gatekeeper`] without the system clipboard.
+  ///
+  /// See [`MockClipboard`] for the differences with the contexts created by the listener. Only available with the `testing` feature.
+  #[must_use]
+  #[inline]
+  pub const fn mock(clipboard: &'a MockClipboard) -> Self {
+    Self {
+      formats: &clipboard.formats,
+      max_size: clipboard.max_size,
+      cache: &clipboard.cache,
+      source: ContextSource::Mock(clipboard),
+    }
+  }
+}
@@ -0,0 +1,152 @@
+use crate::*;
+
+/// The options of a listener, which can be applied all at once with [`from_options`](crate::ClipboardEventListenerBuilder::from_options).
+///
+/// With the `serde` feature enabled, this can be deserialized from a configuration file. Every field is optional, and the missing ones keep their default value. Each field has the same effect as the builder method with the same name (the durations are expressed in milliseconds). The options that cannot be serialized, such as the [`Gatekeeper`] or the [`on_change`](crate::ClipboardEventListenerBuilder::on_change) callback, must still be set with the builder.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct WatcherOptions {
+  /// See [`interval`](crate::ClipboardEventListenerBuilder::interval).
+  pub interval_ms: Option<u64>,
+  /// The minimum and maximum intervals. See [`adaptive_interval`](crate::ClipboardEventListenerBuilder::adaptive_interval).
+  pub adaptive_interval_ms: Option<(u64, u64)>,
+  /// The duration and the interval of the warmup. See [`warmup`](crate::ClipboardEventListenerBuilder::warmup).
+  pub warmup_ms: Option<(u64, u64)>,
+  /// See [`read_poll_interval`](crate::ClipboardEventListenerBuilder::read_poll_interval).
+  pub read_poll_interval_ms: Option<u64>,
+  /// See [`skip_unchanged_targets`](crate::ClipboardEventListenerBuilder::skip_unchanged_targets).
+  pub skip_unchanged_targets: bool,
+  /// See [`skip_clipboard_manager_duplicates`](crate::ClipboardEventListenerBuilder::skip_clipboard_manager_duplicates).
+  pub skip_clipboard_manager_duplicates: bool,
+  /// See [`event_driven`](crate::ClipboardEventListenerBuilder::event_driven).
+  pub event_driven: bool,
+  /// See [`with_custom_formats`](crate::ClipboardEventListenerBuilder::with_custom_formats).
+  pub custom_formats: Vec<String>,
+  /// See [`collect_custom_formats`](crate::ClipboardEventListenerBuilder::collect_custom_formats).
+  pub collect_custom_formats: bool,
+  /// See [`with_text_custom_formats`](crate::ClipboardEventListenerBuilder::with_text_custom_formats).
+  pub text_custom_formats: Vec<String>,
+  /// The names of the formats and the maximum number of bytes that are read from each of them. See [`with_custom_format_prefix`](crate::ClipboardEventListenerBuilder::with_custom_format_prefix).
+  pub custom_format_prefixes: Vec<(String, usize)>,
+  /// See [`with_text_formats`](crate::ClipboardEventListenerBuilder::with_text_formats).
+  pub text_formats: Vec<String>,
+  /// See [`limits`](crate::ClipboardEventListenerBuilder::limits).
+  pub limits: Limits,
+  /// See [`directory_handling`](crate::ClipboardEventListenerBuilder::directory_handling).
+  pub directory_handling: DirectoryHandling,
+  /// See [`delayed_rendering`](crate::ClipboardEventListenerBuilder::delayed_rendering).
+  pub delayed_rendering: DelayedRendering,
+  /// See [`include_text_with_html`](crate::ClipboardEventListenerBuilder::include_text_with_html).
+  pub include_text_with_html: bool,
+  /// See [`coalesce_text_with_files`](crate::ClipboardEventListenerBuilder::coalesce_text_with_files).
+  pub coalesce_text_with_files: bool,
+  /// See [`image_mode`](crate::ClipboardEventListenerBuilder::image_mode).
+  pub image_mode: ImageMode,
+  /// See [`image_output`](crate::ClipboardEventListenerBuilder::image_output).
+  pub image_output: ImageOutput,
+  /// See [`image_with_path_mode`](crate::ClipboardEventListenerBuilder::image_with_path_mode).
+  pub image_path_mode: ImagePathMode,
+  /// See [`keep_source_bytes`](crate::ClipboardEventListenerBuilder::keep_source_bytes).
+  pub keep_source_bytes: bool,
+  /// See [`deliver_undecoded_on_error`](crate::ClipboardEventListenerBuilder::deliver_undecoded_on_error).
+  pub deliver_undecoded_on_error: bool,
+  /// See [`load_image_from_single_file`](crate::ClipboardEventListenerBuilder::load_image_from_single_file).
+  pub load_image_from_single_file: bool,
+  /// See [`suppress_no_matching_format`](crate::ClipboardEventListenerBuilder::suppress_no_matching_format).
+  pub suppress_no_matching_format: bool,
+  /// See [`concatenate_text_items`](crate::ClipboardEventListenerBuilder::concatenate_text_items).
+  pub concatenate_text_items: bool,
+  /// See [`exact_text`](crate::ClipboardEventListenerBuilder::exact_text).
+  pub exact_text: bool,
+  /// See [`respect_nspasteboard_conventions`](crate::ClipboardEventListenerBuilder::respect_nspasteboard_conventions).
+  pub respect_nspasteboard_conventions: bool,
+  /// See [`main_thread_polling`](crate::ClipboardEventListenerBuilder::main_thread_polling).
+  pub main_thread_polling: bool,
+  /// See [`macos_pasteboard`](crate::ClipboardEventListenerBuilder::macos_pasteboard).
+  pub macos_pasteboard: PasteboardName,
+  /// See [`history`](crate::ClipboardEventListenerBuilder::history).
+  pub history: usize,
+  /// See [`dedicated_dispatcher`](crate::ClipboardEventListenerBuilder::dedicated_dispatcher).
+  pub dedicated_dispatcher: bool,
+  /// See [`memory_budget`](crate::ClipboardEventListenerBuilder::memory_budget).
+  pub memory_budget: Option<u64>,
+  /// See [`emit_on_resume`](crate::ClipboardEventListenerBuilder::emit_on_resume).
+  pub emit_on_resume: bool,
+  /// See [`shutdown_timeout`](crate::ClipboardEventListenerBuilder::shutdown_timeout).
+  pub shutdown_timeout_ms: Option<u64>,
+  /// See [`selections`](crate::ClipboardEventListenerBuilder::selections). If empty, it defaults to [`Selection::Clipboard`].
+  pub selections: Vec<Selection>,
+  /// See [`exclude_sources`](crate::ClipboardEventListenerBuilder::exclude_sources).
+  pub excluded_sources: Vec<String>,
+}
+
+impl ClipboardEventListenerBuilder {
+  /// Creates a builder with the given [`WatcherOptions`], such as those loaded from a configuration file.
+  ///
+  /// The builder can still be used to set the remaining options, such as the [`Gatekeeper`].
+  #[must_use]
+  pub fn from_options(options: WatcherOptions) -> Self {
+    let mut builder = Self::default()
+      .skip_unchanged_targets(options.skip_unchanged_targets)
+      .skip_clipboard_manager_duplicates(options.skip_clipboard_manager_duplicates)
+      .event_driven(options.event_driven)
+      .with_custom_formats(options.custom_formats)
+      .collect_custom_formats(options.collect_custom_formats)
+      .with_text_custom_formats(options.text_custom_formats)
+      .with_text_formats(options.text_formats)
+      .limits(options.limits)
+      .directory_handling(options.directory_handling)
+      .delayed_rendering(options.delayed_rendering)
+      .include_text_with_html(options.include_text_with_html)
+      .coalesce_text_with_files(options.coalesce_text_with_files)
+      .image_mode(options.image_mode)
+      .image_output(options.image_output)
+      .image_with_path_mode(options.image_path_mode)
+      .keep_source_bytes(options.keep_source_bytes)
+      .deliver_undecoded_on_error(options.deliver_undecoded_on_error)
+      .load_image_from_single_file(options.load_image_from_single_file)
+      .suppress_no_matching_format(options.suppress_no_matching_format)
+      .concatenate_text_items(options.concatenate_text_items)
+      .exact_text(options.exact_text)
+      .respect_nspasteboard_conventions(options.respect_nspasteboard_conventions)
+      .main_thread_polling(options.main_thread_polling)
+      .macos_pasteboard(options.macos_pasteboard)
+      .history(options.history)
+      .dedicated_dispatcher(options.dedicated_dispatcher)
+      .emit_on_resume(options.emit_on_resume)
+      .selections(options.selections)
+      .exclude_sources(options.excluded_sources);
+
+    for (name, max_prefix_bytes) in options.custom_format_prefixes {
+      builder = builder.with_custom_format_prefix(name, max_prefix_bytes);
+    }
+
+    if let Some(interval) = options.interval_ms {
+      builder = builder.interval(Duration::from_millis(interval));
+    }
+
+    if let Some((min, max)) = options.adaptive_interval_ms {
+      builder = builder.adaptive_interval(Duration::from_millis(min), Duration::from_millis(max));
+    }
+
+    if let Some((duration, interval)) = options.warmup_ms {
+      builder = builder.warmup(Duration::from_millis(duration), Duration::from_millis(interval));
+    }
+
+    if let Some(max_wait) = options.read_poll_interval_ms {
+      builder = builder.read_poll_interval(Duration::from_millis(max_wait));
+    }
+
+    if let Some(max_bytes) = options.memory_budget {
+      builder = builder.memory_budget(max_bytes);
+    }
+
+    if let Some(timeout) = options.shutdown_timeout_ms {
+      builder = builder.shutdown_timeout(Duration::from_millis(timeout));
+    }
+
+    builder
+  }
+}
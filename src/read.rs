@@ -0,0 +1,70 @@
+use crate::*;
+
+/// The name of a custom format, along with its raw bytes.
+pub type CustomFormatData = (Arc<str>, Vec<u8>);
+
+/// Reads the plain text that is currently on the clipboard, without spawning a [`ClipboardEventListener`].
+///
+/// Only the plain text formats are inspected, so this is faster than a full extraction. Returns `None` if the clipboard does not contain any text, or if the text is empty.
+///
+/// On Linux, this reads the `CLIPBOARD` selection with a temporary connection to the X11 server.
+#[inline(never)]
+#[cold]
+pub fn read_text() -> Result<Option<String>, ClipboardError> {
+  #[cfg(target_os = "linux")]
+  {
+    linux::observer::read_text()
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    macos::observer::read_text()
+  }
+
+  #[cfg(windows)]
+  {
+    win::observer::read_text()
+  }
+}
+
+// Reads the image that is currently on the clipboard, as a png or raw image
+pub(crate) fn read_image() -> Result<Option<Body>, ClipboardError> {
+  #[cfg(target_os = "linux")]
+  {
+    linux::observer::read_image()
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    macos::observer::read_image()
+  }
+
+  #[cfg(windows)]
+  {
+    win::observer::read_image()
+  }
+}
+
+// Reads the data of the given custom formats that are currently on the clipboard, skipping the absent ones.
+// The pasteboard is only used on macOS.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub(crate) fn read_custom_formats(
+  names: &[Arc<str>],
+  max_size: Option<u64>,
+  pasteboard: &PasteboardName,
+) -> Result<Vec<CustomFormatData>, ClipboardError> {
+  #[cfg(target_os = "linux")]
+  {
+    linux::observer::read_custom_formats(names, max_size)
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    macos::observer::read_custom_formats(names, max_size, pasteboard)
+  }
+
+  #[cfg(windows)]
+  {
+    win::observer::read_custom_formats(names, max_size)
+  }
+}
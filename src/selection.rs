@@ -0,0 +1,95 @@
+use crate::*;
+
+/// An X11 selection that can be monitored by the listener.
+///
+/// This is only used on Linux, and it is ignored on other platforms.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Selection {
+  /// The `CLIPBOARD` selection, used for explicit copy/paste actions.
+  #[default]
+  Clipboard,
+  /// The `PRIMARY` selection, which holds the most recently selected text.
+  Primary,
+  /// The `SECONDARY` selection.
+  Secondary,
+  /// A selection with an arbitrary name, used by some niche applications.
+  Named(Arc<str>),
+}
+
+impl Selection {
+  /// Returns the name of the atom for this selection.
+  #[must_use]
+  #[inline]
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Clipboard => "CLIPBOARD",
+      Self::Primary => "PRIMARY",
+      Self::Secondary => "SECONDARY",
+      Self::Named(name) => name,
+    }
+  }
+}
+
+impl From<&str> for Selection {
+  #[inline]
+  fn from(value: &str) -> Self {
+    match value {
+      "CLIPBOARD" => Self::Clipboard,
+      "PRIMARY" => Self::Primary,
+      "SECONDARY" => Self::Secondary,
+      _ => Self::Named(value.into()),
+    }
+  }
+}
+
+impl Display for Selection {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+/// A pasteboard that can be monitored by the listener.
+///
+/// This is only used on macOS, and it is ignored on other platforms.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PasteboardName {
+  /// The general pasteboard, used for explicit copy/paste actions.
+  #[default]
+  General,
+  /// The find pasteboard, which holds the text of the most recent search (shared by the find bars of most applications).
+  Find,
+  /// The font pasteboard, used to copy and paste font styles.
+  Font,
+  /// The ruler pasteboard, used to copy and paste paragraph formatting.
+  Ruler,
+  /// The drag pasteboard, used for drag and drop operations.
+  Drag,
+  /// A pasteboard with an arbitrary name, such as one that is shared between specific applications.
+  Named(Arc<str>),
+}
+
+impl PasteboardName {
+  /// Returns the name of this pasteboard, as used by `NSPasteboard`.
+  ///
+  /// The names of the standard pasteboards are the values of the `NSPasteboardName` constants, which the listener uses to open them on macOS.
+  #[must_use]
+  #[inline]
+  pub fn name(&self) -> &str {
+    match self {
+      Self::General => "Apple CFPasteboard general",
+      Self::Find => "Apple CFPasteboard find",
+      Self::Font => "Apple CFPasteboard font",
+      Self::Ruler => "Apple CFPasteboard ruler",
+      Self::Drag => "Apple CFPasteboard drag",
+      Self::Named(name) => name,
+    }
+  }
+}
+
+impl Display for PasteboardName {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.name())
+  }
+}
@@ -0,0 +1,46 @@
+use crate::*;
+
+// A swappable slot that holds the gatekeeper currently used by a listener.
+// It is shared between the listener and its observer, so that the gatekeeper
+// can be replaced while the observer is running.
+pub(crate) struct SharedGatekeeper {
+  current: Mutex<Arc<dyn Gatekeeper>>,
+  // Used to report the rejected changes
+  body_senders: Arc<BodySenders>,
+}
+
+impl SharedGatekeeper {
+  pub(crate) fn new<G: Gatekeeper>(gatekeeper: G, body_senders: Arc<BodySenders>) -> Self {
+    Self {
+      current: Mutex::new(Arc::new(gatekeeper)),
+      body_senders,
+    }
+  }
+
+  /// Replaces the current gatekeeper.
+  pub(crate) fn set<G: Gatekeeper>(&self, gatekeeper: G) {
+    let mut guard = self.current.lock().unwrap();
+    *guard = Arc::new(gatekeeper);
+  }
+
+  /// Runs the check with the current gatekeeper, and reports the change to the rejected changes streams if it fails.
+  pub(crate) fn check(&self, ctx: ClipboardContext) -> bool {
+    // The lock is released before running the check, so that a slow gatekeeper
+    // does not block a concurrent swap
+    let gatekeeper = self.current.lock().unwrap().clone();
+
+    let passed = gatekeeper.check(ctx);
+
+    if !passed && self.body_senders.has_rejection_streams() {
+      self.body_senders.reject_all(&RejectedChange {
+        formats: ctx
+          .formats()
+          .iter()
+          .map(|format| format.name.to_string())
+          .collect(),
+      });
+    }
+
+    passed
+  }
+}
@@ -0,0 +1,294 @@
+use std::{
+  io::{self, Write},
+  path::Path,
+  sync::atomic::AtomicU64,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// A lightweight representation of a [`Body`] for persistent storage, where the binary payloads are kept in external files.
+///
+/// It is created with [`Body::to_stored`], and the original [`Body`] can be reconstructed with [`StoredBody::load`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum StoredBody {
+  /// HTML content.
+  Html(String),
+  /// Plaintext content.
+  PlainText(String),
+  /// A [`Body::RawImage`], with its bytes stored in `asset` (and its source bytes in `source_asset`, if present).
+  RawImage {
+    asset: PathBuf,
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    #[serde(default = "single_frame")]
+    frame_count: u32,
+    #[serde(default)]
+    had_alpha: bool,
+    path: Option<PathBuf>,
+    source_asset: Option<PathBuf>,
+  },
+  /// A [`Body::PngImage`], with its bytes stored in `asset`.
+  PngImage {
+    asset: PathBuf,
+    path: Option<PathBuf>,
+  },
+  /// A [`Body::EncodedImage`], with its bytes stored in `asset`.
+  EncodedImage {
+    format: EncodedImageFormat,
+    asset: PathBuf,
+    path: Option<PathBuf>,
+  },
+  /// A list of files.
+  FileList(Vec<PathBuf>),
+  /// A [`Body::Custom`], with its data stored in `asset`.
+  Custom { name: Arc<str>, asset: PathBuf },
+  /// Text in a specific format.
+  Text { mime: Arc<str>, content: String },
+  /// A color, in the sRGB color space.
+  Color { r: u8, g: u8, b: u8, a: u8 },
+  /// Multiple representations of the same clipboard item.
+  Multi(Vec<Self>),
+}
+
+// Writes the bytes to a file in the asset directory, named after the SHA-256 hash of its content.
+// Identical payloads are only written once. An existing file with the same name is only reused
+// if its content matches, so that a damaged asset is replaced rather than shared.
+fn write_asset(asset_dir: &Path, bytes: &[u8], extension: &str) -> io::Result<PathBuf> {
+  let hash: String = Sha256::digest(bytes)
+    .iter()
+    .map(|byte| format!("{byte:02x}"))
+    .collect();
+
+  let asset = asset_dir.join(format!("{hash}.{extension}"));
+
+  if asset_matches(&asset, bytes)? {
+    return Ok(asset);
+  }
+
+  std::fs::create_dir_all(asset_dir)?;
+
+  // The content is written to a temporary file and then moved into place,
+  // so that an interrupted write never leaves a truncated asset behind
+  static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+  let temp = asset_dir.join(format!(
+    ".{hash}.{}-{}.tmp",
+    std::process::id(),
+    TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+  ));
+
+  let result = std::fs::File::create(&temp)
+    .and_then(|mut file| {
+      file.write_all(bytes)?;
+      file.sync_all()
+    })
+    .and_then(|()| std::fs::rename(&temp, &asset));
+
+  if let Err(e) = result {
+    let _ = std::fs::remove_file(&temp);
+    return Err(e);
+  }
+
+  Ok(asset)
+}
+
+// Whether the asset file already exists with the given content
+fn asset_matches(asset: &Path, bytes: &[u8]) -> io::Result<bool> {
+  match std::fs::metadata(asset) {
+    Ok(metadata) if metadata.len() == bytes.len() as u64 => Ok(std::fs::read(asset)? == bytes),
+    Ok(_) => Ok(false),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+    Err(e) => Err(e),
+  }
+}
+
+impl Body {
+  /// Converts this body into a [`StoredBody`], writing the binary payloads (images and custom formats) to files inside `asset_dir`, which is created if it does not exist.
+  ///
+  /// The assets are named after the SHA-256 hash of their content, so the same payload is only written once, and the names stay the same across runs and versions. An asset that is shared by multiple items is stored in a single file, which all of them reference (see [`assets`](StoredBody::assets)). Each asset is written to a temporary file in `asset_dir` and then renamed, so an interrupted write does not leave a partial asset behind.
+  ///
+  /// # Example
+  ///
+  /// ```rust
+  /// use clipboard_watcher::Body;
+  ///
+  /// let asset_dir = std::env::temp_dir().join("clipboard-watcher-doc-assets");
+  ///
+  /// let body = Body::Custom {
+  ///   name: "application/x-custom".into(),
+  ///   data: vec![1, 2, 3],
+  /// };
+  ///
+  /// let stored = body.to_stored(&asset_dir)?;
+  ///
+  /// assert_eq!(stored.load()?, body);
+  /// # Ok::<(), std::io::Error>(())
+  /// ```
+  pub fn to_stored(&self, asset_dir: &Path) -> io::Result<StoredBody> {
+    let stored = match self {
+      Self::Html(html) => StoredBody::Html(html.clone()),
+      Self::PlainText(text) => StoredBody::PlainText(text.clone()),
+      Self::RawImage(image) => StoredBody::RawImage {
+        asset: write_asset(asset_dir, &image.bytes, "raw")?,
+        width: image.width,
+        height: image.height,
+        color_type: image.color_type,
+        frame_count: image.frame_count,
+        had_alpha: image.had_alpha,
+        path: image.path.clone(),
+        source_asset: image
+          .source_bytes
+          .as_deref()
+          .map(|bytes| write_asset(asset_dir, bytes, "src"))
+          .transpose()?,
+      },
+      Self::PngImage { bytes, path } => StoredBody::PngImage {
+        asset: write_asset(asset_dir, bytes, "png")?,
+        path: path.clone(),
+      },
+      Self::EncodedImage {
+        format,
+        bytes,
+        path,
+      } => StoredBody::EncodedImage {
+        format: *format,
+        asset: write_asset(asset_dir, bytes, "bin")?,
+        path: path.clone(),
+      },
+      Self::FileList(files) => StoredBody::FileList(files.clone()),
+      Self::Custom { name, data } => StoredBody::Custom {
+        name: name.clone(),
+        asset: write_asset(asset_dir, data, "bin")?,
+      },
+      Self::Text { mime, content } => StoredBody::Text {
+        mime: mime.clone(),
+        content: content.clone(),
+      },
+      Self::Color { r, g, b, a } => StoredBody::Color {
+        r: *r,
+        g: *g,
+        b: *b,
+        a: *a,
+      },
+      Self::Multi(bodies) => StoredBody::Multi(
+        bodies
+          .iter()
+          .map(|body| body.to_stored(asset_dir))
+          .collect::<io::Result<_>>()?,
+      ),
+    };
+
+    Ok(stored)
+  }
+}
+
+impl StoredBody {
+  /// Reconstructs the original [`Body`], reading the binary payloads from their asset files.
+  pub fn load(&self) -> io::Result<Body> {
+    let body = match self {
+      Self::Html(html) => Body::Html(html.clone()),
+      Self::PlainText(text) => Body::PlainText(text.clone()),
+      Self::RawImage {
+        asset,
+        width,
+        height,
+        color_type,
+        frame_count,
+        had_alpha,
+        path,
+        source_asset,
+      } => {
+        let bytes = std::fs::read(asset)?;
+
+        if bytes.len() != (*width as usize) * (*height as usize) * color_type.channels() {
+          return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+              "The size of the image asset `{}` does not match its dimensions",
+              asset.display()
+            ),
+          ));
+        }
+
+        Body::RawImage(RawImage {
+          bytes,
+          width: *width,
+          height: *height,
+          path: path.clone(),
+          source_bytes: source_asset
+            .as_ref()
+            .map(|asset| std::fs::read(asset).map(Into::into))
+            .transpose()?,
+          frame_count: *frame_count,
+          had_alpha: *had_alpha,
+          color_type: *color_type,
+        })
+      }
+      Self::PngImage { asset, path } => Body::PngImage {
+        bytes: std::fs::read(asset)?,
+        path: path.clone(),
+      },
+      Self::EncodedImage {
+        format,
+        asset,
+        path,
+      } => Body::EncodedImage {
+        format: *format,
+        bytes: std::fs::read(asset)?,
+        path: path.clone(),
+      },
+      Self::FileList(files) => Body::FileList(files.clone()),
+      Self::Custom { name, asset } => Body::Custom {
+        name: name.clone(),
+        data: std::fs::read(asset)?,
+      },
+      Self::Text { mime, content } => Body::Text {
+        mime: mime.clone(),
+        content: content.clone(),
+      },
+      Self::Color { r, g, b, a } => Body::Color {
+        r: *r,
+        g: *g,
+        b: *b,
+        a: *a,
+      },
+      Self::Multi(bodies) => Body::Multi(
+        bodies
+          .iter()
+          .map(Self::load)
+          .collect::<io::Result<_>>()?,
+      ),
+    };
+
+    Ok(body)
+  }
+
+  /// Returns the paths of the asset files referenced by this item (including the nested ones in [`StoredBody::Multi`]).
+  ///
+  /// The assets are shared: every item with the same payload references the same file, so a file should only be deleted once no other stored item references it (for example, by counting the references to each path across the storage).
+  #[must_use]
+  pub fn assets(&self) -> Vec<&Path> {
+    match self {
+      Self::RawImage {
+        asset,
+        source_asset,
+        ..
+      } => std::iter::once(asset)
+        .chain(source_asset)
+        .map(PathBuf::as_path)
+        .collect(),
+      Self::PngImage { asset, .. }
+      | Self::EncodedImage { asset, .. }
+      | Self::Custom { asset, .. } => vec![asset.as_path()],
+      Self::Multi(bodies) => bodies.iter().flat_map(Self::assets).collect(),
+      Self::Html(_)
+      | Self::PlainText(_)
+      | Self::FileList(_)
+      | Self::Text { .. }
+      | Self::Color { .. } => Vec::new(),
+    }
+  }
+}
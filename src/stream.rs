@@ -6,16 +6,66 @@ use crate::*;
 #[derive(Debug)]
 pub struct ClipboardStream {
   pub(crate) id: StreamId,
-  pub(crate) body_rx: Pin<Box<Receiver<ClipboardResult>>>,
+  pub(crate) body_rx: EventReceiver,
   pub(crate) body_senders: Arc<BodySenders>,
 }
 
+impl ClipboardStream {
+  /// Returns the [`StreamId`] of this stream.
+  #[must_use]
+  #[inline]
+  pub const fn id(&self) -> &StreamId {
+    &self.id
+  }
+
+  /// Drains and returns the items that are currently in the buffer of this stream, without waiting for new ones.
+  ///
+  /// This can be used in shutdown handlers to process the remaining items, which would otherwise be discarded when the stream is dropped. The stream remains usable afterwards, and it yields the items received after this call.
+  #[inline]
+  pub fn flush_pending(&mut self) -> Vec<ClipboardResult> {
+    std::iter::from_fn(|| self.body_rx.try_recv())
+      .map(|result| result.map(|event| event.body))
+      .collect()
+  }
+
+  /// Returns the number of events that this stream has missed because its buffer was full.
+  ///
+  /// This also counts the events evicted by the [`memory_budget`](crate::ClipboardEventListenerBuilder::memory_budget), and the ones dropped while the queue of the [`dedicated_dispatcher`](crate::ClipboardEventListenerBuilder::dedicated_dispatcher) was full.
+  ///
+  /// A growing count means that the stream is not being polled fast enough for the rate of clipboard changes, and that it needs a bigger buffer or faster processing.
+  #[must_use]
+  #[inline]
+  pub fn dropped_count(&self) -> u64 {
+    self.body_rx.dropped_count()
+  }
+
+  /// Polls for the next item of this stream, registering the [`Waker`](std::task::Waker) of the given [`Context`] to be notified when a new item arrives.
+  ///
+  /// This is the same as [`Stream::poll_next`], but it does not require the stream to be pinned, nor the [`Stream`] trait to be in scope. It can be used to integrate the stream with an event loop that provides its own reactor (such as the ones in GUI frameworks), without an async runtime: the loop can poll the stream with a waker that schedules a new iteration of the loop, and drain the items until this returns [`Poll::Pending`].
+  ///
+  /// The waker is stored by the channel of the stream, and it is woken from the thread that delivers the events (which is the thread of the observer, or the dispatcher thread if [`dedicated_dispatcher`](crate::ClipboardEventListenerBuilder::dedicated_dispatcher) is enabled), right after an item is sent, or when the stream is closed. Only the waker from the most recent call is woken, so it must be passed again after every call that returns [`Poll::Pending`]. Since it is woken from another thread, it should only signal the event loop (for example, with a proxy to the loop) rather than do any work with the items.
+  #[inline]
+  pub fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<ClipboardResult>> {
+    self
+      .body_rx
+      .poll_next(cx)
+      .map(|item| item.map(|result| result.map(|event| event.body)))
+  }
+
+  /// Converts this stream into a [`ClipboardEventStream`], which yields the full [`ClipboardEvent`] (with its [`EventMetadata`]) rather than just the [`Body`].
+  #[must_use]
+  #[inline]
+  pub const fn with_metadata(self) -> ClipboardEventStream {
+    ClipboardEventStream { inner: self }
+  }
+}
+
 impl Stream for ClipboardStream {
   type Item = ClipboardResult;
 
   #[inline]
   fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-    self.body_rx.as_mut().poll_next(cx)
+    self.poll_event(cx)
   }
 }
 
@@ -25,6 +75,161 @@ impl Drop for ClipboardStream {
   }
 }
 
-/// An Id to specify the [`ClipboardStream`].
-#[derive(Debug, Clone, Eq, Hash, PartialEq)]
-pub(crate) struct StreamId(pub(crate) usize);
+/// Like [`ClipboardStream`], but yields [`ClipboardEvent`]s, which also carry the [`EventMetadata`] for each change.
+#[derive(Debug)]
+pub struct ClipboardEventStream {
+  inner: ClipboardStream,
+}
+
+impl ClipboardEventStream {
+  /// Returns the [`StreamId`] of this stream.
+  #[must_use]
+  #[inline]
+  pub const fn id(&self) -> &StreamId {
+    &self.inner.id
+  }
+
+  /// Drains and returns the events that are currently in the buffer of this stream, without waiting for new ones.
+  ///
+  /// See [`ClipboardStream::flush_pending`] for more information.
+  #[inline]
+  pub fn flush_pending(&mut self) -> Vec<EventResult> {
+    std::iter::from_fn(|| self.inner.body_rx.try_recv()).collect()
+  }
+
+  /// Returns the number of events that this stream has missed because its buffer was full.
+  ///
+  /// See [`ClipboardStream::dropped_count`] for more information.
+  #[must_use]
+  #[inline]
+  pub fn dropped_count(&self) -> u64 {
+    self.inner.dropped_count()
+  }
+}
+
+impl Stream for ClipboardEventStream {
+  type Item = EventResult;
+
+  #[inline]
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.inner.body_rx.poll_next(cx)
+  }
+}
+
+/// Merges multiple [`ClipboardStream`]s (for example, from listeners that watch different selections) into a single stream, which tags each item with the [`StreamId`] of the stream that received it.
+///
+/// The items are yielded as soon as any of the streams receives them. The merged stream ends when all of the streams have ended, and dropping it drops (and so closes) all of the underlying streams.
+#[inline]
+pub fn merge(
+  streams: Vec<ClipboardStream>,
+) -> impl Stream<Item = (StreamId, ClipboardResult)> + use<> {
+  futures::stream::select_all(streams.into_iter().map(|stream| {
+    let id = stream.id().clone();
+
+    stream.map(move |result| (id.clone(), result))
+  }))
+}
+
+/// A stream that yields a unit each time a clipboard change is detected, without extracting its content.
+///
+/// It is created with [`ClipboardEventListener::change_ticks`].
+#[derive(Debug)]
+pub struct ChangeTicks {
+  pub(crate) id: StreamId,
+  pub(crate) tick_rx: EventReceiver<()>,
+  pub(crate) body_senders: Arc<BodySenders>,
+}
+
+impl Drop for ChangeTicks {
+  fn drop(&mut self) {
+    self.body_senders.unregister_ticks(&self.id);
+  }
+}
+
+impl Stream for ChangeTicks {
+  type Item = ();
+
+  #[inline]
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.tick_rx.poll_next(cx)
+  }
+}
+
+/// A stream that yields the clipboard changes rejected by the [`Gatekeeper`].
+///
+/// It is created with [`ClipboardEventListener::rejected_stream`].
+#[derive(Debug)]
+pub struct RejectedStream {
+  pub(crate) id: StreamId,
+  pub(crate) rejection_rx: EventReceiver<RejectedChange>,
+  pub(crate) body_senders: Arc<BodySenders>,
+}
+
+impl RejectedStream {
+  /// Returns the [`StreamId`] of this stream.
+  #[must_use]
+  #[inline]
+  pub const fn id(&self) -> &StreamId {
+    &self.id
+  }
+}
+
+impl Drop for RejectedStream {
+  fn drop(&mut self) {
+    self.body_senders.unregister_rejections(&self.id);
+  }
+}
+
+impl Stream for RejectedStream {
+  type Item = RejectedChange;
+
+  #[inline]
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.rejection_rx.poll_next(cx)
+  }
+}
+
+/// The identifier of a [`ClipboardStream`].
+///
+/// It can be obtained with [`ClipboardStream::id`], and it can be used to correlate the streams with some external bookkeeping, or to identify them in logs.
+///
+/// The ids come from a counter shared by all of the listeners in the process, so they are unique across the listeners until the counter wraps around after [`usize::MAX`] streams. After that, an id is only guaranteed to be unique among the live streams of the same kind and of the same listener: the ids that are still used by those streams are skipped, so a new stream never replaces a live one, but an id may be shared with a stream of another listener.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct StreamId(pub(crate) usize);
+
+// Shared by all of the listeners, so that the ids of their streams never collide (such as when they are merged)
+static NEXT_STREAM_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl StreamId {
+  fn next() -> Self {
+    Self(NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed))
+  }
+
+  // Inserts the value with a new id, which is returned. After the counter wraps around,
+  // the ids that are still in use are skipped, so that a live stream is never replaced.
+  pub(crate) fn insert_new<T>(map: &mut HashMap<Self, T>, value: T) -> Self {
+    loop {
+      let id = Self::next();
+
+      if let std::collections::hash_map::Entry::Vacant(entry) = map.entry(id.clone()) {
+        entry.insert(value);
+        return id;
+      }
+
+      warn!("The stream id {id} is still in use. Skipping it...");
+    }
+  }
+
+  /// Returns the numeric value of this identifier.
+  #[must_use]
+  #[inline]
+  pub const fn get(&self) -> usize {
+    self.0
+  }
+}
+
+impl Display for StreamId {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
@@ -0,0 +1,112 @@
+use std::{any::Any, panic::AssertUnwindSafe};
+
+use crate::*;
+
+const MIN_RESTART_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+
+// Runs the observer created by `init` until the stop signal is received.
+//
+// The result of the first initialization is reported through `init_tx`. If the observer exits
+// because of a fatal error and `restart` is set, a new observer is created after a backoff period
+// that doubles on every consecutive failure.
+//
+// A panic in the observer is caught and reported to the streams as a fatal error, so it is
+// handled like any other failure.
+pub(crate) fn supervise<O, F>(
+  stop: &AtomicBool,
+  restart: &AtomicBool,
+  body_senders: &Arc<BodySenders>,
+  init_tx: SyncSender<Result<(), String>>,
+  mut init: F,
+) where
+  O: Observer,
+  F: FnMut() -> Result<O, String>,
+{
+  let mut init_tx = Some(init_tx);
+  let mut backoff = MIN_RESTART_BACKOFF;
+
+  loop {
+    match init() {
+      Ok(mut observer) => {
+        if let Some(init_tx) = init_tx.take() {
+          init_tx.send(Ok(())).unwrap();
+        }
+
+        let started_at = Instant::now();
+
+        let result =
+          std::panic::catch_unwind(AssertUnwindSafe(|| observer.observe(body_senders.clone())));
+
+        if let Err(payload) = result {
+          let msg = format!("The clipboard monitor panicked: {}", panic_message(&*payload));
+
+          error!("{msg}");
+
+          body_senders.send_all(&Err(ClipboardError::monitor(msg)));
+        }
+
+        // The observer was healthy for a while, so this is not a crash loop
+        if started_at.elapsed() > MAX_RESTART_BACKOFF {
+          backoff = MIN_RESTART_BACKOFF;
+        }
+      }
+      Err(e) => {
+        // Initialization errors are returned directly from `spawn`
+        if let Some(init_tx) = init_tx.take() {
+          init_tx.send(Err(e)).unwrap();
+          return;
+        }
+
+        error!("Failed to restart the clipboard monitor: {e}");
+
+        body_senders.send_all(&Err(ClipboardError::monitor(e)));
+      }
+    };
+
+    if stop.load(Ordering::Relaxed) || !restart.load(Ordering::Relaxed) {
+      break;
+    }
+
+    warn!("Restarting the clipboard monitor in {backoff:?}");
+
+    if !sleep_unless_stopped(stop, backoff) {
+      break;
+    }
+
+    backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+  }
+}
+
+// Extracts the message from a panic payload
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+  if let Some(msg) = payload.downcast_ref::<&str>() {
+    msg
+  } else if let Some(msg) = payload.downcast_ref::<String>() {
+    msg
+  } else {
+    "unknown panic payload"
+  }
+}
+
+// Sleeps for the given duration, waking up periodically to check the stop signal.
+// Returns false if the stop signal was received in the meantime.
+pub(crate) fn sleep_unless_stopped(stop: &AtomicBool, duration: Duration) -> bool {
+  const STEP: Duration = Duration::from_millis(50);
+
+  let deadline = Instant::now() + duration;
+
+  loop {
+    if stop.load(Ordering::Relaxed) {
+      return false;
+    }
+
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    if remaining.is_zero() {
+      return true;
+    }
+
+    std::thread::sleep(remaining.min(STEP));
+  }
+}
@@ -0,0 +1,91 @@
+/// The parts of an item in the `HTML Format` (`CF_HTML`) used by the Windows clipboard, as returned by [`parse_cf_html`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfHtml {
+  /// The copied html, between the `StartFragment` and `EndFragment` offsets.
+  pub fragment: String,
+  /// The html document that surrounds the fragment, between the `StartHTML` and `EndHTML` offsets (if they are present).
+  pub context: Option<String>,
+  /// The url of the page that the html was copied from, from the `SourceURL` header (if it is present).
+  pub source_url: Option<String>,
+}
+
+/// Parses an item in the `HTML Format` (`CF_HTML`), such as the one returned by [`get_format_data`](crate::ClipboardContext::get_format_data) for the `HTML Format` format.
+///
+/// The offsets in the header are interpreted as byte offsets from the start of the input, as required by the format. The input does not need to be valid UTF-8: only the fragment and the context are decoded, and their invalid characters (including the ones cut by an offset that does not fall on a character boundary) are replaced.
+///
+/// Returns `None` if the fragment offsets are missing, or if they are out of bounds.
+#[must_use]
+pub fn parse_cf_html(cf_html: impl AsRef<[u8]>) -> Option<CfHtml> {
+  let cf_html = cf_html.as_ref();
+  let header = Header::parse(cf_html);
+
+  Some(CfHtml {
+    fragment: decode(cf_html, header.start_fragment?, header.end_fragment?)?,
+    context: header
+      .start_html
+      .zip(header.end_html)
+      .and_then(|(start, end)| decode(cf_html, start, end)),
+    source_url: header.source_url,
+  })
+}
+
+// Returns the fragment and the source url, without decoding the rest of the item
+pub(crate) fn parse_cf_html_fragment(cf_html: &[u8]) -> Option<(String, Option<String>)> {
+  let header = Header::parse(cf_html);
+
+  let fragment = decode(cf_html, header.start_fragment?, header.end_fragment?)?;
+
+  Some((fragment, header.source_url))
+}
+
+#[derive(Default)]
+struct Header {
+  start_html: Option<usize>,
+  end_html: Option<usize>,
+  start_fragment: Option<usize>,
+  end_fragment: Option<usize>,
+  source_url: Option<String>,
+}
+
+impl Header {
+  fn parse(cf_html: &[u8]) -> Self {
+    let mut header = Self::default();
+
+    // The header ends where the html starts
+    for line in cf_html
+      .split(|&byte| byte == b'\n')
+      .take_while(|line| !line.starts_with(b"<"))
+    {
+      let Some(separator) = line.iter().position(|&byte| byte == b':') else {
+        continue;
+      };
+
+      let (key, value) = (&line[..separator], line[separator + 1..].trim_ascii());
+
+      match key {
+        b"StartHTML" => header.start_html = parse_offset(value),
+        b"EndHTML" => header.end_html = parse_offset(value),
+        b"StartFragment" => header.start_fragment = parse_offset(value),
+        b"EndFragment" => header.end_fragment = parse_offset(value),
+        b"SourceURL" => {
+          header.source_url =
+            Some(String::from_utf8_lossy(value).into_owned()).filter(|url| !url.is_empty());
+        }
+        _ => {}
+      }
+    }
+
+    header
+  }
+}
+
+fn decode(cf_html: &[u8], start: usize, end: usize) -> Option<String> {
+  cf_html
+    .get(start..end)
+    .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+}
+
+// Negative offsets (such as -1) mean that the part is not present
+fn parse_offset(value: &[u8]) -> Option<usize> {
+  std::str::from_utf8(value).ok()?.parse().ok()
+}
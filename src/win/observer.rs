@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::{cell::Cell, time::Instant};
 
 use clipboard_win::{
   Clipboard, EnumFormats, Getter, Monitor,
@@ -6,19 +6,49 @@ use clipboard_win::{
   raw::format_name_big,
 };
 use image::DynamicImage;
+use windows_sys::Win32::{
+  Foundation::CloseHandle,
+  System::Threading::{
+    OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    QueryFullProcessImageNameW,
+  },
+  UI::WindowsAndMessaging::GetWindowThreadProcessId,
+};
 
 use crate::*;
 
-pub(crate) struct WinObserver<G: Gatekeeper = DefaultGatekeeper> {
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct WinObserver {
   stop: Arc<AtomicBool>,
   monitor: Monitor,
   html_format: Html,
   png_format: u32,
   custom_formats: Formats,
+  // The custom formats that are decoded as text
+  text_custom_formats: Vec<Arc<str>>,
+  format_validators: FormatValidators,
+  format_prefixes: FormatPrefixes,
   formats_cache: HashMap<u32, Arc<str>>,
-  interval: Duration,
-  max_size: Option<u32>,
-  gatekeeper: G,
+  interval: PollInterval,
+  size_limits: SizeLimits,
+  max_file_count: Option<usize>,
+  truncate_file_lists: bool,
+  directory_handling: DirectoryHandling,
+  include_text_with_html: bool,
+  coalesce_text_with_files: bool,
+  suppress_no_matching_format: bool,
+  load_image_from_single_file: bool,
+  image_output: ImageOutput,
+  image_path_mode: ImagePathMode,
+  keep_source_bytes: bool,
+  deliver_undecoded_on_error: bool,
+  collect_custom_formats: bool,
+  image_mode: ImageMode,
+  delayed_rendering: DelayedRendering,
+  excluded_sources: Vec<String>,
+  gatekeeper: Arc<SharedGatekeeper>,
+  // The metadata for the event currently being extracted
+  metadata: EventMetadata,
 }
 
 impl ClipboardContext<'_> {
@@ -27,7 +57,101 @@ impl ClipboardContext<'_> {
   #[must_use]
   #[inline]
   pub fn get_data(&self, format: &Format) -> Option<Vec<u8>> {
-    clipboard_win::get(clipboard_win::formats::RawData(format.id)).ok()
+    match self.source {
+      ContextSource::Clipboard(_) => {
+        clipboard_win::get(clipboard_win::formats::RawData(format.id)).ok()
+      }
+      #[cfg(feature = "testing")]
+      ContextSource::Mock(mock) => mock.data(format),
+    }
+  }
+
+  // Reads the data of the format, unless it exceeds the size limit
+  pub(crate) fn read_bounded(&self, format: &Format) -> Option<Vec<u8>> {
+    match self.source {
+      ContextSource::Clipboard(_) => read_format_data(format.id, self.max_size).ok().flatten(),
+      #[cfg(feature = "testing")]
+      ContextSource::Mock(mock) => mock.bounded_data(format, self.max_size),
+    }
+  }
+}
+
+// The delay before extracting the content again, when the owner of a format failed to render it
+const DELAYED_RENDERING_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// Applies the delayed rendering handling to the reads of the formats in a single extraction
+struct RenderPolicy {
+  handling: DelayedRendering,
+  // Set when a format failed to render with the `Retry` handling,
+  // so that the extraction is repeated after closing the clipboard
+  retry: Cell<bool>,
+}
+
+impl RenderPolicy {
+  const fn new(handling: DelayedRendering) -> Self {
+    Self {
+      handling,
+      retry: Cell::new(false),
+    }
+  }
+
+  // A failed read of an advertised format means that its owner could not render it
+  fn apply<T>(
+    &self,
+    format_id: u32,
+    result: Result<Option<T>, ErrorWrapper>,
+  ) -> Result<Option<T>, ErrorWrapper> {
+    match result {
+      Err(ErrorWrapper::ReadError(e)) => match self.handling {
+        DelayedRendering::Error => Err(ErrorWrapper::ReadError(e)),
+        DelayedRendering::Retry => {
+          debug!("Failed to render the format {format_id}. Retrying after a delay: {e}");
+          self.retry.set(true);
+
+          Err(ErrorWrapper::ReadError(e))
+        }
+        DelayedRendering::Skip => {
+          debug!("Failed to render the format {format_id}. Skipping it: {e}");
+          Ok(None)
+        }
+      },
+      result => result,
+    }
+  }
+}
+
+// Reads the data of a format that is on the clipboard, while checking for the max size
+fn read_format_data(
+  format_id: u32,
+  max_bytes: Option<u64>,
+) -> Result<Option<Vec<u8>>, ErrorWrapper> {
+  if let Some(max) = max_bytes {
+    match clipboard_win::size(format_id) {
+      Some(size) => {
+        if max < size.get() as u64 {
+          debug!(
+            "Found content with {} size, beyond maximum allowed size. Skipping it...",
+            HumanBytes(size.get())
+          );
+          // Invalid size, we use an error to exit early later on
+          return Err(ErrorWrapper::SizeTooLarge);
+        }
+      }
+
+      // Should be impossible given that the format
+      // is already in the list, but we should trigger
+      // an early exit regardless, as something went wrong
+      None => return Err(ErrorWrapper::EmptyContent),
+    };
+  }
+
+  let data =
+    clipboard_win::get(formats::RawData(format_id)).map_err(ClipboardError::read_from)?;
+
+  if data.is_empty() {
+    Err(ErrorWrapper::EmptyContent)
+  } else {
+    Ok(Some(data))
   }
 }
 
@@ -37,77 +161,65 @@ impl Formats {
   fn extract_clipboard_format(
     &self,
     format_id: u32,
-    max_bytes: Option<u32>,
+    max_bytes: Option<u64>,
+    policy: &RenderPolicy,
   ) -> Result<Option<Vec<u8>>, ErrorWrapper> {
-    if self.contains_id(format_id) {
-      if let Some(max) = max_bytes {
-        match clipboard_win::size(format_id) {
-          Some(size) => {
-            if (max as usize) < size.get() {
-              debug!(
-                "Found content with {} size, beyond maximum allowed size. Skipping it...",
-                HumanBytes(size.get())
-              );
-              // Invalid size, we use an error to exit early later on
-              return Err(ErrorWrapper::SizeTooLarge);
-            }
-          }
-
-          // Should be impossible given that the format
-          // is already in the list, but we should trigger
-          // an early exit regardless, as something went wrong
-          None => return Err(ErrorWrapper::EmptyContent),
-        };
-      }
-
-      let data = clipboard_win::get(formats::RawData(format_id))
-        .map_err(|e| ClipboardError::ReadError(e.to_string()))?;
-
-      if data.is_empty() {
-        Err(ErrorWrapper::EmptyContent)
-      } else {
-        Ok(Some(data))
-      }
-    } else {
+    if !self.contains_id(format_id) {
       // Format was not available at all
-      Ok(None)
+      return Ok(None);
     }
+
+    policy.apply(format_id, read_format_data(format_id, max_bytes))
   }
 
-  fn extract_raw_image(&self, max_size: Option<u32>) -> Result<Option<DynamicImage>, ErrorWrapper> {
-    let image_bytes =
-      if let Some(bytes) = self.extract_clipboard_format(formats::CF_DIBV5, max_size)? {
-        bytes
-      } else if let Some(bytes) = self.extract_clipboard_format(formats::CF_DIB, max_size)? {
-        bytes
-      } else {
-        return Ok(None);
-      };
+  // Reads a format with one of the readers of clipboard_win, if it is on the clipboard
+  fn read_with<T: Default>(
+    &self,
+    format_id: u32,
+    reader: &impl Getter<T>,
+    policy: &RenderPolicy,
+  ) -> Result<Option<T>, ErrorWrapper> {
+    if !self.contains_id(format_id) {
+      return Ok(None);
+    }
 
-    let image = load_dib(&image_bytes)?;
-    Ok(Some(image))
+    let mut out = T::default();
+
+    let result = reader
+      .read_clipboard(&mut out)
+      .map(|_| Some(out))
+      .map_err(|e| ClipboardError::read_from(e).into());
+
+    policy.apply(format_id, result)
   }
 
-  fn extract_files_list(&self) -> Result<Option<Vec<PathBuf>>, ErrorWrapper> {
-    if self.contains_id(formats::FileList.into()) {
-      let mut files_list: Vec<PathBuf> = Vec::new();
-      if let Ok(_num_files) = formats::FileList.read_clipboard(&mut files_list) {
-        if files_list.is_empty() {
-          Err(ErrorWrapper::EmptyContent)
-        } else {
-          Ok(Some(files_list))
-        }
-      } else {
-        // Can only happen if the clipboard changed in the meantime
-        Ok(None)
+  // Returns the undecoded bytes of the bitmap, preferring the V5 header, along with the name of the format that was read
+  fn extract_dib(
+    &self,
+    max_size: Option<u64>,
+    policy: &RenderPolicy,
+  ) -> Result<Option<(&'static str, Vec<u8>)>, ErrorWrapper> {
+    for format_id in [formats::CF_DIBV5, formats::CF_DIB] {
+      if let Some(bytes) = self.extract_clipboard_format(format_id, max_size, policy)? {
+        return Ok(Some((dib_format_name(format_id), bytes)));
       }
-    } else {
-      Ok(None)
+    }
+
+    Ok(None)
+  }
+
+  fn extract_files_list(
+    &self,
+    policy: &RenderPolicy,
+  ) -> Result<Option<Vec<PathBuf>>, ErrorWrapper> {
+    match self.read_with::<Vec<PathBuf>>(formats::FileList.into(), &formats::FileList, policy)? {
+      Some(files_list) if files_list.is_empty() => Err(ErrorWrapper::EmptyContent),
+      files_list => Ok(files_list),
     }
   }
 }
 
-impl<G: Gatekeeper> Observer for WinObserver<G> {
+impl Observer for WinObserver {
   fn observe(&mut self, body_senders: Arc<BodySenders>) {
     info!("Started monitoring the clipboard");
 
@@ -125,10 +237,27 @@ impl<G: Gatekeeper> Observer for WinObserver<G> {
           // Necessary on windows since it has random double-fire events sometimes
           if time_since_last > Duration::from_millis(50) {
             last_read = now;
+            self.interval.record_change();
+            body_senders.tick_all();
+
+            let (source_pid, source) = clipboard_owner();
+
+            if is_excluded_source(&self.excluded_sources, source.as_deref()) {
+              debug!(
+                "Skipping content from excluded source `{}`",
+                source.unwrap_or_default()
+              );
+
+              continue;
+            }
 
             match self.poll_clipboard() {
               Ok(Some(body)) => {
-                body_senders.send_all(&Ok(Arc::new(body)));
+                let mut metadata = std::mem::take(&mut self.metadata);
+                metadata.source = source;
+                metadata.source_pid = source_pid;
+
+                body_senders.send_all(&Ok(ClipboardEvent::new(body, metadata)));
               }
               Err(e) => {
                 warn!("{e}");
@@ -143,11 +272,15 @@ impl<G: Gatekeeper> Observer for WinObserver<G> {
           }
         }
         Ok(false) => {
+          if body_senders.take_refresh_request() {
+            self.refresh(&body_senders);
+          }
+
           // No event, waiting
-          std::thread::sleep(self.interval);
+          self.interval.sleep();
         }
         Err(e) => {
-          let error = ClipboardError::MonitorFailed(e.to_string());
+          let error = ClipboardError::monitor_from(e);
 
           error!("{error}");
 
@@ -161,16 +294,39 @@ impl<G: Gatekeeper> Observer for WinObserver<G> {
   }
 }
 
-impl<G: Gatekeeper> WinObserver<G> {
+impl WinObserver {
+  // Extracts the content of the clipboard on demand, and delivers it if it changed
+  fn refresh(&mut self, body_senders: &BodySenders) {
+    let (source_pid, source) = clipboard_owner();
+
+    if is_excluded_source(&self.excluded_sources, source.as_deref()) {
+      return;
+    }
+
+    match self.poll_clipboard() {
+      Ok(Some(body)) => {
+        let mut metadata = std::mem::take(&mut self.metadata);
+        metadata.source = source;
+        metadata.source_pid = source_pid;
+
+        body_senders.send_if_changed(ClipboardEvent::new(body, metadata));
+      }
+      Ok(None) => {}
+      Err(e) => {
+        warn!("{e}");
+
+        body_senders.send_all(&Err(e));
+      }
+    }
+  }
+
   #[inline(never)]
   #[cold]
   pub(crate) fn new(
     stop: Arc<AtomicBool>,
     monitor: Monitor,
-    custom_format_names: Vec<Arc<str>>,
-    interval: Option<Duration>,
-    max_bytes: Option<u32>,
-    gatekeeper: G,
+    options: ObserverOptions,
+    gatekeeper: Arc<SharedGatekeeper>,
   ) -> Result<Self, String> {
     let html_format = Html::new().ok_or("Failed to create html format identifier".to_string())?;
 
@@ -180,7 +336,7 @@ impl<G: Gatekeeper> WinObserver<G> {
     let mut custom_formats = Formats::default();
     let mut formats_cache: HashMap<u32, Arc<str>> = HashMap::new();
 
-    for name in custom_format_names {
+    for name in options.custom_formats {
       if let Some(id) = clipboard_win::register_format(name.as_ref()) {
         formats_cache.insert(id.get(), name.clone());
         custom_formats.data.push(Format { id: id.get(), name });
@@ -195,16 +351,38 @@ impl<G: Gatekeeper> WinObserver<G> {
       html_format,
       png_format: png_format.get(),
       custom_formats,
+      text_custom_formats: options.text_custom_formats,
+      format_validators: options.format_validators,
+      format_prefixes: options.format_prefixes,
       formats_cache,
-      interval: interval.unwrap_or_else(|| Duration::from_millis(200)),
-      max_size: max_bytes,
+      interval: PollInterval::new(options.interval, options.adaptive_interval, options.warmup),
+      size_limits: options.size_limits,
+      max_file_count: options.max_file_count,
+      truncate_file_lists: options.truncate_file_lists,
+      directory_handling: options.directory_handling,
+      include_text_with_html: options.include_text_with_html,
+      coalesce_text_with_files: options.coalesce_text_with_files,
+      suppress_no_matching_format: options.suppress_no_matching_format,
+      load_image_from_single_file: options.load_image_from_single_file,
+      image_output: options.image_output,
+      image_path_mode: options.image_path_mode,
+      keep_source_bytes: options.keep_source_bytes,
+      deliver_undecoded_on_error: options.deliver_undecoded_on_error,
+      collect_custom_formats: options.collect_custom_formats,
+      image_mode: options.image_mode,
+      delayed_rendering: options.delayed_rendering,
+      excluded_sources: options.excluded_sources,
       gatekeeper,
+      metadata: EventMetadata::default(),
     })
   }
 
   // Reads the clipboard and extracts the first matching format, following the priority list
   // Here we return None if we weren't able to read any format
-  fn extract_clipboard_content(&mut self) -> Result<Option<Body>, ErrorWrapper> {
+  fn extract_clipboard_content(
+    &mut self,
+    policy: &RenderPolicy,
+  ) -> Result<Option<Body>, ErrorWrapper> {
     let formats: Formats = EnumFormats::new()
       .filter_map(|id| {
         if let Some(name) = self.formats_cache.get(&id) {
@@ -224,61 +402,214 @@ impl<G: Gatekeeper> WinObserver<G> {
       })
       .collect();
 
-    let ctx = ClipboardContext { formats: &formats };
+    let cache = FormatCache::default();
+
+    let ctx = ClipboardContext {
+      formats: &formats,
+      max_size: self.size_limits.get(BodyKind::Custom),
+      cache: &cache,
+      source: ContextSource::Clipboard(std::marker::PhantomData),
+    };
 
     if !self.gatekeeper.check(ctx) {
       return Err(ErrorWrapper::UserSkipped);
     }
 
-    let max_size = self.max_size;
+    // The file list that the text custom formats are compared with
+    let coalesced_files = if self.coalesce_text_with_files && !self.text_custom_formats.is_empty() {
+      formats.extract_files_list(policy).ok().flatten()
+    } else {
+      None
+    };
+
+    let mut custom_bodies = Vec::new();
 
     for format in self.custom_formats.iter() {
-      if let Some(bytes) = formats.extract_clipboard_format(format.id, max_size)? {
-        return Ok(Some(Body::new_custom(format.name.clone(), bytes)));
+      let prefix_len = self.format_prefixes.get(&format.name);
+
+      // The prefixes are truncated instead of being checked against the size limit
+      if let Some(mut bytes) = formats.extract_clipboard_format(
+        format.id,
+        if prefix_len.is_some() {
+          None
+        } else {
+          self.size_limits.get(BodyKind::Custom)
+        },
+        policy,
+      )? {
+        let mut truncated = false;
+
+        if let Some(max_bytes) = prefix_len {
+          (bytes, truncated) = truncate_prefix(bytes, max_bytes);
+        }
+
+        self.format_validators.validate(&format.name, &bytes)?;
+
+        let as_text = self.text_custom_formats.contains(&format.name);
+        let body = Body::new_custom(format.name.clone(), bytes, as_text);
+
+        if coalesced_files
+          .as_deref()
+          .is_some_and(|files| body.is_text_of_files(files))
+        {
+          trace!("The text describes the file list. Skipping it...");
+          continue;
+        }
+
+        if truncated {
+          self.metadata.truncated_formats.push(format.name.to_string());
+        }
+
+        if !self.collect_custom_formats {
+          return Ok(Some(body));
+        }
+
+        custom_bodies.push(body);
       }
     }
 
-    if let Some(png_bytes) = formats.extract_clipboard_format(self.png_format, max_size)? {
+    if let Some(body) = Body::from_representations(custom_bodies) {
+      return Ok(Some(body));
+    }
+
+    // The images that come with the path of their file can be emitted as a file list instead
+    let prefer_path = self.image_path_mode == ImagePathMode::PreferPath
+      && formats
+        .extract_files_list(policy)?
+        .is_some_and(|list| list.len() == 1);
+
+    if !prefer_path
+      && let Some(png_bytes) = formats
+        .extract_clipboard_format(
+          self.png_format,
+          self.size_limits.get(BodyKind::PngImage),
+          policy,
+        )?
+    {
       // Extract the image path if we have a list of files with a single item
       let image_path = formats
-        .extract_files_list()?
+        .extract_files_list(policy)?
         .filter(|list| list.len() == 1)
         .map(|mut files| files.remove(0));
 
       Ok(Some(Body::new_png(png_bytes, image_path)))
-    } else if let Some(image) = formats.extract_raw_image(max_size)? {
+    } else if !prefer_path
+      && let Some((dib_format, dib_bytes)) = formats.extract_dib(
+        self.size_limits.get(match self.image_mode {
+          ImageMode::Decoded => BodyKind::RawImage,
+          ImageMode::Original => BodyKind::EncodedImage,
+        }),
+        policy,
+      )?
+    {
       // Extract the image path if we have a list of files with a single item
       let image_path = formats
-        .extract_files_list()?
+        .extract_files_list(policy)?
         .filter(|list| list.len() == 1)
         .map(|mut files| files.remove(0));
 
-      Ok(Some(Body::new_image(image, image_path)))
-    } else if let Some(files_list) = formats.extract_files_list()? {
-      Ok(Some(Body::new_file_list(files_list)))
-    } else {
-      let mut text = String::new();
+      match self.image_mode {
+        ImageMode::Decoded => {
+          let mut image = match load_dib(&dib_bytes) {
+            Ok(image) => image,
+            Err(e) if self.deliver_undecoded_on_error => {
+              return Ok(Some(Body::new_undecoded(dib_format, dib_bytes, &e)));
+            }
+            Err(e) => return Err(e.into()),
+          };
+          image.path = image_path;
+          let source_bytes = self.keep_source_bytes.then(|| dib_bytes.into());
 
-      if self.html_format.read_clipboard(&mut text).is_ok() && content_is_not_empty(&text)? {
-        Ok(Some(Body::new_html(text)))
-      } else if let Ok(_num_bytes) = formats::Unicode.read_clipboard(&mut text)
-        && content_is_not_empty(&text)?
+          Ok(Some(Body::new_image(image, source_bytes)))
+        }
+        ImageMode::Original => Ok(Some(Body::new_encoded_image(
+          EncodedImageFormat::Dib,
+          dib_bytes,
+          image_path,
+        ))),
+      }
+    } else if let Some(files_list) = formats.extract_files_list(policy)? {
+      let (files_list, truncated) = limit_file_count(
+        handle_directories(files_list, self.directory_handling)?,
+        self.max_file_count,
+        self.truncate_file_lists,
+      )?;
+
+      if self.load_image_from_single_file
+        && !truncated
+        && let [path] = files_list.as_slice()
+        && let Some(image) = load_image_file(path, self.size_limits.get(BodyKind::RawImage))?
       {
-        Ok(Some(Body::new_text(text)))
-      } else {
-        Ok(None)
+        return Ok(Some(image));
       }
+
+      self.metadata.truncated = truncated;
+
+      Ok(Some(Body::new_file_list(files_list)))
+    } else if let Some((text, source_url)) = formats
+      .extract_clipboard_format(self.html_format.code(), None, policy)?
+      .and_then(|cf_html| win::parse_cf_html_fragment(&cf_html))
+      && content_is_not_empty(&text)?
+    {
+      // A companion that cannot be read does not prevent the html from being emitted
+      let plain_text = if self.include_text_with_html {
+        formats
+          .read_with::<String>(formats::CF_UNICODETEXT, &formats::Unicode, policy)
+          .ok()
+          .flatten()
+          .filter(|text| !text.is_empty())
+      } else {
+        None
+      };
+
+      self.metadata.source_url = source_url;
+
+      Ok(Some(Body::new_html_with_text(text, plain_text)))
+    } else if let Some(text) =
+      formats.read_with::<String>(formats::CF_UNICODETEXT, &formats::Unicode, policy)?
+      && content_is_not_empty(&text)?
+    {
+      Ok(Some(Body::new_text(text)))
+    } else {
+      Ok(None)
     }
   }
 
-  // Opens the clipboard and calls the extractor, then handles the result
-  fn poll_clipboard(&mut self) -> Result<Option<Body>, ClipboardError> {
+  // Opens the clipboard and extracts its content, closing it before returning
+  fn extract_from_clipboard(
+    &mut self,
+    policy: &RenderPolicy,
+  ) -> Result<Option<Body>, ErrorWrapper> {
     let _clipboard =
-      Clipboard::new_attempts(10).map_err(|e| ClipboardError::ReadError(e.to_string()))?;
+      Clipboard::new_attempts(10).map_err(ClipboardError::read_from)?;
+
+    self.metadata = EventMetadata::default();
 
-    match self.extract_clipboard_content() {
+    self.extract_clipboard_content(policy)
+  }
+
+  // Extracts the content of the clipboard, then handles the result
+  fn poll_clipboard(&mut self) -> Result<Option<Body>, ClipboardError> {
+    let policy = RenderPolicy::new(self.delayed_rendering);
+
+    let mut result = self.extract_from_clipboard(&policy);
+
+    // The clipboard is closed during the delay, since its owner may need it to render the format
+    if policy.retry.get() {
+      if !sleep_unless_stopped(&self.stop, DELAYED_RENDERING_RETRY_DELAY) {
+        return Ok(None);
+      }
+
+      // The formats that fail to render once more are reported as errors
+      result = self.extract_from_clipboard(&RenderPolicy::new(DelayedRendering::Error));
+    }
+
+    match result {
       // Found content
-      Ok(Some(content)) => Ok(Some(content)),
+      Ok(Some(content)) if self.size_limits.is_below_min(&content) => Ok(None),
+      Ok(Some(content)) => content
+        .into_image_output(self.image_output, self.keep_source_bytes)
+        .map(Some),
 
       // Non-fatal errors, we just return None
       Err(ErrorWrapper::EmptyContent) => {
@@ -292,11 +623,164 @@ impl<G: Gatekeeper> WinObserver<G> {
       Err(ErrorWrapper::ReadError(e)) => Err(e),
 
       // There was content but we could not read it
-      Ok(None) => Err(ClipboardError::NoMatchingFormat),
+      Ok(None) => {
+        if self.suppress_no_matching_format {
+          trace!("No supported format was found. Skipping it...");
+          Ok(None)
+        } else {
+          Err(ClipboardError::NoMatchingFormat)
+        }
+      }
     }
   }
 }
 
+// Opens the clipboard and reads its text
+pub(crate) fn read_text() -> Result<Option<String>, ClipboardError> {
+  let _clipboard =
+    Clipboard::new_attempts(10).map_err(ClipboardError::read_from)?;
+
+  if !clipboard_win::is_format_avail(formats::CF_UNICODETEXT) {
+    return Ok(None);
+  }
+
+  let mut text = String::new();
+
+  formats::Unicode
+    .read_clipboard(&mut text)
+    .map_err(ClipboardError::read_from)?;
+
+  Ok(Some(text).filter(|text| !text.is_empty()))
+}
+
+// Reads the image in the clipboard, preferring png over the bitmap formats
+pub(crate) fn read_image() -> Result<Option<Body>, ClipboardError> {
+  let _clipboard =
+    Clipboard::new_attempts(10).map_err(ClipboardError::read_from)?;
+
+  let read_format = |format: u32| -> Result<Option<Vec<u8>>, ClipboardError> {
+    if !clipboard_win::is_format_avail(format) {
+      return Ok(None);
+    }
+
+    let bytes = clipboard_win::get(formats::RawData(format))
+      .map_err(ClipboardError::read_from)?;
+
+    Ok(Some(bytes).filter(|bytes| !bytes.is_empty()))
+  };
+
+  if let Some(png_format) = clipboard_win::register_format("PNG")
+    && let Some(bytes) = read_format(png_format.get())?
+  {
+    return Ok(Some(Body::PngImage { bytes, path: None }));
+  }
+
+  let dib_bytes = match read_format(formats::CF_DIBV5)? {
+    Some(bytes) => Some(bytes),
+    None => read_format(formats::CF_DIB)?,
+  };
+
+  dib_bytes
+    .map(|bytes| Ok(Body::RawImage(load_dib(&bytes)?)))
+    .transpose()
+}
+
+// Opens the clipboard once and reads the data of the given custom formats that are in it
+pub(crate) fn read_custom_formats(
+  names: &[Arc<str>],
+  max_size: Option<u64>,
+) -> Result<Vec<CustomFormatData>, ClipboardError> {
+  let _clipboard =
+    Clipboard::new_attempts(10).map_err(ClipboardError::read_from)?;
+
+  let mut data = Vec::new();
+
+  for name in names {
+    let Some(id) = clipboard_win::register_format(name.as_ref()) else {
+      return Err(ClipboardError::read(format!(
+        "Failed to register custom format `{name}`"
+      )));
+    };
+
+    if !clipboard_win::is_format_avail(id.get()) {
+      continue;
+    }
+
+    let available = Formats {
+      data: vec![Format {
+        id: id.get(),
+        name: name.clone(),
+      }],
+    };
+
+    // The formats that are empty or too large are skipped
+    if let Some(bytes) = available
+      .extract_clipboard_format(
+        id.get(),
+        max_size,
+        &RenderPolicy::new(DelayedRendering::Error),
+      )
+      .or_else(ErrorWrapper::into_read_result)?
+    {
+      data.push((name.clone(), bytes));
+    }
+  }
+
+  Ok(data)
+}
+
+// Detects the process of the window that owns the clipboard, and returns its id
+// along with the file name of its executable.
+// The owner is null when the content was set without a window, or by a window
+// that was destroyed after offering its content with delayed rendering.
+fn clipboard_owner() -> (Option<u32>, Option<String>) {
+  let Some(owner) = clipboard_win::raw::get_owner() else {
+    return (None, None);
+  };
+
+  let mut pid = 0;
+
+  // Returns 0 (and leaves the pid unset) if the window was destroyed in the meantime
+  unsafe { GetWindowThreadProcessId(owner.as_ptr(), &mut pid) };
+
+  if pid == 0 {
+    return (None, None);
+  }
+
+  (Some(pid), process_name(pid))
+}
+
+// Returns the file name of the executable of a process.
+// This fails for some protected system processes, in which case only the pid is reported.
+fn process_name(pid: u32) -> Option<String> {
+  const MAX_PATH_LEN: u32 = 1024;
+
+  let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+
+  if process.is_null() {
+    return None;
+  }
+
+  let mut buffer = [0u16; MAX_PATH_LEN as usize];
+  let mut len = MAX_PATH_LEN;
+
+  let success = unsafe {
+    QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len)
+  };
+
+  unsafe { CloseHandle(process) };
+
+  if success == 0 {
+    return None;
+  }
+
+  let path = PathBuf::from(String::from_utf16_lossy(&buffer[..len as usize]));
+
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+}
+
 // We use a result rather than a simple boolean to trigger early exits and reduce verbosity
 const fn content_is_not_empty(content: &str) -> Result<bool, ErrorWrapper> {
   if content.is_empty() {
@@ -306,16 +790,66 @@ const fn content_is_not_empty(content: &str) -> Result<bool, ErrorWrapper> {
   }
 }
 
-fn load_dib(bytes: &[u8]) -> Result<DynamicImage, ClipboardError> {
+// Returns the name of one of the bitmap formats, which is used to label their undecoded bytes
+const fn dib_format_name(format_id: u32) -> &'static str {
+  match format_id {
+    formats::CF_DIBV5 => "CF_DIBV5",
+    _ => "CF_DIB",
+  }
+}
+
+// The size of the BITMAPV5HEADER struct
+const DIBV5_HEADER_SIZE: u32 = 124;
+
+// The offset of the `bV5AlphaMask` field in the BITMAPV5HEADER struct
+const DIBV5_ALPHA_MASK_OFFSET: usize = 52;
+
+// Decodes a DIB image, keeping the alpha channel for the DIBV5 images that declare one.
+//
+// The alpha channel is declared by the alpha mask of the DIBV5 header, and it is straight
+// (non-premultiplied) alpha, as defined by the format. The header has no field to declare
+// premultiplied alpha, so the pixels are kept as they are (consumers that know that a source
+// writes premultiplied alpha can convert it with `RawImage::unmultiply_alpha`).
+// Images where every alpha is 0 are treated as opaque, since this is how the applications
+// that do not use the alpha channel write it.
+pub(crate) fn load_dib(bytes: &[u8]) -> Result<RawImage, ClipboardError> {
   use std::io::Cursor;
 
-  use image::{DynamicImage, codecs::bmp::BmpDecoder};
+  use image::codecs::bmp::BmpDecoder;
 
   let cursor = Cursor::new(bytes);
 
   let decoder = BmpDecoder::new_without_file_header(cursor)
-    .map_err(|e| ClipboardError::ReadError(format!("Failed to load DIB image: {e}")))?;
+    .map_err(|e| ClipboardError::read_with_context("Failed to load DIB image", e))?;
+
+  let image = DynamicImage::from_decoder(decoder)
+    .map_err(|e| ClipboardError::read_with_context("Failed to load DIB image", e))?;
+
+  let read_u32 = |offset: usize| {
+    bytes
+      .get(offset..offset + 4)
+      .map_or(0, |field| u32::from_le_bytes([field[0], field[1], field[2], field[3]]))
+  };
+
+  // Only the V5 header defines the alpha channel
+  let declares_alpha =
+    read_u32(0) >= DIBV5_HEADER_SIZE && read_u32(DIBV5_ALPHA_MASK_OFFSET) != 0;
+
+  if !declares_alpha || !image.color().has_alpha() {
+    let mut image = RawImage::from_dynamic_image(image, None);
+    image.had_alpha = false;
+
+    return Ok(image);
+  }
+
+  let rgba = image.into_rgba8();
+
+  if rgba.pixels().all(|pixel| pixel[3] == 0) {
+    let mut image = RawImage::from_dynamic_image(DynamicImage::ImageRgba8(rgba), None);
+    image.had_alpha = false;
+
+    return Ok(image);
+  }
 
-  DynamicImage::from_decoder(decoder)
-    .map_err(|e| ClipboardError::ReadError(format!("Failed to load DIB image: {e}")))
+  Ok(RawImage::from(rgba))
 }
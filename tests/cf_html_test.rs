@@ -0,0 +1,128 @@
+#![cfg(windows)]
+
+use clipboard_watcher::win::{CfHtml, parse_cf_html};
+
+// As written by Chromium, with a `SourceURL` and line breaks around the fragment
+const CHROMIUM_SAMPLE: &str = concat!(
+  "Version:0.9\r\n",
+  "StartHTML:0000000137\r\n",
+  "EndHTML:0000000219\r\n",
+  "StartFragment:0000000171\r\n",
+  "EndFragment:0000000183\r\n",
+  "SourceURL:https://example.com/\r\n",
+  "<html><body>\r\n",
+  "<!--StartFragment--><b>Hello</b><!--EndFragment-->\r\n",
+  "</body>\r\n",
+  "</html>"
+);
+
+// Contains multi-byte characters, so that the offsets differ from the character indexes
+const UNICODE_SAMPLE: &str = concat!(
+  "Version:0.9\r\n",
+  "StartHTML:0000000105\r\n",
+  "EndHTML:0000000191\r\n",
+  "StartFragment:0000000137\r\n",
+  "EndFragment:0000000159\r\n",
+  "<html><body><!--StartFragment--><p>Grüße, 世界</p><!--EndFragment--></body></html>"
+);
+
+#[test]
+fn chromium_sample() {
+  assert_eq!(
+    parse_cf_html(CHROMIUM_SAMPLE),
+    Some(CfHtml {
+      fragment: "<b>Hello</b>".to_string(),
+      context: Some(
+        "<html><body>\r\n<!--StartFragment--><b>Hello</b><!--EndFragment-->\r\n</body>\r\n</html>"
+          .to_string()
+      ),
+      source_url: Some("https://example.com/".to_string()),
+    })
+  );
+}
+
+#[test]
+fn byte_offsets() {
+  let cf_html = parse_cf_html(UNICODE_SAMPLE).unwrap();
+
+  assert_eq!(cf_html.fragment, "<p>Grüße, 世界</p>");
+  assert_eq!(
+    cf_html.context.as_deref(),
+    Some("<html><body><!--StartFragment--><p>Grüße, 世界</p><!--EndFragment--></body></html>")
+  );
+  assert_eq!(cf_html.source_url, None);
+}
+
+#[test]
+fn invalid_utf8_outside_of_fragment() {
+  // The offsets are byte offsets, so an invalid byte before the fragment must not shift them
+  let mut bytes = CHROMIUM_SAMPLE.as_bytes().to_vec();
+  let body_start = CHROMIUM_SAMPLE.find("<body>").unwrap();
+  bytes[body_start + 1] = 0xFF;
+
+  let cf_html = parse_cf_html(bytes).unwrap();
+
+  assert_eq!(cf_html.fragment, "<b>Hello</b>");
+  assert!(
+    cf_html
+      .context
+      .unwrap()
+      .starts_with("<html><\u{FFFD}ody>")
+  );
+  assert_eq!(cf_html.source_url.as_deref(), Some("https://example.com/"));
+}
+
+#[test]
+fn fragment_offset_mid_string() {
+  // Points inside of the `<b>` element rather than at its start
+  let cf_html =
+    parse_cf_html(CHROMIUM_SAMPLE.replace("StartFragment:0000000171", "StartFragment:0000000174"))
+      .unwrap();
+
+  assert_eq!(cf_html.fragment, "Hello</b>");
+}
+
+#[test]
+fn fragment_offset_mid_character() {
+  // Points at the second byte of `ü`
+  let cf_html =
+    parse_cf_html(UNICODE_SAMPLE.replace("StartFragment:0000000137", "StartFragment:0000000143"))
+      .unwrap();
+
+  assert_eq!(cf_html.fragment, "\u{FFFD}ße, 世界</p>");
+}
+
+#[test]
+fn missing_context() {
+  // Keeps the length of the header, so that the other offsets are still valid
+  let cf_html = parse_cf_html(
+    UNICODE_SAMPLE
+      .replace("StartHTML:0000000105", "StartHTML:-000000001")
+      .replace("EndHTML:0000000191", "EndHTML:-000000001"),
+  )
+  .unwrap();
+
+  assert_eq!(cf_html.fragment, "<p>Grüße, 世界</p>");
+  assert_eq!(cf_html.context, None);
+}
+
+#[test]
+fn invalid_fragment_offsets() {
+  // Out of bounds
+  assert_eq!(
+    parse_cf_html(CHROMIUM_SAMPLE.replace("EndFragment:0000000183", "EndFragment:0000009999")),
+    None
+  );
+
+  // Start after the end
+  assert_eq!(
+    parse_cf_html(CHROMIUM_SAMPLE.replace("StartFragment:0000000171", "StartFragment:0000000190")),
+    None
+  );
+
+  // Missing
+  assert_eq!(
+    parse_cf_html("<html><body><b>Hello</b></body></html>"),
+    None
+  );
+}
@@ -84,6 +84,31 @@ mod win {
     };
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn rejected_stream_win() {
+    let mut event_listener = ClipboardEventListener::builder()
+      .with_gatekeeper(|ctx| !ctx.has_format("ExcludeClipboardContentFromMonitorProcessing"))
+      .spawn()
+      .unwrap();
+
+    let mut rejected = event_listener.rejected_stream(5);
+
+    set_private_clipboard_win(FlagKind::ExcludeClipboard).unwrap();
+
+    let change = tokio::time::timeout(Duration::from_secs(2), rejected.next())
+      .await
+      .expect("The rejected change was not reported")
+      .expect("Stream was closed prematurely");
+
+    assert!(
+      change
+        .formats
+        .iter()
+        .any(|format| format == "ExcludeClipboardContentFromMonitorProcessing")
+    );
+  }
+
   #[allow(clippy::needless_pass_by_value)]
   fn set_private_clipboard_win(flag: FlagKind) -> Result<(), String> {
     let _clip =
@@ -128,6 +153,7 @@ mod win {
 mod linux {
   use super::*;
 
+  use clipboard_watcher::{ClipboardError, FormatId};
   use std::thread;
   use x11rb::connection::Connection;
   use x11rb::protocol::Event;
@@ -169,6 +195,94 @@ mod linux {
     };
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn format_ids_linux() {
+    let _owner_handle = spawn_x11_privacy_owner(FlagKind::CanInclude);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (id_tx, id_rx) = std::sync::mpsc::channel();
+
+    let _event_listener = ClipboardEventListener::builder()
+      .with_gatekeeper(move |ctx| {
+        if let Some(format) = ctx.get_format("CanIncludeInClipboardHistory") {
+          let _ = id_tx.send(format.id());
+        }
+
+        true
+      })
+      .spawn()
+      .unwrap();
+
+    let id = id_rx
+      .recv_timeout(Duration::from_secs(2))
+      .expect("The format was not detected");
+
+    let (conn, _) = RustConnection::connect(None).unwrap();
+    let atom = conn
+      .intern_atom(false, b"CanIncludeInClipboardHistory")
+      .unwrap()
+      .reply()
+      .unwrap()
+      .atom;
+
+    assert_eq!(id, FormatId::X11(atom));
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn lazy_bytes_linux() {
+    use clipboard_watcher::{BodyKind, Limits};
+
+    let _owner_handle = spawn_x11_privacy_owner(FlagKind::CanInclude);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (bytes_tx, bytes_rx) = std::sync::mpsc::channel();
+
+    let _event_listener = ClipboardEventListener::builder()
+      .with_gatekeeper(move |ctx| {
+        let first = ctx.get_bytes("CanIncludeInClipboardHistory");
+        let second = ctx.get_bytes("CanIncludeInClipboardHistory");
+
+        let _ = bytes_tx.send((first, second));
+
+        true
+      })
+      .spawn()
+      .unwrap();
+
+    let (first, second) = bytes_rx
+      .recv_timeout(Duration::from_secs(2))
+      .expect("The gatekeeper was not called");
+
+    let first = first.expect("The format was not read");
+
+    assert_eq!(&*first, 0u32.to_ne_bytes());
+    // The second call returns the cached data
+    assert!(std::sync::Arc::ptr_eq(&first, &second.unwrap()));
+
+    let (bytes_tx, bytes_rx) = std::sync::mpsc::channel();
+
+    let _limited_listener = ClipboardEventListener::builder()
+      .limits(Limits {
+        max_size_overrides: [(BodyKind::Custom, 2)].into(),
+        ..Default::default()
+      })
+      .with_gatekeeper(move |ctx| {
+        let _ = bytes_tx.send(ctx.get_bytes("CanIncludeInClipboardHistory"));
+
+        true
+      })
+      .spawn()
+      .unwrap();
+
+    let bytes = bytes_rx
+      .recv_timeout(Duration::from_secs(2))
+      .expect("The gatekeeper was not called");
+
+    assert!(bytes.is_none(), "The size limit was not applied");
+  }
+
   #[tokio::test]
   #[serial]
   async fn gatekeeper_linux_2() {
@@ -203,6 +317,111 @@ mod linux {
     };
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn set_gatekeeper_linux() {
+    let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+    let mut stream = event_listener.new_stream(5);
+
+    event_listener.set_gatekeeper(|_| false);
+
+    let _owner_handle = spawn_x11_privacy_owner(FlagKind::CanInclude);
+
+    let result = tokio::time::timeout(Duration::from_secs(2), stream.next()).await;
+
+    match result {
+      Ok(Some(_)) => {
+        panic!("The new gatekeeper was not applied");
+      }
+      Ok(None) => {
+        panic!("Stream was closed prematurely");
+      }
+      Err(_) => {}
+    };
+
+    event_listener.set_gatekeeper(|_| true);
+
+    // Claim the clipboard again to trigger a new event
+    let _owner_handle = spawn_x11_privacy_owner(FlagKind::CanInclude);
+
+    let result = tokio::time::timeout(Duration::from_secs(2), stream.next()).await;
+
+    match result {
+      Ok(Some(Ok(_))) => {}
+      Ok(Some(Err(e))) => {
+        panic!("Unexpected error: {e}");
+      }
+      Ok(None) => {
+        panic!("Stream was closed prematurely");
+      }
+      Err(_) => {
+        panic!("The gatekeeper was not replaced");
+      }
+    };
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn rejected_stream_linux() {
+    let mut event_listener = ClipboardEventListener::builder()
+      .with_gatekeeper(|ctx| !ctx.has_format("ExcludeClipboardContentFromMonitorProcessing"))
+      .spawn()
+      .unwrap();
+
+    let mut stream = event_listener.new_stream(5);
+    let mut rejected = event_listener.rejected_stream(5);
+
+    let _owner_handle = spawn_x11_privacy_owner(FlagKind::ExcludeClipboard);
+
+    let change = tokio::time::timeout(Duration::from_secs(2), rejected.next())
+      .await
+      .expect("The rejected change was not reported")
+      .expect("Stream was closed prematurely");
+
+    assert!(
+      change
+        .formats
+        .iter()
+        .any(|format| format == "ExcludeClipboardContentFromMonitorProcessing")
+    );
+
+    // The content itself must still be suppressed
+    let result = tokio::time::timeout(Duration::from_millis(500), stream.next()).await;
+
+    assert!(result.is_err(), "The rejected content was emitted: {result:?}");
+  }
+
+  #[tokio::test]
+  #[serial]
+  async fn panicking_gatekeeper_linux() {
+    let mut event_listener = ClipboardEventListener::builder()
+      .with_gatekeeper(|_| panic!("gatekeeper failure"))
+      .spawn()
+      .unwrap();
+
+    let mut stream = event_listener.new_stream(5);
+
+    let _owner_handle = spawn_x11_privacy_owner(FlagKind::CanInclude);
+
+    let result = tokio::time::timeout(Duration::from_secs(2), stream.next()).await;
+
+    match result {
+      Ok(Some(Err(ClipboardError::MonitorFailed { message: msg, .. }))) => {
+        assert!(msg.contains("gatekeeper failure"));
+      }
+      Ok(other) => {
+        panic!("Expected a MonitorFailed error, got {other:?}");
+      }
+      Err(_) => {
+        panic!("The panic was not reported");
+      }
+    };
+
+    // Must not panic
+    drop(event_listener);
+  }
+
   fn spawn_x11_privacy_owner(flag: FlagKind) -> thread::JoinHandle<()> {
     thread::spawn(move || {
       let (conn, screen_num) = RustConnection::connect(None).unwrap();
@@ -424,6 +643,44 @@ mod macos {
     };
   }
 
+  #[tokio::test]
+  #[serial]
+  async fn nspasteboard_conventions() {
+    let mut event_listener = ClipboardEventListener::builder()
+      .respect_nspasteboard_conventions(true)
+      .spawn()
+      .unwrap();
+
+    let mut stream = event_listener.new_stream(5);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    unsafe {
+      let pb = NSPasteboard::generalPasteboard();
+
+      let type_text = NSPasteboardTypeString;
+      let type_concealed = NSString::from_str("org.nspasteboard.ConcealedType");
+
+      let types = NSArray::from_slice(&[type_text, &*type_concealed]);
+      pb.declareTypes_owner(&types, None);
+
+      pb.setString_forType(&NSString::from_str("hunter2"), type_text);
+      pb.setData_forType(Some(&NSData::new()), &type_concealed);
+    }
+
+    let result = tokio::time::timeout(Duration::from_secs(2), stream.next()).await;
+
+    match result {
+      Ok(Some(_)) => {
+        panic!("org.nspasteboard.ConcealedType was not detected");
+      }
+      Ok(None) => {
+        panic!("Stream was closed prematurely");
+      }
+      Err(_) => {}
+    };
+  }
+
   pub fn set_private_clipboard_mac(flag: FlagKind) {
     unsafe {
       let pb = NSPasteboard::generalPasteboard();
@@ -457,3 +714,68 @@ mod macos {
     }
   }
 }
+
+#[cfg(feature = "testing")]
+mod mock {
+  use clipboard_watcher::{ClipboardContext, Gatekeeper, MockClipboard};
+  use std::collections::HashMap;
+
+  // Rejects the content flagged by the clipboard managers, as well as the text with a marker
+  fn gatekeeper(ctx: ClipboardContext) -> bool {
+    if ctx.get_format_as_u32("CanIncludeInClipboardHistory") == Some(0)
+      || ctx.has_format("ExcludeClipboardContentFromMonitorProcessing")
+    {
+      return false;
+    }
+
+    ctx
+      .get_bytes("text/plain")
+      .is_none_or(|bytes| !bytes.starts_with(b"secret:"))
+  }
+
+  #[test]
+  fn mock_gatekeeper() {
+    let allowed = MockClipboard::new(HashMap::from([(
+      "text/plain".to_string(),
+      b"hello".to_vec(),
+    )]));
+    assert!(gatekeeper.check(ClipboardContext::mock(&allowed)));
+
+    let excluded = MockClipboard::new([
+      ("text/plain", b"hello".to_vec()),
+      ("ExcludeClipboardContentFromMonitorProcessing", Vec::new()),
+    ]);
+    assert!(!gatekeeper.check(ClipboardContext::mock(&excluded)));
+
+    let history = MockClipboard::new([
+      ("text/plain", b"hello".to_vec()),
+      ("CanIncludeInClipboardHistory", 0u32.to_ne_bytes().to_vec()),
+    ]);
+    assert!(!gatekeeper.check(ClipboardContext::mock(&history)));
+
+    let secret = MockClipboard::new([("text/plain", b"secret:hunter2".to_vec())]);
+    assert!(!gatekeeper.check(ClipboardContext::mock(&secret)));
+
+    // The content over the size limit cannot be read with `get_bytes`
+    let secret = secret.max_size(4);
+    assert!(gatekeeper.check(ClipboardContext::mock(&secret)));
+  }
+
+  #[test]
+  fn mock_formats() {
+    let clipboard = MockClipboard::new([
+      ("text/html", b"<b>hi</b>".to_vec()),
+      ("text/plain", b"hi".to_vec()),
+    ]);
+    let ctx = ClipboardContext::mock(&clipboard);
+
+    let names: Vec<&str> = ctx.formats().iter().map(|f| f.name()).collect();
+    assert_eq!(names, ["text/html", "text/plain"]);
+    assert_eq!(ctx.get_format_data("text/plain").unwrap(), b"hi");
+    assert_eq!(
+      ctx.get_bytes("text/html").as_deref(),
+      Some(&b"<b>hi</b>"[..])
+    );
+    assert!(ctx.get_bytes("image/png").is_none());
+  }
+}
@@ -11,7 +11,7 @@ use std::{
   time::Duration,
 };
 
-use clipboard_watcher::{Body, ClipboardEventListener};
+use clipboard_watcher::{Body, ClipboardEventListener, ColorType};
 use futures::StreamExt;
 use image::{ImageFormat, RgbImage};
 use tokio::sync::mpsc;
@@ -596,6 +596,76 @@ async fn tiff() {
   listener_task.abort();
 }
 
+#[cfg(target_os = "macos")]
+#[tokio::test]
+#[serial]
+async fn original_tiff() {
+  use clipboard_watcher::{EncodedImageFormat, ImageMode};
+
+  init_logging();
+
+  let img = RgbImage::new(1, 1);
+
+  let mut tiff_bytes = Vec::new();
+  img
+    .write_to(&mut Cursor::new(&mut tiff_bytes), ImageFormat::Tiff)
+    .expect("Failed to encode dummy TIFF");
+
+  let (signal_tx, mut signal_rx) = mpsc::channel(1);
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .image_mode(ImageMode::Original)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let expected_bytes = tiff_bytes.clone();
+  let listener_task = tokio::spawn(async move {
+    while let Some(result) = stream.next().await {
+      if let Ok(content) = result
+        && let Body::EncodedImage {
+          format: EncodedImageFormat::Tiff,
+          bytes,
+          ..
+        } = content.as_ref()
+      {
+        assert_eq!(&expected_bytes, bytes);
+
+        signal_tx.send(()).await.unwrap();
+      }
+    }
+  });
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let script = format!(
+    "set the clipboard to {{«class TIFF»:«data TIFF{}»}}",
+    hex::encode(&tiff_bytes)
+  );
+
+  let status = Command::new("osascript")
+    .arg("-e")
+    .arg(&script)
+    .status()
+    .expect("Failed to execute osascript for TIFF data.");
+
+  assert!(status.success(), "osascript for TIFF data failed.");
+
+  match tokio::time::timeout(Duration::from_secs(2), signal_rx.recv()).await {
+    Ok(Some(_)) => {}
+    Ok(None) => {
+      panic!("Listening task finished without receiving the correct clipboard content.");
+    }
+    Err(_) => {
+      panic!("Test timed out: Did not receive clipboard update in time.");
+    }
+  }
+
+  // Clean up the spawned task.
+  listener_task.abort();
+}
+
 #[tokio::test]
 #[serial]
 async fn size_limits() {
@@ -832,3 +902,2839 @@ async fn custom_formats() {
   // Clean up the spawned task.
   listener_task.abort();
 }
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn secondary_selection() {
+  use clipboard_watcher::Selection;
+
+  init_logging();
+
+  let (signal_tx, mut signal_rx) = mpsc::channel(1);
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .selection(Selection::Secondary)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let test_string = "one does not simply walk into Mordor";
+
+  let listener_task = tokio::spawn(async move {
+    while let Some(result) = stream.next().await {
+      if let Ok(content) = result
+        && let Body::PlainText(text) = content.as_ref()
+      {
+        assert_eq!(text, test_string);
+
+        signal_tx.send(()).await.unwrap();
+      }
+    }
+  });
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("secondary")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_string.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  match tokio::time::timeout(Duration::from_secs(2), signal_rx.recv()).await {
+    Ok(Some(_)) => {}
+    Ok(None) => {
+      panic!("Listening task finished without receiving the correct clipboard content.");
+    }
+    Err(_) => {
+      panic!("Test timed out: Did not receive clipboard update in time.");
+    }
+  }
+
+  listener_task.abort();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn multiple_selections() {
+  use clipboard_watcher::Selection;
+
+  init_logging();
+
+  let (signal_tx, mut signal_rx) = mpsc::channel(1);
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .selections([Selection::Clipboard, Selection::Primary])
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  let test_string = "a wizard is never late";
+
+  let listener_task = tokio::spawn(async move {
+    while let Some(result) = stream.next().await {
+      if let Ok(event) = result
+        && let Body::PlainText(text) = event.body.as_ref()
+      {
+        assert_eq!(text, test_string);
+        assert_eq!(event.metadata.selection, Some(Selection::Primary));
+
+        signal_tx.send(()).await.unwrap();
+      }
+    }
+  });
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("primary")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_string.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  match tokio::time::timeout(Duration::from_secs(2), signal_rx.recv()).await {
+    Ok(Some(_)) => {}
+    Ok(None) => {
+      panic!("Listening task finished without receiving the correct clipboard content.");
+    }
+    Err(_) => {
+      panic!("Test timed out: Did not receive clipboard update in time.");
+    }
+  }
+
+  listener_task.abort();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn merge_streams() {
+  use clipboard_watcher::Selection;
+
+  init_logging();
+
+  let mut clipboard_listener = ClipboardEventListener::spawn().unwrap();
+  let mut primary_listener = ClipboardEventListener::builder()
+    .selection(Selection::Primary)
+    .spawn()
+    .unwrap();
+
+  let clipboard_stream = clipboard_listener.new_stream(1);
+  let primary_stream = primary_listener.new_stream(1);
+
+  let clipboard_id = clipboard_stream.id().clone();
+  let primary_id = primary_stream.id().clone();
+
+  // The ids are unique across listeners
+  assert_ne!(clipboard_id, primary_id);
+
+  let mut merged = clipboard_watcher::merge(vec![clipboard_stream, primary_stream]);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for (selection, text) in [("primary", "from primary"), ("clipboard", "from clipboard")] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg(selection)
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    let (id, result) = tokio::time::timeout(Duration::from_secs(2), merged.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap();
+
+    let expected_id = if selection == "primary" {
+      &primary_id
+    } else {
+      &clipboard_id
+    };
+
+    assert_eq!(&id, expected_id);
+    assert_eq!(*result.unwrap(), Body::PlainText(text.to_string()));
+  }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn directory_handling() {
+  use clipboard_watcher::DirectoryHandling;
+
+  init_logging();
+
+  let temp_dir = tempfile::tempdir().unwrap();
+  let root = temp_dir.path().canonicalize().unwrap();
+
+  let dir = root.join("folder");
+  std::fs::create_dir_all(dir.join("nested")).unwrap();
+  std::fs::write(dir.join("inner.txt"), "inner").unwrap();
+  std::fs::write(dir.join("nested").join("deep.txt"), "deep").unwrap();
+
+  let file = root.join("standalone.txt");
+  std::fs::write(&file, "standalone").unwrap();
+
+  for (handling, expected) in [
+    (DirectoryHandling::Keep, vec![dir.clone(), file.clone()]),
+    (DirectoryHandling::Skip, vec![file.clone()]),
+    (DirectoryHandling::ExpandShallow, vec![dir.join("inner.txt"), file.clone()]),
+  ] {
+    let mut event_listener = ClipboardEventListener::builder()
+      .directory_handling(handling)
+      .spawn()
+      .unwrap();
+
+    let mut stream = event_listener.new_stream(1);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .arg("-target")
+      .arg("text/uri-list")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let uris = format!("file://{}\r\nfile://{}", dir.display(), file.display());
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(uris.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(*body, Body::FileList(expected), "{handling:?}");
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn copy_unsupported_format() {
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("application/x-unsupported")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"not for you").unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn no_matching_format() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  copy_unsupported_format();
+
+  match tokio::time::timeout(Duration::from_secs(2), stream.next()).await {
+    Ok(Some(Err(clipboard_watcher::ClipboardError::NoMatchingFormat))) => {}
+    Ok(other) => {
+      panic!("Expected a NoMatchingFormat error, got {other:?}");
+    }
+    Err(_) => {
+      panic!("Test timed out: Did not receive clipboard update in time.");
+    }
+  }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn suppress_no_matching_format() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .suppress_no_matching_format(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  copy_unsupported_format();
+
+  if let Ok(item) = tokio::time::timeout(Duration::from_secs(2), stream.next()).await {
+    panic!("Expected no event, got {item:?}");
+  }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn history() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .history(2)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(5);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for text in ["first", "second", "third"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+  }
+
+  let recent: Vec<Body> = event_listener
+    .recent()
+    .iter()
+    .map(|body| body.as_ref().clone())
+    .collect();
+
+  assert_eq!(
+    recent,
+    [
+      Body::PlainText("second".to_string()),
+      Body::PlainText("third".to_string())
+    ]
+  );
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn image_from_single_file() {
+  init_logging();
+
+  let temp_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  let file_path = temp_file
+    .path()
+    .to_path_buf()
+    .canonicalize()
+    .expect("Failed to canonicalize path");
+
+  RgbImage::from_pixel(2, 3, image::Rgb([10, 20, 30]))
+    .save_with_format(&file_path, ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .load_image_from_single_file(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("text/uri-list")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let file_uri = format!("file://{}", file_path.display());
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(file_uri.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let Body::RawImage(image) = body.as_ref() else {
+    panic!("Expected a raw image, got {body:?}");
+  };
+
+  assert_eq!((image.width, image.height), (2, 3));
+  assert_eq!(&image.bytes[..3], &[10, 20, 30]);
+  assert_eq!(image.path.as_deref(), Some(file_path.as_path()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn image_from_single_file_over_limit() {
+  init_logging();
+
+  let temp_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  let file_path = temp_file
+    .path()
+    .to_path_buf()
+    .canonicalize()
+    .expect("Failed to canonicalize path");
+
+  RgbImage::from_pixel(2, 3, image::Rgb([10, 20, 30]))
+    .save_with_format(&file_path, ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .load_image_from_single_file(true)
+    .max_size(16)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("text/uri-list")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let file_uri = format!("file://{}", file_path.display());
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(file_uri.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  // The file is larger than the limit, so the file list is emitted instead of the image
+  assert_eq!(*body, Body::FileList(vec![file_path]));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn empty_selection_name() {
+  let result = ClipboardEventListener::builder()
+    .selection(clipboard_watcher::Selection::Named("".into()))
+    .spawn();
+
+  assert!(result.is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn read_text() {
+  init_logging();
+
+  let test_string = "fly, you fools!";
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_string.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let text = clipboard_watcher::read_text().expect("Failed to read the clipboard");
+
+  assert_eq!(text.as_deref(), Some(test_string));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn read_all_custom() {
+  init_logging();
+
+  const CUSTOM_FORMAT: &str = "application/goldberry";
+  const ABSENT_FORMAT: &str = "application/old-man-willow";
+  let test_data = "hey dol! merry dol!".as_bytes();
+
+  let event_listener = ClipboardEventListener::builder()
+    .with_custom_formats([ABSENT_FORMAT, CUSTOM_FORMAT])
+    .spawn()
+    .unwrap();
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-t")
+    .arg(CUSTOM_FORMAT)
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_data).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let data = event_listener
+    .read_all_custom()
+    .expect("Failed to read the clipboard");
+
+  assert_eq!(data.len(), 1);
+  assert_eq!(data[0].0.as_ref(), CUSTOM_FORMAT);
+  assert_eq!(data[0].1, test_data);
+}
+
+#[cfg(target_os = "macos")]
+#[tokio::test]
+#[serial]
+async fn concatenate_text_items() {
+  use objc2::runtime::ProtocolObject;
+  use objc2_app_kit::{NSPasteboard, NSPasteboardItem, NSPasteboardTypeString};
+  use objc2_foundation::{NSArray, NSString};
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .concatenate_text_items(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  unsafe {
+    let pasteboard = NSPasteboard::generalPasteboard();
+
+    let items = ["speak friend", "and enter"].map(|text| {
+      let item = NSPasteboardItem::new();
+      item.setString_forType(&NSString::from_str(text), NSPasteboardTypeString);
+
+      ProtocolObject::from_retained(item)
+    });
+
+    pasteboard.clearContents();
+    pasteboard.writeObjects(&NSArray::from_retained_slice(&items));
+  }
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let Body::PlainText(text) = body.as_ref() else {
+    panic!("Expected plain text, got {body:?}");
+  };
+
+  assert!(text.contains("speak friend"));
+  assert!(text.contains("and enter"));
+}
+
+#[cfg(target_os = "macos")]
+#[tokio::test]
+#[serial]
+async fn exact_text() {
+  use objc2_app_kit::{NSPasteboard, NSPasteboardTypeString};
+  use objc2_foundation::NSData;
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .exact_text(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  unsafe {
+    let pasteboard = NSPasteboard::generalPasteboard();
+
+    pasteboard.clearContents();
+    pasteboard.setData_forType(
+      Some(&NSData::with_bytes(b"line one\r\nline two \xff")),
+      NSPasteboardTypeString,
+    );
+  }
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  // The bytes are kept as they are, except for the invalid UTF-8
+  assert_eq!(
+    *body,
+    Body::PlainText("line one\r\nline two \u{FFFD}".to_string())
+  );
+}
+
+#[test]
+#[serial]
+fn stream_ids() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let first = event_listener.new_stream(1);
+  let second = event_listener.new_stream(1).with_metadata();
+
+  assert_ne!(first.id(), second.id());
+  assert_eq!(second.id().to_string(), second.id().get().to_string());
+}
+
+#[test]
+#[serial]
+fn stream_ids_across_listeners() {
+  let mut first_listener = ClipboardEventListener::spawn().unwrap();
+  let mut second_listener = ClipboardEventListener::spawn().unwrap();
+
+  let kept = first_listener.new_stream(1);
+  let removed = first_listener.new_stream(1);
+
+  first_listener.unsubscribe(removed.id());
+
+  let ids = [
+    kept.id().clone(),
+    removed.id().clone(),
+    first_listener.new_stream(1).id().clone(),
+    second_listener.new_stream(1).id().clone(),
+    second_listener.new_stream(1).id().clone(),
+  ];
+
+  // The counter is shared and only moves forward, so the ids are never reused
+  // (not even the one of a removed stream) and they never collide across the listeners
+  assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{ids:?}");
+}
+
+#[tokio::test]
+#[serial]
+async fn close_all_streams() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut first = event_listener.new_stream(1);
+  let mut second = event_listener.new_stream(1);
+
+  event_listener.close_all_streams();
+
+  for stream in [&mut first, &mut second] {
+    let result = tokio::time::timeout(Duration::from_secs(1), stream.next()).await;
+
+    assert!(matches!(result, Ok(None)), "The stream was not closed");
+  }
+
+  // New streams are unaffected
+  let mut third = event_listener.new_stream(1);
+
+  let result = tokio::time::timeout(Duration::from_millis(200), third.next()).await;
+
+  assert!(result.is_err(), "The new stream should still be open");
+}
+
+#[tokio::test]
+#[serial]
+async fn unsubscribe() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut first = event_listener.new_stream(1);
+  let mut second = event_listener.new_stream(1);
+
+  event_listener.unsubscribe(first.id());
+
+  let result = tokio::time::timeout(Duration::from_secs(1), first.next()).await;
+
+  assert!(matches!(result, Ok(None)), "The stream was not closed");
+
+  // The other streams are unaffected
+  let result = tokio::time::timeout(Duration::from_millis(200), second.next()).await;
+
+  assert!(result.is_err(), "The other stream should still be open");
+}
+
+#[test]
+#[serial]
+fn poll_event_with_custom_waker() {
+  use std::{
+    sync::{
+      Arc,
+      atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+  };
+
+  // Counts the wakeups, like an event loop that schedules a new iteration
+  struct CountingWaker(AtomicUsize);
+
+  impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+      self.0.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+  let waker = Waker::from(counter.clone());
+  let mut cx = Context::from_waker(&waker);
+
+  assert!(stream.poll_event(&mut cx).is_pending());
+
+  // Closing the stream from the listener wakes the registered waker
+  event_listener.unsubscribe(stream.id());
+
+  assert!(
+    counter.0.load(Ordering::SeqCst) > 0,
+    "The waker was not woken"
+  );
+  assert!(matches!(stream.poll_event(&mut cx), Poll::Ready(None)));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn text_formats() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_text_formats(["text/markdown"])
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let test_markdown = "# Mellon\n\n*speak friend* and enter — ünïcödé";
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("text/markdown")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_markdown.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    body.as_ref(),
+    &Body::Text {
+      mime: "text/markdown".into(),
+      content: test_markdown.to_string()
+    }
+  );
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn size_limits_per_kind() {
+  use clipboard_watcher::BodyKind;
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_custom_formats(["application/x-limited"])
+    .max_size(1_000_000)
+    .max_size_for(BodyKind::Custom, 4)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("application/x-limited")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"beyond the limit").unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  if let Ok(item) = tokio::time::timeout(Duration::from_secs(2), stream.next()).await {
+    panic!("Custom format exceeding its maximum size was not ignored: {item:?}");
+  }
+}
+
+#[cfg(windows)]
+#[tokio::test]
+#[serial]
+async fn dib_original() {
+  use clipboard_watcher::{EncodedImageFormat, ImageMode};
+  use clipboard_win::options::DoClear;
+
+  init_logging();
+
+  let img = RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0]));
+  let mut bmp_bytes = Vec::new();
+  img
+    .write_to(&mut Cursor::new(&mut bmp_bytes), ImageFormat::Bmp)
+    .expect("Failed to encode dummy bitmap");
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .image_mode(ImageMode::Original)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let _clipboard = clipboard_win::Clipboard::new_attempts(10).expect("Failed to access clipboard");
+
+  clipboard_win::raw::set_bitmap_with(&bmp_bytes, DoClear).expect("Failed to write dib");
+
+  drop(_clipboard);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let Body::EncodedImage { format, bytes, .. } = body.as_ref() else {
+    panic!("Expected an encoded image, got {body:?}");
+  };
+
+  assert_eq!(*format, EncodedImageFormat::Dib);
+  assert!(!bytes.is_empty());
+}
+
+#[test]
+fn to_image() {
+  use clipboard_watcher::{ClipboardImage, EncodedImageFormat, ImageEncoding};
+
+  let temp_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  let file_path = temp_file.path().to_path_buf();
+
+  RgbImage::from_pixel(3, 2, image::Rgb([1, 2, 3]))
+    .save_with_format(&file_path, ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let png_bytes = std::fs::read(&file_path).unwrap();
+
+  // Png image
+  let png = Body::PngImage {
+    bytes: png_bytes.clone(),
+    path: None,
+  }
+  .to_image(None)
+  .unwrap();
+
+  assert_eq!(
+    png,
+    ClipboardImage::Encoded {
+      bytes: png_bytes.clone(),
+      format: ImageEncoding::Png,
+      path: None,
+    }
+  );
+  assert_eq!(png.to_png().unwrap(), png_bytes);
+
+  // Raw image
+  let raw = png.to_raw().unwrap();
+
+  assert_eq!((raw.width, raw.height), (3, 2));
+  assert_eq!(&raw.bytes[..3], &[1, 2, 3]);
+  assert_eq!(
+    Body::RawImage(raw.clone()).to_image(None),
+    Some(ClipboardImage::Raw(raw.clone()))
+  );
+
+  // Image file
+  let file = Body::FileList(vec![file_path.clone()])
+    .to_image(None)
+    .unwrap();
+
+  assert_eq!(
+    file,
+    ClipboardImage::Encoded {
+      bytes: png_bytes,
+      format: ImageEncoding::Png,
+      path: Some(file_path.clone()),
+    }
+  );
+  assert_eq!(file.path(), Some(&file_path));
+  assert_eq!(file.to_raw().unwrap().bytes, raw.bytes);
+
+  // Image file beyond the size limit
+  let file_size = std::fs::metadata(&file_path).unwrap().len();
+
+  assert!(
+    Body::FileList(vec![file_path.clone()])
+      .to_image(Some(file_size - 1))
+      .is_none()
+  );
+  assert!(
+    Body::FileList(vec![file_path.clone()])
+      .to_image(Some(file_size))
+      .is_some()
+  );
+
+  // Original encoding
+  assert_eq!(
+    Body::EncodedImage {
+      format: EncodedImageFormat::Dib,
+      bytes: vec![1, 2, 3],
+      path: None,
+    }
+    .to_image(None),
+    Some(ClipboardImage::Encoded {
+      bytes: vec![1, 2, 3],
+      format: ImageEncoding::Dib,
+      path: None,
+    })
+  );
+
+  // Multiple representations
+  assert_eq!(
+    Body::Multi(vec![
+      Body::PlainText("caption".into()),
+      Body::RawImage(raw.clone()),
+    ])
+    .to_image(None),
+    Some(ClipboardImage::Raw(raw))
+  );
+
+  // No image
+  assert!(
+    Body::PlainText("text".into())
+      .to_image(None)
+      .is_none()
+  );
+  assert!(
+    Body::FileList(vec![file_path.clone(), file_path])
+      .to_image(None)
+      .is_none()
+  );
+
+  let text_file = tempfile::Builder::new().suffix(".txt").tempfile().unwrap();
+
+  assert!(
+    Body::FileList(vec![text_file.path().to_path_buf()])
+      .to_image(None)
+      .is_none()
+  );
+}
+
+#[test]
+fn try_load_single_image() {
+  let temp_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  let file_path = temp_file.path().to_path_buf();
+
+  RgbImage::from_pixel(3, 2, image::Rgb([1, 2, 3]))
+    .save_with_format(&file_path, ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let image = Body::FileList(vec![file_path.clone()])
+    .try_load_single_image()
+    .expect("The file was not recognized as an image")
+    .expect("Failed to load the image");
+
+  assert_eq!((image.width, image.height), (3, 2));
+  assert_eq!(&image.bytes[..3], &[1, 2, 3]);
+  assert_eq!(image.path.as_deref(), Some(file_path.as_path()));
+
+  // Not a single file
+  assert!(
+    Body::FileList(vec![file_path.clone(), file_path])
+      .try_load_single_image()
+      .is_none()
+  );
+
+  // Not an image
+  let text_file = tempfile::Builder::new().suffix(".txt").tempfile().unwrap();
+
+  assert!(
+    Body::FileList(vec![text_file.path().to_path_buf()])
+      .try_load_single_image()
+      .is_none()
+  );
+
+  // Looks like an image, but it cannot be decoded
+  assert!(matches!(
+    Body::FileList(vec![text_file.path().with_extension("png")]).try_load_single_image(),
+    Some(Err(_))
+  ));
+}
+
+#[test]
+fn error_source() {
+  use std::error::Error;
+
+  let missing_file = std::env::temp_dir().join("clipboard-watcher-missing-image.png");
+
+  let error = Body::FileList(vec![missing_file])
+    .try_load_single_image()
+    .expect("The file was not recognized as an image")
+    .expect_err("The file should not exist");
+
+  assert!(
+    error
+      .to_string()
+      .starts_with("Failed to read the clipboard: Failed to read `")
+  );
+
+  let source = error
+    .source()
+    .and_then(|source| source.downcast_ref::<std::io::Error>())
+    .expect("The io error was not kept as the source");
+
+  assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn dropped_count() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  // The stream is never polled, so only the first event fits in its buffer
+  let stream = event_listener.new_stream(0);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for text in ["first", "second", "third"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  assert_eq!(stream.dropped_count(), 2);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn memory_budget() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .memory_budget(10)
+    .spawn()
+    .unwrap();
+
+  // The buffers have room for all of the items, but the budget only fits one of them.
+  // The streams share the same copy of each item, so it is only counted once.
+  let mut stream = event_listener.new_stream(8);
+  let mut other_stream = event_listener.new_stream(8);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for text in ["first!", "second", "third!"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  // The oldest items are evicted to make room for the newest one
+  for stream in [&mut stream, &mut other_stream] {
+    assert_eq!(stream.dropped_count(), 2);
+
+    let pending = stream.flush_pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(
+      *pending[0].as_ref().unwrap().as_ref(),
+      Body::PlainText("third!".to_string())
+    );
+  }
+}
+
+#[test]
+#[serial]
+fn shutdown_timeout() {
+  let event_listener = ClipboardEventListener::builder()
+    .shutdown_timeout(Duration::from_secs(1))
+    .spawn()
+    .unwrap();
+
+  let start = std::time::Instant::now();
+
+  drop(event_listener);
+
+  assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn on_change() {
+  init_logging();
+
+  let (callback_tx, callback_rx) = std::sync::mpsc::channel();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .on_change(move |result| {
+      callback_tx.send(result).unwrap();
+    })
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let test_string = "called back";
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_string.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let from_stream = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let from_callback = callback_rx
+    .recv_timeout(Duration::from_secs(2))
+    .expect("The callback was not invoked")
+    .unwrap();
+
+  assert_eq!(*from_stream, Body::PlainText(test_string.to_string()));
+  assert_eq!(from_callback, from_stream);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn spawn_channel() {
+  init_logging();
+
+  let (handle, rx) = clipboard_watcher::spawn_channel().unwrap();
+
+  std::thread::sleep(Duration::from_millis(100));
+
+  let test_string = "sent on a channel";
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_string.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = rx
+    .recv_timeout(Duration::from_secs(2))
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText(test_string.to_string()));
+
+  // Dropping the handle stops the monitor, which disconnects the channel
+  drop(handle);
+
+  assert!(rx.recv_timeout(Duration::from_secs(2)).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn once() {
+  use clipboard_watcher::ClipboardError;
+
+  init_logging();
+
+  // Nothing is copied, so it times out
+  let result = ClipboardEventListener::spawn()
+    .unwrap()
+    .once(Some(Duration::from_millis(300)))
+    .await;
+
+  assert!(matches!(result, Err(ClipboardError::Timeout)), "{result:?}");
+
+  let test_string = "captured once";
+
+  let writer = std::thread::spawn(move || {
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(test_string.as_bytes()).unwrap();
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+  });
+
+  let body = ClipboardEventListener::spawn()
+    .unwrap()
+    .once(Some(Duration::from_secs(2)))
+    .await
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText(test_string.to_string()));
+
+  writer.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn text_plain_without_charset() {
+  init_logging();
+
+  let test_string = "no charset here";
+
+  let writer = std::thread::spawn(move || {
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .arg("-target")
+      .arg("text/plain")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(test_string.as_bytes()).unwrap();
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+  });
+
+  let body = ClipboardEventListener::spawn()
+    .unwrap()
+    .once(Some(Duration::from_secs(2)))
+    .await
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText(test_string.to_string()));
+
+  writer.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn from_options() {
+  use clipboard_watcher::{ClipboardEventListenerBuilder, WatcherOptions};
+
+  init_logging();
+
+  let options = WatcherOptions {
+    interval_ms: Some(50),
+    custom_formats: vec!["application/x-from-options".to_string()],
+    ..Default::default()
+  };
+
+  let writer = std::thread::spawn(move || {
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .arg("-target")
+      .arg("application/x-from-options")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"configured").unwrap();
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+  });
+
+  let body = ClipboardEventListenerBuilder::from_options(options)
+    .spawn()
+    .unwrap()
+    .once(Some(Duration::from_secs(2)))
+    .await
+    .unwrap();
+
+  let Body::Custom { name, data } = body.as_ref() else {
+    panic!("Expected custom content, found {body:?}");
+  };
+
+  assert_eq!(name.as_ref(), "application/x-from-options");
+  assert_eq!(data, b"configured");
+
+  writer.join().unwrap();
+}
+
+#[test]
+fn raw_image_color_type() {
+  let temp_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  let file_path = temp_file.path().to_path_buf();
+
+  image::RgbaImage::from_pixel(4, 3, image::Rgba([1, 2, 3, 4]))
+    .save_with_format(&file_path, ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let image = Body::FileList(vec![file_path])
+    .try_load_single_image()
+    .expect("The file was not recognized as an image")
+    .expect("Failed to load the image");
+
+  assert_eq!(image.color_type(), ColorType::Rgb8);
+  assert_eq!(
+    image.bytes.len(),
+    (image.width * image.height) as usize * image.color_type().channels()
+  );
+
+  let rebuilt = RgbImage::from_raw(image.width, image.height, image.bytes)
+    .expect("The byte length does not match the color type");
+
+  assert_eq!(rebuilt.get_pixel(0, 0), &image::Rgb([1, 2, 3]));
+
+  // Grayscale images are expanded to rgb
+  let gray_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+
+  image::GrayAlphaImage::from_pixel(2, 2, image::LumaA([7, 128]))
+    .save_with_format(gray_file.path(), ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let gray = Body::FileList(vec![gray_file.path().to_path_buf()])
+    .try_load_single_image()
+    .expect("The file was not recognized as an image")
+    .expect("Failed to load the image");
+
+  assert_eq!(gray.color_type(), ColorType::Rgb8);
+  assert!(gray.had_alpha);
+  assert_eq!(&gray.bytes[..3], &[7, 7, 7]);
+}
+
+#[test]
+fn raw_image_new() {
+  use clipboard_watcher::RawImage;
+
+  let rgba = RawImage::new(vec![1, 2, 3, 4, 5, 6, 7, 8], 2, 1, ColorType::Rgba8)
+    .expect("The byte length matches the color type");
+
+  assert_eq!(rgba.color_type(), ColorType::Rgba8);
+  assert!(rgba.had_alpha);
+
+  // The alpha channel survives the encoding
+  let png = clipboard_watcher::ClipboardImage::Raw(rgba.clone())
+    .to_png()
+    .unwrap();
+
+  assert_eq!(
+    image::load_from_memory(&png)
+      .unwrap()
+      .into_rgba8()
+      .into_raw(),
+    rgba.bytes
+  );
+
+  let rgb = RawImage::new(vec![1, 2, 3, 4, 5, 6], 2, 1, ColorType::Rgb8)
+    .expect("The byte length matches the color type");
+
+  assert_eq!(rgb.color_type(), ColorType::Rgb8);
+  assert!(!rgb.had_alpha);
+
+  // The rgba bytes do not fit an rgb image of the same size, and vice versa
+  assert!(RawImage::new(vec![1, 2, 3, 4, 5, 6, 7, 8], 2, 1, ColorType::Rgb8).is_none());
+  assert!(RawImage::new(vec![1, 2, 3, 4, 5, 6], 2, 1, ColorType::Rgba8).is_none());
+}
+
+#[test]
+fn had_alpha() {
+  let load = |file_path: std::path::PathBuf| {
+    Body::FileList(vec![file_path])
+      .try_load_single_image()
+      .expect("The file was not recognized as an image")
+      .expect("Failed to load the image")
+  };
+
+  let rgba_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  image::RgbaImage::from_pixel(2, 2, image::Rgba([1, 2, 3, 4]))
+    .save_with_format(rgba_file.path(), ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let rgb_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+  RgbImage::from_pixel(2, 2, image::Rgb([1, 2, 3]))
+    .save_with_format(rgb_file.path(), ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  // The alpha channel is discarded, but it is still reported
+  let image = load(rgba_file.path().to_path_buf());
+  assert_eq!(image.color_type(), ColorType::Rgb8);
+  assert!(image.had_alpha);
+
+  assert!(!load(rgb_file.path().to_path_buf()).had_alpha);
+}
+
+#[test]
+fn content_eq() {
+  use clipboard_watcher::RawImage;
+
+  let image = |pixel: [u8; 4], path: &str| {
+    let mut image = RawImage::from(image::RgbaImage::from_pixel(2, 2, image::Rgba(pixel)));
+    image.path = Some(path.into());
+    Body::RawImage(image)
+  };
+
+  let first = image([10, 20, 30, 255], "/tmp/first.png");
+  let second = image([10, 20, 30, 255], "/tmp/second.png");
+  let different = image([40, 50, 60, 255], "/tmp/first.png");
+
+  assert_ne!(first, second);
+  assert!(first.content_eq(&second));
+  assert!(!first.content_eq(&different));
+
+  let png = |path: &str| Body::PngImage {
+    bytes: b"png bytes".to_vec(),
+    path: Some(path.into()),
+  };
+
+  assert!(png("/tmp/first.png").content_eq(&png("/tmp/second.png")));
+
+  // Applies to the representations of a multi body
+  assert!(
+    Body::Multi(vec![first, Body::PlainText("text".into())])
+      .content_eq(&Body::Multi(vec![second, Body::PlainText("text".into())]))
+  );
+
+  // Other kinds are compared in full
+  assert!(
+    !Body::FileList(vec!["/tmp/first.png".into()])
+      .content_eq(&Body::FileList(vec!["/tmp/second.png".into()]))
+  );
+}
+
+#[test]
+fn byte_size() {
+  use clipboard_watcher::{EncodedImageFormat, RawImage};
+
+  let text = Body::PlainText("héllo".into());
+  assert_eq!(text.byte_size(), 6);
+
+  assert_eq!(Body::Html("<p>hi</p>".into()).byte_size(), 9);
+
+  assert_eq!(
+    Body::Text {
+      mime: "text/markdown".into(),
+      content: "# hi".into(),
+    }
+    .byte_size(),
+    4
+  );
+
+  let raw = Body::RawImage(RawImage::from(image::RgbaImage::from_pixel(
+    3,
+    2,
+    image::Rgba([0, 0, 0, 255]),
+  )));
+  assert_eq!(raw.byte_size(), 3 * 2 * 4);
+
+  let png = Body::PngImage {
+    bytes: vec![0; 10],
+    path: Some("/tmp/image.png".into()),
+  };
+  assert_eq!(png.byte_size(), 10);
+
+  let encoded = Body::EncodedImage {
+    format: EncodedImageFormat::Dib,
+    bytes: vec![0; 12],
+    path: None,
+  };
+  assert_eq!(encoded.byte_size(), 12);
+
+  let files = Body::FileList(vec!["/tmp/a.txt".into(), "/tmp/bb.txt".into()]);
+  assert_eq!(files.byte_size(), 10 + 11);
+
+  let custom = Body::Custom {
+    name: "custom".into(),
+    data: vec![1, 2, 3],
+  };
+  assert_eq!(custom.byte_size(), 3);
+
+  assert_eq!(
+    Body::Color {
+      r: 1,
+      g: 2,
+      b: 3,
+      a: 4
+    }
+    .byte_size(),
+    4
+  );
+
+  assert_eq!(Body::Multi(vec![text, custom]).byte_size(), 6 + 3);
+
+  assert!(Body::PlainText(String::new()).is_empty());
+  assert!(Body::FileList(vec![]).is_empty());
+  assert!(Body::Multi(vec![Body::Html(String::new())]).is_empty());
+  assert!(!files.is_empty());
+}
+
+#[test]
+#[cfg(unix)]
+fn to_uri_list() {
+  let files = Body::FileList(vec![
+    "/tmp/with spaces.txt".into(),
+    "/tmp/café/日本.png".into(),
+    "/tmp/100%~_-.txt".into(),
+  ]);
+
+  assert_eq!(
+    files.to_uri_list().as_deref(),
+    Some(
+      "file:///tmp/with%20spaces.txt\r\nfile:///tmp/caf%C3%A9/%E6%97%A5%E6%9C%AC.png\r\nfile:///tmp/100%25~_-.txt\r\n"
+    )
+  );
+
+  let image = Body::PngImage {
+    bytes: vec![],
+    path: Some("/tmp/image.png".into()),
+  };
+
+  assert_eq!(image.to_uri_list().as_deref(), Some("file:///tmp/image.png\r\n"));
+
+  assert!(
+    Body::PngImage {
+      bytes: vec![],
+      path: None
+    }
+    .to_uri_list()
+    .is_none()
+  );
+  assert!(Body::FileList(vec![]).to_uri_list().is_none());
+  assert!(Body::PlainText("/tmp/file.txt".into()).to_uri_list().is_none());
+}
+
+#[test]
+fn best_representation() {
+  let html = Body::Html("<b>bold</b>".into());
+  let text = Body::PlainText("bold".into());
+  let png = Body::PngImage {
+    bytes: b"png bytes".to_vec(),
+    path: None,
+  };
+
+  // Regardless of the order of the representations
+  let multi = Body::Multi(vec![text.clone(), html.clone()]);
+  assert_eq!(multi.best(), &html);
+
+  let nested = Body::Multi(vec![multi.clone(), png.clone()]);
+  assert_eq!(nested.best(), &png);
+
+  assert_eq!(text.best(), &text);
+
+  assert_eq!(multi.into_representations(), vec![text.clone(), html]);
+  assert_eq!(text.clone().into_representations(), vec![text]);
+}
+
+#[test]
+fn supported_kinds() {
+  use clipboard_watcher::BodyKind;
+
+  let kinds = clipboard_watcher::supported_kinds();
+
+  assert!(kinds.contains(&BodyKind::PlainText));
+  assert!(kinds.contains(&BodyKind::RawImage));
+  assert_eq!(
+    kinds.contains(&BodyKind::EncodedImage),
+    cfg!(any(windows, target_os = "macos"))
+  );
+}
+
+#[test]
+fn premultiplied_alpha() {
+  let pixels = image::RgbaImage::from_fn(2, 1, |x, _| match x {
+    0 => image::Rgba([64, 32, 0, 128]),
+    _ => image::Rgba([10, 10, 10, 0]),
+  });
+
+  let mut image = clipboard_watcher::RawImage::from(pixels);
+
+  assert_eq!(image.color_type(), ColorType::Rgba8);
+
+  image.unmultiply_alpha();
+
+  assert_eq!(image.bytes, [128, 64, 0, 128, 0, 0, 0, 0]);
+
+  image.premultiply_alpha();
+
+  assert_eq!(image.bytes, [64, 32, 0, 128, 0, 0, 0, 0]);
+}
+
+#[test]
+fn image_similarity() {
+  use clipboard_watcher::RawImage;
+
+  let base = image::RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]));
+
+  let mut blinked = base.clone();
+  blinked.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+
+  let base_image = RawImage::from(base.clone());
+
+  assert!((base_image.similarity(&RawImage::from(base)) - 1.0).abs() < f32::EPSILON);
+
+  // A single changed pixel out of 16
+  let score = base_image.similarity(&RawImage::from(blinked));
+  assert!(score > 0.95 && score < 1.0, "{score}");
+
+  // Only the alpha channels are the same
+  let white = image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 255, 255, 255]));
+  let black = image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 0, 255]));
+  let score = RawImage::from(white).similarity(&RawImage::from(black));
+  assert!((score - 0.25).abs() < f32::EPSILON, "{score}");
+
+  let different_size = image::RgbaImage::from_pixel(2, 2, image::Rgba([100, 100, 100, 255]));
+  assert!(base_image.similarity(&RawImage::from(different_size)) < f32::EPSILON);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn adaptive_interval() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .adaptive_interval(Duration::from_millis(20), Duration::from_millis(500))
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(5);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  // The first change is detected at the slowest interval, the second one at the fastest
+  for text in ["idle", "active"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(*body, Body::PlainText(text.to_string()));
+  }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+  use clipboard_watcher::{EncodedImageFormat, RawImage};
+
+  let round_trip = |body: &Body| {
+    let json = serde_json::to_string(body).unwrap();
+
+    serde_json::from_str::<Body>(&json).unwrap()
+  };
+
+  let bodies = [
+    Body::Html("<b>bold</b>".to_string()),
+    Body::PlainText("plain".to_string()),
+    Body::RawImage(RawImage::new(vec![1, 2, 3, 4], 1, 1, ColorType::Rgba8).unwrap()),
+    Body::PngImage {
+      bytes: vec![1, 2],
+      path: Some("/tmp/image.png".into()),
+    },
+    Body::EncodedImage {
+      format: EncodedImageFormat::Dib,
+      bytes: vec![1, 2],
+      path: None,
+    },
+    Body::EncodedImage {
+      format: EncodedImageFormat::Tiff,
+      bytes: vec![3, 4],
+      path: None,
+    },
+    Body::FileList(vec!["/tmp/one.txt".into(), "/tmp/two.txt".into()]),
+    Body::Custom {
+      name: "application/x-custom".into(),
+      data: vec![5, 6],
+    },
+    Body::Text {
+      mime: "text/markdown".into(),
+      content: "**bold**".to_string(),
+    },
+    Body::Color {
+      r: 1,
+      g: 2,
+      b: 3,
+      a: 4,
+    },
+  ];
+
+  for body in &bodies {
+    assert_eq!(&round_trip(body), body);
+  }
+
+  // The html with its plain text alternative, as emitted with `include_text_with_html`
+  let multi = Body::Multi(vec![
+    Body::Html("<b>x</b>".to_string()),
+    Body::PlainText("x".to_string()),
+  ]);
+
+  assert_eq!(round_trip(&multi), multi);
+
+  // Every kind of representation can be nested
+  let nested = Body::Multi(bodies.to_vec());
+
+  assert_eq!(round_trip(&nested), nested);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn stored_body() {
+  let asset_dir = tempfile::tempdir().unwrap();
+
+  let image_file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+
+  RgbImage::from_pixel(2, 2, image::Rgb([1, 2, 3]))
+    .save_with_format(image_file.path(), ImageFormat::Png)
+    .expect("Failed to write the image");
+
+  let raw_image = Body::FileList(vec![image_file.path().to_path_buf()])
+    .try_load_single_image()
+    .unwrap()
+    .unwrap();
+
+  let body = Body::Multi(vec![
+    Body::RawImage(raw_image),
+    Body::PngImage {
+      bytes: std::fs::read(image_file.path()).unwrap(),
+      path: None,
+    },
+    Body::PlainText("caption".to_string()),
+  ]);
+
+  let stored = body.to_stored(asset_dir.path()).unwrap();
+
+  let assets = stored.assets();
+
+  assert_eq!(assets.len(), 2);
+  assert!(
+    assets
+      .iter()
+      .all(|asset| asset.starts_with(asset_dir.path()) && asset.exists())
+  );
+
+  assert_eq!(stored.load().unwrap(), body);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn stored_assets() {
+  use clipboard_watcher::StoredBody;
+
+  let asset_dir = tempfile::tempdir().unwrap();
+
+  let body = Body::Custom {
+    name: "application/x-custom".into(),
+    data: b"abc".to_vec(),
+  };
+
+  let stored = body.to_stored(asset_dir.path()).unwrap();
+
+  // The name is the SHA-256 hash of the content
+  let StoredBody::Custom { asset, .. } = &stored else {
+    panic!("Expected a custom body, got {stored:?}");
+  };
+
+  assert_eq!(
+    asset.file_name().unwrap(),
+    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad.bin"
+  );
+
+  // The same payload is written once, and no temporary files are left behind
+  let other = body.to_stored(asset_dir.path()).unwrap();
+
+  assert_eq!(other.assets(), stored.assets());
+  assert_eq!(std::fs::read_dir(asset_dir.path()).unwrap().count(), 1);
+
+  // A damaged asset is replaced, rather than reused
+  std::fs::write(asset, b"ab").unwrap();
+
+  let repaired = body.to_stored(asset_dir.path()).unwrap();
+
+  assert_eq!(repaired.load().unwrap(), body);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn read_image_png() {
+  let event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut png_bytes = Vec::new();
+
+  RgbImage::from_pixel(4, 4, image::Rgb([10, 20, 30]))
+    .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+    .expect("Failed to encode the image");
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("image/png")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().expect("Failed to open xclip stdin");
+  stdin
+    .write_all(&png_bytes)
+    .expect("Failed to write to xclip stdin");
+  drop(stdin);
+
+  let status = child.wait().expect("xclip command failed to run");
+  assert!(status.success(), "xclip command exited with an error");
+
+  let image = event_listener
+    .read_image_png()
+    .unwrap()
+    .expect("No image was found");
+
+  assert_eq!(image, png_bytes);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn image_output_raw() {
+  use clipboard_watcher::ImageOutput;
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .image_output(ImageOutput::Raw)
+    .keep_source_bytes(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut png_bytes = Vec::new();
+
+  RgbImage::from_pixel(3, 2, image::Rgb([10, 20, 30]))
+    .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+    .expect("Failed to encode the image");
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("image/png")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().expect("Failed to open xclip stdin");
+  stdin
+    .write_all(&png_bytes)
+    .expect("Failed to write to xclip stdin");
+  drop(stdin);
+
+  let status = child.wait().expect("xclip command failed to run");
+  assert!(status.success(), "xclip command exited with an error");
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let Body::RawImage(image) = body.as_ref() else {
+    panic!("Expected a raw image, found {body:?}");
+  };
+
+  assert_eq!((image.width, image.height), (3, 2));
+  assert_eq!(image.bytes, [10, 20, 30].repeat(6));
+  assert_eq!(image.source_bytes.as_deref(), Some(png_bytes.as_slice()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn flush_pending() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(5);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for text in ["first", "second"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  let pending: Vec<Body> = stream
+    .flush_pending()
+    .into_iter()
+    .map(|result| result.unwrap().as_ref().clone())
+    .collect();
+
+  assert_eq!(
+    pending,
+    [
+      Body::PlainText("first".to_string()),
+      Body::PlainText("second".to_string())
+    ]
+  );
+
+  assert!(stream.flush_pending().is_empty());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn change_ticks() {
+  init_logging();
+
+  // The content is never extracted, but the changes are still detected
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_gatekeeper(|_| false)
+    .spawn()
+    .unwrap();
+
+  let mut ticks = event_listener.change_ticks();
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"tick").unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  tokio::time::timeout(Duration::from_secs(2), ticks.next())
+    .await
+    .expect("Test timed out: Did not receive the change tick in time.")
+    .expect("The stream was closed");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn min_size() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .min_size(8)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for text in ["x", "long enough"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  // Only the second copy reaches the stream
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("long enough".to_string()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn text_custom_formats() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_text_custom_formats(["application/json"])
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("application/json")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"{\"key\": \"value\"}").unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Text {
+      mime: "application/json".into(),
+      content: r#"{"key": "value"}"#.to_string(),
+    }
+  );
+}
+
+#[cfg(target_os = "macos")]
+#[tokio::test]
+#[serial]
+async fn color() {
+  use objc2_app_kit::{NSColor, NSPasteboard};
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let pasteboard = NSPasteboard::generalPasteboard();
+  pasteboard.clearContents();
+  NSColor::colorWithSRGBRed_green_blue_alpha(1.0, 0.5, 0.0, 1.0).writeToPasteboard(&pasteboard);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Color {
+      r: 255,
+      g: 128,
+      b: 0,
+      a: 255,
+    }
+  );
+}
+
+#[cfg(target_os = "macos")]
+#[tokio::test]
+#[serial]
+async fn find_pasteboard() {
+  use clipboard_watcher::PasteboardName;
+  use objc2_app_kit::{NSPasteboard, NSPasteboardNameFind, NSPasteboardTypeString};
+  use objc2_foundation::NSString;
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .macos_pasteboard(PasteboardName::Find)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  unsafe {
+    let pasteboard = NSPasteboard::pasteboardWithName(NSPasteboardNameFind);
+
+    pasteboard.clearContents();
+    pasteboard.setString_forType(&NSString::from_str("search query"), NSPasteboardTypeString);
+  }
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("search query".into()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn limits() {
+  use clipboard_watcher::{BodyKind, Limits};
+
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_custom_formats(["application/x-limited"])
+    .limits(Limits {
+      max_size_overrides: [(BodyKind::Custom, 4)].into(),
+      ..Default::default()
+    })
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for data in [b"beyond the limit".as_slice(), b"tiny"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .arg("-target")
+      .arg("application/x-limited")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(data).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  // Only the item within the limit reaches the stream
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Custom {
+      name: "application/x-limited".into(),
+      data: b"tiny".to_vec(),
+    }
+  );
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn webp_image() {
+  init_logging();
+
+  let img = image::RgbImage::from_fn(4, 2, |x, y| image::Rgb([x as u8 * 60, y as u8 * 120, 30]));
+
+  let mut webp_bytes = Vec::new();
+  img
+    .write_to(
+      &mut std::io::Cursor::new(&mut webp_bytes),
+      image::ImageFormat::WebP,
+    )
+    .expect("Failed to encode WebP");
+
+  let mut event_listener = ClipboardEventListener::builder().spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("image/webp")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(&webp_bytes).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let Body::RawImage(image) = body.as_ref() else {
+    panic!("Expected a raw image, got {body:?}");
+  };
+
+  // WebP is encoded losslessly, so the pixels must match
+  assert_eq!((image.width, image.height), (4, 2));
+  assert_eq!(&image.bytes, img.as_raw());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn animated_gif() {
+  use image::{Delay, Frame, Rgba, RgbaImage, codecs::gif::GifEncoder};
+
+  init_logging();
+
+  let red = RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]));
+  let blue = RgbaImage::from_pixel(3, 2, Rgba([0, 0, 255, 255]));
+
+  let mut gif_bytes = Vec::new();
+  {
+    let mut encoder = GifEncoder::new(&mut gif_bytes);
+    encoder
+      .encode_frames([red.clone(), blue].map(|image| {
+        Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(100, 1))
+      }))
+      .expect("Failed to encode GIF");
+  }
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .keep_source_bytes(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("image/gif")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(&gif_bytes).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let Body::RawImage(image) = body.as_ref() else {
+    panic!("Expected a raw image, got {body:?}");
+  };
+
+  // Only the first frame is decoded, but the animation is still available
+  assert_eq!(image.frame_count, 2);
+  assert_eq!((image.width, image.height), (3, 2));
+  assert_eq!(&image.bytes, red.as_raw());
+  assert_eq!(image.source_bytes.as_deref(), Some(gif_bytes.as_slice()));
+  // The frames are fully opaque
+  assert!(!image.had_alpha);
+}
+
+#[test]
+fn size_limit_integer_types() {
+  use clipboard_watcher::BodyKind;
+
+  let legacy_limit: u32 = 1024;
+  let len: usize = 2048;
+
+  let _builder = ClipboardEventListener::builder()
+    .max_size(legacy_limit)
+    .max_size_for(BodyKind::Custom, len)
+    .max_size_for(BodyKind::PngImage, u64::MAX)
+    .min_size(1);
+}
+
+#[test]
+#[should_panic(expected = "Invalid size limit")]
+fn negative_size_limit() {
+  let _builder = ClipboardEventListener::builder().max_size(-1);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn max_size_above_u32() {
+  use clipboard_watcher::BodyKind;
+
+  init_logging();
+
+  const LIMIT: u64 = u32::MAX as u64 + 1;
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_custom_formats(["application/x-limited"])
+    .max_size(LIMIT)
+    .max_size_for(BodyKind::Custom, LIMIT * 2)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .arg("-target")
+    .arg("application/x-limited")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"within the limit").unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Custom {
+      name: "application/x-limited".into(),
+      data: b"within the limit".to_vec(),
+    }
+  );
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn dedicated_dispatcher() {
+  init_logging();
+
+  // The slow callback would block the monitor if it ran on its thread
+  let mut event_listener = ClipboardEventListener::builder()
+    .dedicated_dispatcher(true)
+    .on_change(|_| std::thread::sleep(Duration::from_secs(1)))
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(2);
+  let mut ticks = event_listener.change_ticks();
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for text in ["first", "second"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_millis(500), ticks.next())
+      .await
+      .expect("The change was not detected while the callback was running")
+      .unwrap();
+  }
+
+  // The events are still delivered in order
+  for text in ["first", "second"] {
+    let body = tokio::time::timeout(Duration::from_secs(3), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(*body, Body::PlainText(text.to_string()));
+  }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn mute() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  for (text, muted) in [("while muted", true), ("after unmuting", false)] {
+    if muted {
+      event_listener.mute();
+    } else {
+      event_listener.unmute();
+    }
+
+    assert_eq!(event_listener.is_muted(), muted);
+
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  // The event received while muted was dropped
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("after unmuting".to_string()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn emit_on_resume() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::builder()
+    .emit_on_resume(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  event_listener.mute();
+
+  for text in ["missed", "final state"] {
+    let mut child = Command::new("xclip")
+      .arg("-selection")
+      .arg("clipboard")
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("Failed to spawn xclip. Is it installed?");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(text.as_bytes()).unwrap();
+    drop(stdin);
+
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+
+  event_listener.unmute();
+
+  // Only the content at the time of the resume is emitted
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("final state".to_string()));
+
+  let result = tokio::time::timeout(Duration::from_millis(500), stream.next()).await;
+
+  assert!(result.is_err(), "Only the final state should be emitted");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn refresh() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(2);
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  // The change is missed while muted
+  event_listener.mute();
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"missed while muted").unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  tokio::time::sleep(Duration::from_millis(500)).await;
+
+  event_listener.unmute();
+  event_listener.refresh();
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("missed while muted".to_string()));
+
+  // The content is unchanged, so it is not emitted again
+  event_listener.refresh();
+
+  tokio::time::sleep(Duration::from_millis(500)).await;
+
+  assert!(stream.flush_pending().is_empty());
+}
+
+#[cfg(windows)]
+#[tokio::test]
+#[serial]
+async fn source_pid() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  // Set-Clipboard goes through the OLE clipboard, which is owned by a window of the process
+  let mut child = Command::new("powershell")
+    .arg("-NoProfile")
+    .arg("-Command")
+    .arg("Set-Clipboard -Value 'from powershell'; Start-Sleep -Seconds 1")
+    .spawn()
+    .expect("Failed to spawn powershell");
+
+  let event = tokio::time::timeout(Duration::from_secs(5), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(event.metadata.source_pid, Some(child.id()));
+  assert!(
+    event
+      .metadata
+      .source
+      .is_some_and(|source| source.eq_ignore_ascii_case("powershell.exe"))
+  );
+
+  child.wait().unwrap();
+}
+
+#[cfg(windows)]
+#[tokio::test]
+#[serial]
+async fn html_source_url() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let source_url = "https://example.com/article";
+  let fragment = "<b>attributed</b>";
+
+  let prefix = "<html><body><!--StartFragment-->";
+  let suffix = "<!--EndFragment--></body></html>";
+
+  let header = |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+    format!(
+      concat!(
+        "Version:0.9\r\n",
+        "StartHTML:{:010}\r\n",
+        "EndHTML:{:010}\r\n",
+        "StartFragment:{:010}\r\n",
+        "EndFragment:{:010}\r\n",
+        "SourceURL:{}\r\n",
+      ),
+      start_html, end_html, start_fragment, end_fragment, source_url
+    )
+  };
+
+  // The offsets always have 10 digits, so the length of the header is known in advance
+  let start_html = header(0, 0, 0, 0).len();
+  let start_fragment = start_html + prefix.len();
+  let end_fragment = start_fragment + fragment.len();
+  let end_html = end_fragment + suffix.len();
+
+  let cf_html = format!(
+    "{}{prefix}{fragment}{suffix}",
+    header(start_html, end_html, start_fragment, end_fragment)
+  );
+
+  {
+    let _clipboard =
+      clipboard_win::Clipboard::new_attempts(10).expect("Failed to get the windows clipboard");
+
+    let html =
+      clipboard_win::formats::Html::new().expect("Failed to get html format identifier in windows");
+
+    clipboard_win::raw::set(html.code(), cf_html.as_bytes()).expect("Failed to write html");
+  }
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*event.body, Body::Html(fragment.to_string()));
+  assert_eq!(event.metadata.source_url.as_deref(), Some(source_url));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn new_stream_with_current() {
+  init_logging();
+
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  // Nothing has been emitted yet
+  let mut empty_stream = event_listener.new_stream_with_current(1);
+
+  assert!(
+    tokio::time::timeout(Duration::from_millis(100), empty_stream.next())
+      .await
+      .is_err()
+  );
+
+  let mut stream = event_listener.new_stream(1);
+
+  let test_string = "current content";
+
+  let mut child = Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(test_string.as_bytes()).unwrap();
+  drop(stdin);
+
+  let status = child.wait().unwrap();
+  assert!(status.success());
+
+  tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  let mut late_stream = event_listener.new_stream_with_current(1);
+
+  let replayed = tokio::time::timeout(Duration::from_millis(100), late_stream.next())
+    .await
+    .expect("The current content was not replayed")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*replayed, Body::PlainText(test_string.to_string()));
+}
@@ -0,0 +1,1360 @@
+#![cfg(target_os = "linux")]
+#![allow(
+  clippy::ignored_unit_patterns,
+  clippy::cast_possible_truncation,
+  clippy::cast_possible_wrap
+)]
+
+use clipboard_watcher::{
+  Body, BodyKind, ClipboardContext, ClipboardError, ClipboardEventListener, FileOperation,
+  Selection,
+};
+use futures::StreamExt;
+use serial_test::serial;
+use std::{
+  sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+  },
+  thread,
+  time::{Duration, Instant},
+};
+use x11rb::{
+  connection::Connection,
+  protocol::{
+    Event,
+    xproto::{
+      AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, PropMode, Property,
+      SelectionNotifyEvent, Time, WindowClass,
+    },
+  },
+  rust_connection::RustConnection,
+  wrapper::ConnectionExt as WrapperExt,
+};
+
+fn intern(conn: &RustConnection, name: &[u8]) -> u32 {
+  conn.intern_atom(false, name).unwrap().reply().unwrap().atom
+}
+
+// Claims the clipboard and starts an INCR transfer for the text, without ever sending any chunk
+fn spawn_stalled_incr_owner() -> thread::JoinHandle<()> {
+  thread::spawn(move || {
+    let (conn, screen_num) = RustConnection::connect(None).unwrap();
+    let screen = &conn.setup().roots[screen_num];
+
+    let win_id = conn.generate_id().unwrap();
+    conn
+      .create_window(
+        x11rb::COPY_FROM_PARENT as u8,
+        win_id,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        x11rb::COPY_FROM_PARENT,
+        &Default::default(),
+      )
+      .unwrap();
+
+    let clipboard_atom = intern(&conn, b"CLIPBOARD");
+    let targets_atom = intern(&conn, b"TARGETS");
+    let utf8_atom = intern(&conn, b"UTF8_STRING");
+    let incr_atom = intern(&conn, b"INCR");
+
+    conn
+      .set_selection_owner(win_id, clipboard_atom, Time::CURRENT_TIME)
+      .unwrap();
+    conn.flush().unwrap();
+
+    while let Ok(event) = conn.wait_for_event() {
+      match event {
+        Event::SelectionRequest(req) => {
+          if req.target == targets_atom {
+            conn
+              .change_property32(
+                PropMode::REPLACE,
+                req.requestor,
+                req.property,
+                AtomEnum::ATOM,
+                &[targets_atom, utf8_atom],
+              )
+              .unwrap();
+          } else if req.target == utf8_atom {
+            // Announce a large transfer, and then never deliver it
+            conn
+              .change_property32(
+                PropMode::REPLACE,
+                req.requestor,
+                req.property,
+                incr_atom,
+                &[10_000_000],
+              )
+              .unwrap();
+          }
+
+          let notify = SelectionNotifyEvent {
+            response_type: x11rb::protocol::xproto::SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: req.time,
+            requestor: req.requestor,
+            selection: req.selection,
+            target: req.target,
+            property: req.property,
+          };
+
+          conn
+            .send_event(false, req.requestor, EventMask::NO_EVENT, notify)
+            .unwrap();
+          conn.flush().unwrap();
+        }
+        Event::SelectionClear(_) => break,
+        _ => {}
+      }
+    }
+  })
+}
+
+// Claims the clipboard and serves the data for the target through an INCR transfer, one chunk at a time
+fn spawn_incr_owner(
+  target: &'static [u8],
+  chunks: &'static [&'static [u8]],
+) -> thread::JoinHandle<()> {
+  thread::spawn(move || {
+    let (conn, screen_num) = RustConnection::connect(None).unwrap();
+    let screen = &conn.setup().roots[screen_num];
+
+    let win_id = conn.generate_id().unwrap();
+    conn
+      .create_window(
+        x11rb::COPY_FROM_PARENT as u8,
+        win_id,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        x11rb::COPY_FROM_PARENT,
+        &Default::default(),
+      )
+      .unwrap();
+
+    let clipboard_atom = intern(&conn, b"CLIPBOARD");
+    let targets_atom = intern(&conn, b"TARGETS");
+    let target_atom = intern(&conn, target);
+    let incr_atom = intern(&conn, b"INCR");
+
+    conn
+      .set_selection_owner(win_id, clipboard_atom, Time::CURRENT_TIME)
+      .unwrap();
+    conn.flush().unwrap();
+
+    // The requestor and the property of the transfer in progress, and the chunks left to send
+    let mut transfer = None;
+    let mut remaining = chunks.iter();
+
+    while let Ok(event) = conn.wait_for_event() {
+      match event {
+        Event::SelectionRequest(req) => {
+          if req.target == targets_atom {
+            conn
+              .change_property32(
+                PropMode::REPLACE,
+                req.requestor,
+                req.property,
+                AtomEnum::ATOM,
+                &[targets_atom, target_atom],
+              )
+              .unwrap();
+          } else if req.target == target_atom {
+            // The chunks are sent when the requestor deletes the property
+            conn
+              .change_window_attributes(
+                req.requestor,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+              )
+              .unwrap();
+
+            let size: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+
+            conn
+              .change_property32(
+                PropMode::REPLACE,
+                req.requestor,
+                req.property,
+                incr_atom,
+                &[size as u32],
+              )
+              .unwrap();
+
+            transfer = Some((req.requestor, req.property));
+            remaining = chunks.iter();
+          }
+
+          let notify = SelectionNotifyEvent {
+            response_type: x11rb::protocol::xproto::SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: req.time,
+            requestor: req.requestor,
+            selection: req.selection,
+            target: req.target,
+            property: req.property,
+          };
+
+          conn
+            .send_event(false, req.requestor, EventMask::NO_EVENT, notify)
+            .unwrap();
+          conn.flush().unwrap();
+        }
+        Event::PropertyNotify(ev) if ev.state == Property::DELETE => {
+          let Some((requestor, property)) = transfer else {
+            continue;
+          };
+
+          if ev.window != requestor || ev.atom != property {
+            continue;
+          }
+
+          // The transfer ends with an empty chunk
+          let chunk = remaining.next().copied().unwrap_or_default();
+
+          if chunk.is_empty() {
+            transfer = None;
+          }
+
+          conn
+            .change_property8(PropMode::REPLACE, requestor, property, target_atom, chunk)
+            .unwrap();
+          conn.flush().unwrap();
+        }
+        Event::SelectionClear(_) => break,
+        _ => {}
+      }
+    }
+  })
+}
+
+#[test]
+#[serial]
+fn drop_during_incr_transfer() {
+  let mut event_listener = ClipboardEventListener::builder().spawn().unwrap();
+
+  let _stream = event_listener.new_stream(1);
+
+  let _owner_handle = spawn_stalled_incr_owner();
+
+  // Give the observer the time to get stuck in the transfer
+  thread::sleep(Duration::from_millis(500));
+
+  let start = Instant::now();
+
+  drop(event_listener);
+
+  // The INCR timeout is 3 seconds, so this proves that the stop signal was respected
+  assert!(
+    start.elapsed() < Duration::from_secs(1),
+    "The listener took {:?} to shut down",
+    start.elapsed()
+  );
+}
+
+#[test]
+#[serial]
+fn overlapping_reads() {
+  let test_string = "the quick brown fox jumps over the lazy dog ".repeat(100);
+
+  let mut child = std::process::Command::new("xclip")
+    .arg("-selection")
+    .arg("clipboard")
+    .stdin(std::process::Stdio::piped())
+    .spawn()
+    .expect("Failed to spawn xclip. Is it installed?");
+
+  {
+    use std::io::Write;
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(test_string.as_bytes()).unwrap();
+  }
+
+  assert!(child.wait().unwrap().success());
+
+  // The observer keeps polling the clipboard while the other reads are running
+  let _listener = ClipboardEventListener::builder()
+    .interval(Duration::from_millis(10))
+    .spawn()
+    .unwrap();
+
+  let readers: Vec<_> = (0..8)
+    .map(|_| {
+      thread::spawn(|| {
+        (0..10)
+          .map(|_| clipboard_watcher::read_text().unwrap())
+          .collect::<Vec<_>>()
+      })
+    })
+    .collect();
+
+  for reader in readers {
+    for text in reader.join().unwrap() {
+      assert_eq!(text.as_deref(), Some(test_string.as_str()));
+    }
+  }
+}
+
+// Claims the clipboard from a window with the given WM_CLASS, and serves the data for each target
+fn spawn_owner(
+  class: &'static [u8],
+  targets: &'static [(&'static [u8], &'static [u8])],
+) -> thread::JoinHandle<()> {
+  spawn_selection_owner(b"CLIPBOARD", class, targets)
+}
+
+fn spawn_selection_owner(
+  selection: &'static [u8],
+  class: &'static [u8],
+  targets: &'static [(&'static [u8], &'static [u8])],
+) -> thread::JoinHandle<()> {
+  thread::spawn(move || {
+    let (conn, screen_num) = RustConnection::connect(None).unwrap();
+    let screen = &conn.setup().roots[screen_num];
+
+    let win_id = conn.generate_id().unwrap();
+    conn
+      .create_window(
+        x11rb::COPY_FROM_PARENT as u8,
+        win_id,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        x11rb::COPY_FROM_PARENT,
+        &Default::default(),
+      )
+      .unwrap();
+
+    // Instance and class, separated by null bytes
+    let wm_class = [b"instance\0".as_slice(), class, b"\0"].concat();
+
+    conn
+      .change_property8(
+        PropMode::REPLACE,
+        win_id,
+        AtomEnum::WM_CLASS,
+        AtomEnum::STRING,
+        &wm_class,
+      )
+      .unwrap();
+
+    let selection_atom = intern(&conn, selection);
+    let targets_atom = intern(&conn, b"TARGETS");
+
+    let targets: Vec<(u32, &[u8])> = targets
+      .iter()
+      .map(|(name, data)| (intern(&conn, name), *data))
+      .collect();
+
+    let mut target_atoms = vec![targets_atom];
+    target_atoms.extend(targets.iter().map(|(atom, _)| *atom));
+
+    conn
+      .set_selection_owner(win_id, selection_atom, Time::CURRENT_TIME)
+      .unwrap();
+    conn.flush().unwrap();
+
+    while let Ok(event) = conn.wait_for_event() {
+      match event {
+        Event::SelectionRequest(req) => {
+          if req.target == targets_atom {
+            conn
+              .change_property32(
+                PropMode::REPLACE,
+                req.requestor,
+                req.property,
+                AtomEnum::ATOM,
+                &target_atoms,
+              )
+              .unwrap();
+          } else if let Some((atom, data)) = targets.iter().find(|(atom, _)| *atom == req.target) {
+            conn
+              .change_property8(PropMode::REPLACE, req.requestor, req.property, *atom, data)
+              .unwrap();
+          }
+
+          let notify = SelectionNotifyEvent {
+            response_type: x11rb::protocol::xproto::SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: req.time,
+            requestor: req.requestor,
+            selection: req.selection,
+            target: req.target,
+            property: req.property,
+          };
+
+          conn
+            .send_event(false, req.requestor, EventMask::NO_EVENT, notify)
+            .unwrap();
+          conn.flush().unwrap();
+        }
+        Event::SelectionClear(_) => break,
+        _ => {}
+      }
+    }
+  })
+}
+
+#[tokio::test]
+#[serial]
+async fn source_detection() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+  let mut filtered_listener = ClipboardEventListener::builder()
+    .exclude_sources(["secretkeeper"])
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+  let mut filtered_stream = filtered_listener.new_stream(1);
+
+  let _owner_handle = spawn_owner(b"SecretKeeper", &[(b"UTF8_STRING", b"hunter2")]);
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(event.metadata.source.as_deref(), Some("SecretKeeper"));
+
+  let filtered = tokio::time::timeout(Duration::from_millis(500), filtered_stream.next()).await;
+
+  assert!(filtered.is_err(), "The excluded source was not filtered");
+}
+
+#[tokio::test]
+#[serial]
+async fn collect_custom_formats() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_custom_formats(["application/x-header", "application/x-missing", "application/x-payload"])
+    .collect_custom_formats(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let _owner_handle = spawn_owner(
+    b"Protocol",
+    &[
+      (b"application/x-payload", b"payload"),
+      (b"application/x-header", b"header"),
+    ],
+  );
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  // In the order in which the formats were given
+  assert_eq!(
+    *body,
+    Body::Multi(vec![
+      Body::Custom {
+        name: "application/x-header".into(),
+        data: b"header".to_vec(),
+      },
+      Body::Custom {
+        name: "application/x-payload".into(),
+        data: b"payload".to_vec(),
+      },
+    ])
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn custom_format_prefix() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_custom_format_prefix("application/x-archive", 6)
+    .max_size(8)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  for (data, truncated) in [
+    (b"7z\xbc\xaf\x27\x1c and the rest of the archive".as_slice(), true),
+    (b"PK\x03\x04", false),
+  ] {
+    let targets: &'static [(&[u8], &[u8])] =
+      Box::leak(Box::new([(b"application/x-archive".as_slice(), data)]));
+
+    let _owner_handle = spawn_owner(b"Archiver", targets);
+
+    let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    // The size limit does not apply to the prefixes
+    assert_eq!(
+      *event.body,
+      Body::Custom {
+        name: "application/x-archive".into(),
+        data: data[..data.len().min(6)].to_vec(),
+      }
+    );
+    assert_eq!(
+      event.metadata.truncated_formats,
+      if truncated {
+        vec!["application/x-archive".to_string()]
+      } else {
+        Vec::new()
+      }
+    );
+    assert!(!event.metadata.truncated);
+  }
+}
+
+#[tokio::test]
+#[serial]
+async fn validated_custom_format() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_validated_custom_format("application/json", |data| data.starts_with(b"{"))
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let _owner_handle = spawn_owner(b"Malformed", &[(b"application/json", b"not json")]);
+
+  let rejected = tokio::time::timeout(Duration::from_millis(500), stream.next()).await;
+
+  assert!(rejected.is_err(), "The malformed content was not skipped");
+
+  let _owner_handle = spawn_owner(b"Wellformed", &[(b"application/json", b"{}")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Custom {
+      name: "application/json".into(),
+      data: b"{}".to_vec(),
+    }
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn skip_clipboard_manager_duplicates() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .skip_clipboard_manager_duplicates(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"persisted")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("persisted".into()));
+
+  // The manager takes the ownership to preserve the same content
+  let _manager_handle = spawn_owner(b"klipper", &[(b"UTF8_STRING", b"persisted")]);
+
+  let duplicate = tokio::time::timeout(Duration::from_millis(500), stream.next()).await;
+
+  assert!(duplicate.is_err(), "The duplicate was not skipped");
+
+  // New content from the manager (such as an item restored from its history) is still emitted
+  let _manager_handle = spawn_owner(b"klipper", &[(b"UTF8_STRING", b"restored")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("restored".into()));
+}
+
+#[cfg(feature = "encoding")]
+#[tokio::test]
+#[serial]
+async fn text_charset() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let cases: [(&'static [u8], &'static [u8], &str); 3] = [
+    (b"text/plain;charset=windows-1252", b"caf\xe9 \x80", "café €"),
+    (b"text/plain;charset=Shift_JIS", b"\x93\xfa\x96\x7b", "日本"),
+    // Unknown charsets are decoded as UTF-8
+    (b"text/plain;charset=x-unknown", "naïve".as_bytes(), "naïve"),
+  ];
+
+  for (target, data, expected) in cases {
+    let targets: &'static [(&[u8], &[u8])] = Box::leak(Box::new([
+      (target, data),
+      // The generic format does not specify its charset, so the one above is preferred
+      (b"text/plain".as_slice(), b"ignored".as_slice()),
+    ]));
+
+    let _owner_handle = spawn_owner(b"LegacyApp", targets);
+
+    let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(*body, Body::PlainText(expected.into()));
+  }
+}
+
+#[tokio::test]
+#[serial]
+async fn cut_file_operation() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  // xclip can only offer a single format, so this mimics a file manager
+  let _owner_handle = spawn_owner(
+    b"Nautilus",
+    &[
+      (b"text/uri-list", b"file:///tmp/moved.txt"),
+      (b"x-special/gnome-copied-files", b"cut\nfile:///tmp/moved.txt"),
+    ],
+  );
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *event.body,
+    Body::FileList(vec![std::path::PathBuf::from("/tmp/moved.txt")])
+  );
+  assert_eq!(event.metadata.file_operation, FileOperation::Cut);
+}
+
+#[tokio::test]
+#[serial]
+async fn truncated_large_file_list() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .max_file_count(2)
+    .truncate_file_lists(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  let _owner_handle = spawn_owner(
+    b"Nautilus",
+    &[(
+      b"text/uri-list",
+      b"file:///tmp/one.txt\r\nfile:///tmp/two%20words.txt\r\nfile:///tmp/three.txt\r\nfile:///tmp/four.txt",
+    )],
+  );
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *event.body,
+    Body::FileList(vec![
+      std::path::PathBuf::from("/tmp/one.txt"),
+      std::path::PathBuf::from("/tmp/two words.txt"),
+    ])
+  );
+  assert!(event.metadata.truncated);
+}
+
+#[tokio::test]
+#[serial]
+async fn skipped_large_file_list() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .max_file_count(2)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let _large_owner_handle = spawn_owner(
+    b"Nautilus",
+    &[(
+      b"text/uri-list",
+      b"file:///tmp/one.txt\r\nfile:///tmp/two.txt\r\nfile:///tmp/three.txt",
+    )],
+  );
+
+  // Without truncation, the list that exceeds the limit is skipped entirely
+  assert!(
+    tokio::time::timeout(Duration::from_millis(500), stream.next())
+      .await
+      .is_err()
+  );
+
+  let _owner_handle = spawn_owner(
+    b"Nautilus",
+    &[(
+      b"text/uri-list",
+      b"file:///tmp/one.txt\r\nfile:///tmp/two.txt",
+    )],
+  );
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::FileList(vec![
+      std::path::PathBuf::from("/tmp/one.txt"),
+      std::path::PathBuf::from("/tmp/two.txt"),
+    ])
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn uri_list_split_across_incr_chunks() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  // The second path is cut in the middle of an escape, and the line ending between two chunks
+  let _owner_handle = spawn_incr_owner(
+    b"text/uri-list",
+    &[
+      b"file:///tmp/one.txt\r\nfile:///tmp/two%2",
+      b"0words.txt\r",
+      b"\nfile:///tmp/three.txt",
+    ],
+  );
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::FileList(vec![
+      std::path::PathBuf::from("/tmp/one.txt"),
+      std::path::PathBuf::from("/tmp/two words.txt"),
+      std::path::PathBuf::from("/tmp/three.txt"),
+    ])
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn uri_list_beyond_max_size() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .max_size_for(BodyKind::FileList, 32)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let _owner_handle = spawn_incr_owner(
+    b"text/uri-list",
+    &[
+      b"file:///tmp/one.txt\r\n",
+      b"file:///tmp/two.txt\r\n",
+      b"file:///tmp/three.txt",
+    ],
+  );
+
+  // The list is skipped once the transfer exceeds the limit
+  assert!(
+    tokio::time::timeout(Duration::from_millis(500), stream.next())
+      .await
+      .is_err()
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn uri_list_round_trip() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let files = Body::FileList(vec![
+    "/tmp/with spaces.txt".into(),
+    "/tmp/café/日本.png".into(),
+  ]);
+
+  let uri_list: &'static [u8] = files.to_uri_list().unwrap().into_bytes().leak();
+  let targets: &'static [(&[u8], &[u8])] =
+    Box::leak(Box::new([(b"text/uri-list".as_slice(), uri_list)]));
+
+  let _owner_handle = spawn_owner(b"Nautilus", targets);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, files);
+}
+
+#[tokio::test]
+#[serial]
+async fn coalesce_text_with_files() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_text_custom_formats(["text/plain"])
+    .coalesce_text_with_files(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  // The path of the single file, and the uri list of the files
+  for text in [b"/tmp/dropped.txt\n".as_slice(), b"file:///tmp/dropped.txt\r\n"] {
+    let targets: &'static [(&[u8], &[u8])] = Box::leak(Box::new([
+      (b"text/plain".as_slice(), text),
+      (b"text/uri-list".as_slice(), b"file:///tmp/dropped.txt".as_slice()),
+    ]));
+
+    let _owner_handle = spawn_owner(b"Nautilus", targets);
+
+    let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(*body, Body::FileList(vec!["/tmp/dropped.txt".into()]));
+  }
+
+  // Any other text is still emitted
+  let _owner_handle = spawn_owner(
+    b"Nautilus",
+    &[
+      (b"text/plain", b"/tmp/other.txt"),
+      (b"text/uri-list", b"file:///tmp/dropped.txt"),
+    ],
+  );
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Text {
+      mime: "text/plain".into(),
+      content: "/tmp/other.txt".into(),
+    }
+  );
+
+  // The text without a file list is emitted as well, even if it is the path of the last files
+  let _owner_handle = spawn_owner(b"Editor", &[(b"text/plain", b"/tmp/dropped.txt")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *body,
+    Body::Text {
+      mime: "text/plain".into(),
+      content: "/tmp/dropped.txt".into(),
+    }
+  );
+}
+
+#[test]
+#[serial]
+fn wait_for_format() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_custom_formats(["application/x-awaited"])
+    .spawn()
+    .unwrap();
+
+  let owners = thread::spawn(|| {
+    thread::sleep(Duration::from_millis(200));
+    let _ = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"ignored")]);
+
+    thread::sleep(Duration::from_millis(500));
+    let _ = spawn_owner(b"Target", &[(b"application/x-awaited", b"found")]);
+  });
+
+  let body = event_listener
+    .wait_for_format("application/x-awaited", Some(Duration::from_secs(3)))
+    .unwrap()
+    .expect("Test timed out: Did not receive the format in time.");
+
+  assert_eq!(
+    *body,
+    Body::Custom {
+      name: "application/x-awaited".into(),
+      data: b"found".to_vec(),
+    }
+  );
+
+  owners.join().unwrap();
+
+  let timed_out = event_listener
+    .wait_for_format("application/x-awaited", Some(Duration::from_millis(200)))
+    .unwrap();
+
+  assert!(timed_out.is_none());
+}
+
+#[test]
+#[serial]
+fn read_latency() {
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"fast")]);
+
+  thread::sleep(Duration::from_millis(200));
+
+  const READS: u32 = 20;
+
+  let start = Instant::now();
+
+  for _ in 0..READS {
+    assert_eq!(clipboard_watcher::read_text().unwrap().as_deref(), Some("fast"));
+  }
+
+  let average = start.elapsed() / READS;
+
+  // Each read takes two round trips (for the targets and for the data).
+  // With a fixed 20ms sleep between the polls, this was always above 40ms.
+  assert!(average < Duration::from_millis(20), "The average read took {average:?}");
+}
+
+#[tokio::test]
+#[serial]
+async fn event_driven() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .interval(Duration::from_secs(1))
+    .event_driven(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  // Let the monitor start waiting for the events
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let start = Instant::now();
+
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"instant")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("instant".into()));
+
+  // When sleeping for the interval, the change could be noticed up to a second later
+  let latency = start.elapsed();
+  assert!(latency < Duration::from_millis(500), "The event took {latency:?}");
+
+  // The stop signal is still checked within the interval
+  let start = Instant::now();
+  drop(event_listener);
+
+  let shutdown = start.elapsed();
+  assert!(shutdown < Duration::from_millis(1500), "The shutdown took {shutdown:?}");
+}
+
+#[tokio::test]
+#[serial]
+async fn warmup() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .interval(Duration::from_secs(10))
+    .warmup(Duration::from_secs(2), Duration::from_millis(20))
+    .spawn()
+    .unwrap();
+
+  // Let the monitor start its slow sleep
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let mut stream = event_listener.new_stream(1);
+
+  // The subscription interrupts the current sleep, so the faster polling starts right away
+  tokio::time::sleep(Duration::from_millis(100)).await;
+
+  let start = Instant::now();
+
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"warm")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("warm".into()));
+
+  let latency = start.elapsed();
+  assert!(latency < Duration::from_millis(500), "The event took {latency:?}");
+}
+
+#[test]
+#[serial]
+fn request_save_targets() {
+  let event_listener = ClipboardEventListener::spawn().unwrap();
+
+  assert!(
+    event_listener.request_save_targets().is_err(),
+    "The request succeeded without a clipboard manager"
+  );
+
+  // The clipboard manager confirms the request with an empty reply
+  let _manager_handle =
+    spawn_selection_owner(b"CLIPBOARD_MANAGER", b"Manager", &[(b"SAVE_TARGETS", b"")]);
+
+  thread::sleep(Duration::from_millis(200));
+
+  event_listener.request_save_targets().unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn skip_unchanged_targets() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .skip_unchanged_targets(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let owners: [&'static [(&'static [u8], &'static [u8])]; 3] = [
+    &[(b"UTF8_STRING", b"first")],
+    // Same targets, so this is skipped
+    &[(b"UTF8_STRING", b"second")],
+    &[(b"text/plain;charset=utf-8", b"third")],
+  ];
+
+  let mut received = Vec::new();
+
+  for targets in owners {
+    let _owner_handle = spawn_owner(b"Editor", targets);
+
+    if let Ok(item) = tokio::time::timeout(Duration::from_millis(500), stream.next()).await {
+      received.push(item.unwrap().unwrap());
+    }
+  }
+
+  assert_eq!(
+    received
+      .iter()
+      .map(|body| body.as_ref().clone())
+      .collect::<Vec<_>>(),
+    [
+      Body::PlainText("first".to_string()),
+      Body::PlainText("third".to_string()),
+    ]
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn skip_unchanged_targets_with_refresh() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .skip_unchanged_targets(true)
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(1);
+
+  let _first_owner = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"first")]);
+
+  let first = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(first.as_ref(), &Body::PlainText("first".to_string()));
+
+  // Same targets, so the change itself is skipped
+  let _second_owner = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"second")]);
+
+  assert!(
+    tokio::time::timeout(Duration::from_millis(500), stream.next())
+      .await
+      .is_err()
+  );
+
+  event_listener.refresh();
+
+  let refreshed = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: The refresh did not emit the new content.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(refreshed.as_ref(), &Body::PlainText("second".to_string()));
+
+  // The regular changes are still compared with the previous targets
+  let _third_owner = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"third")]);
+
+  assert!(
+    tokio::time::timeout(Duration::from_millis(500), stream.next())
+      .await
+      .is_err()
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn html_source_url() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  let _owner_handle = spawn_owner(
+    b"Chromium",
+    &[
+      (b"text/html", b"<b>attributed</b>"),
+      (b"chromium/x-source-url", b"https://example.com/article"),
+    ],
+  );
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*event.body, Body::Html("<b>attributed</b>".to_string()));
+  assert_eq!(
+    event.metadata.source_url.as_deref(),
+    Some("https://example.com/article")
+  );
+}
+
+#[tokio::test]
+#[serial]
+async fn image_with_path_mode() {
+  use clipboard_watcher::ImagePathMode;
+  use std::path::PathBuf;
+
+  for mode in [ImagePathMode::PreferBytes, ImagePathMode::PreferPath] {
+    let mut event_listener = ClipboardEventListener::builder()
+      .image_with_path_mode(mode)
+      .spawn()
+      .unwrap();
+
+    let mut stream = event_listener.new_stream(1);
+
+    // The png bytes are not decoded, so they do not need to be valid
+    let _owner_handle = spawn_owner(
+      b"ImageViewer",
+      &[
+        (b"image/png", b"png bytes"),
+        (b"text/uri-list", b"file:///tmp/copied.png"),
+      ],
+    );
+
+    let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: Did not receive clipboard update in time.")
+      .unwrap()
+      .unwrap();
+
+    let expected = match mode {
+      ImagePathMode::PreferBytes => Body::PngImage {
+        bytes: b"png bytes".to_vec(),
+        path: Some(PathBuf::from("/tmp/copied.png")),
+      },
+      ImagePathMode::PreferPath => Body::FileList(vec![PathBuf::from("/tmp/copied.png")]),
+    };
+
+    assert_eq!(*body, expected);
+  }
+}
+
+#[tokio::test]
+#[serial]
+async fn back_to_back_selection_changes() {
+  let mut event_listener = ClipboardEventListener::builder()
+    .selections([Selection::Clipboard, Selection::Primary])
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.new_stream(2).with_metadata();
+
+  // The primary selection changes while the content of the clipboard is being transferred
+  let _incr_owner_handle = spawn_incr_owner(b"UTF8_STRING", &[b"from the ", b"clipboard"]);
+  let _primary_owner_handle =
+    spawn_selection_owner(b"PRIMARY", b"Editor", &[(b"UTF8_STRING", b"from primary")]);
+
+  let mut received = Vec::new();
+
+  for _ in 0..2 {
+    let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+      .await
+      .expect("Test timed out: A selection change was lost.")
+      .unwrap()
+      .unwrap();
+
+    received.push((event.metadata.selection.clone(), (*event.body).clone()));
+  }
+
+  assert!(received.contains(&(
+    Some(Selection::Clipboard),
+    Body::PlainText("from the clipboard".to_string())
+  )));
+  assert!(received.contains(&(
+    Some(Selection::Primary),
+    Body::PlainText("from primary".to_string())
+  )));
+}
+
+#[tokio::test]
+#[serial]
+async fn incremental_metadata() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut stream = event_listener.new_stream(1).with_metadata();
+
+  let _incr_owner_handle = spawn_incr_owner(b"UTF8_STRING", &[b"sent in ", b"two chunks"]);
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(
+    *event.body,
+    Body::PlainText("sent in two chunks".to_string())
+  );
+  assert!(event.metadata.incremental);
+
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"sent at once")]);
+
+  let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*event.body, Body::PlainText("sent at once".to_string()));
+  assert!(!event.metadata.incremental);
+}
+
+#[tokio::test]
+#[serial]
+async fn resilient_stream() {
+  let failed = Arc::new(AtomicBool::new(false));
+
+  // The first check panics, which stops the observer
+  let mut event_listener = ClipboardEventListener::builder()
+    .with_gatekeeper({
+      let failed = failed.clone();
+
+      move |_: ClipboardContext| {
+        assert!(
+          failed.swap(true, Ordering::Relaxed),
+          "The gatekeeper failed"
+        );
+        true
+      }
+    })
+    .spawn()
+    .unwrap();
+
+  let mut stream = event_listener.resilient_stream(4);
+
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"lost")]);
+
+  let result = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive the failure in time.")
+    .unwrap();
+
+  assert!(
+    matches!(result, Err(ClipboardError::MonitorFailed { .. })),
+    "{result:?}"
+  );
+
+  // The stream stays open, and the observer is restarted after the backoff
+  tokio::time::sleep(Duration::from_millis(800)).await;
+
+  let _owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"after the restart")]);
+
+  let body = tokio::time::timeout(Duration::from_secs(2), stream.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(*body, Body::PlainText("after the restart".to_string()));
+}
+
+#[tokio::test]
+#[serial]
+async fn file_stream() {
+  let mut event_listener = ClipboardEventListener::spawn().unwrap();
+
+  let mut files = std::pin::pin!(event_listener.file_stream(1));
+
+  // The content without files is skipped
+  let _text_owner_handle = spawn_owner(b"Editor", &[(b"UTF8_STRING", b"not a file")]);
+
+  tokio::time::sleep(Duration::from_millis(300)).await;
+
+  let _owner_handle = spawn_owner(
+    b"Nautilus",
+    &[(
+      b"text/uri-list",
+      b"file:///tmp/one.txt\r\nfile:///tmp/two.txt",
+    )],
+  );
+
+  let paths = tokio::time::timeout(Duration::from_secs(2), files.next())
+    .await
+    .expect("Test timed out: Did not receive clipboard update in time.")
+    .unwrap();
+
+  assert_eq!(
+    paths,
+    vec![
+      std::path::PathBuf::from("/tmp/one.txt"),
+      std::path::PathBuf::from("/tmp/two.txt"),
+    ]
+  );
+}